@@ -0,0 +1,36 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// Behaviour hints that let clients classify a tool and pick an approval policy;
+/// unset hints are left to the client's defaults
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolAnnotations {
+    /// The tool doesn't modify its environment
+    pub read_only: Option<bool>,
+    /// The tool may perform destructive updates
+    pub destructive: Option<bool>,
+    /// Repeating a call with the same arguments has no additional effect
+    pub idempotent: Option<bool>,
+    /// The tool interacts with external entities, such as the web
+    pub open_world: Option<bool>,
+}
+
+impl ToolAnnotations {
+    pub fn to_json(&self) -> Value {
+        let hints = [
+            ("readOnlyHint", self.read_only),
+            ("destructiveHint", self.destructive),
+            ("idempotentHint", self.idempotent),
+            ("openWorldHint", self.open_world),
+        ];
+
+        let mut annotations = Map::new();
+        for (key, hint) in hints {
+            if let Some(hint) = hint {
+                annotations.insert(key.into(), Value::Bool(hint));
+            }
+        }
+        Value::Object(annotations)
+    }
+}
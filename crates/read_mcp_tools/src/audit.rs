@@ -0,0 +1,158 @@
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    future::Future,
+    io::Write,
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use http_client::http::HeaderMap;
+use serde_json::{Value, json};
+
+const DEFAULT_RECENT_CAPACITY: usize = 100;
+
+tokio::task_local! {
+    static CALLER: AuditCaller;
+}
+
+/// The session and tool a fetch is made on behalf of
+#[derive(Debug, Clone)]
+pub struct AuditCaller {
+    pub session: String,
+    pub tool: String,
+}
+
+impl AuditCaller {
+    /// Attribute the fetches `future` makes to this caller
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CALLER.scope(self, future).await
+    }
+
+    fn current() -> Option<AuditCaller> {
+        CALLER.try_with(AuditCaller::clone).ok()
+    }
+}
+
+/// One outbound fetch, successful or not
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    /// Unknown for fetches made outside a tool call
+    pub caller: Option<AuditCaller>,
+    pub url: String,
+    /// Missing when no response was received
+    pub status: Option<u16>,
+    pub bytes: usize,
+    /// Whether a proxy or CDN answered from its cache
+    pub cache_hit: bool,
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "timestamp": self.timestamp.to_rfc3339(),
+            "session": self.caller.as_ref().map(|caller| &caller.session),
+            "tool": self.caller.as_ref().map(|caller| &caller.tool),
+            "url": self.url,
+            "status": self.status,
+            "bytes": self.bytes,
+            "cache_hit": self.cache_hit,
+            "error": self.error,
+        })
+    }
+}
+
+/// Append-only record of every fetch the server makes, written as JSON lines,
+/// with the most recent entries kept in memory
+pub struct AuditLog {
+    file: Option<Mutex<File>>,
+    recent: Mutex<VecDeque<AuditEntry>>,
+    capacity: usize,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_RECENT_CAPACITY)
+    }
+}
+
+impl AuditLog {
+    pub fn with_capacity(capacity: usize) -> Self {
+        AuditLog {
+            file: None,
+            recent: Mutex::new(VecDeque::new()),
+            capacity,
+        }
+    }
+
+    /// Append every entry to the file at `path`, creating it if needed
+    pub fn with_file(mut self, path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+        self.file = Some(Mutex::new(file));
+        Ok(self)
+    }
+
+    /// Record a fetch of `url`, attributed to the caller in scope
+    pub(crate) fn record(
+        &self,
+        url: &str,
+        response: Result<(u16, usize, &HeaderMap), &anyhow::Error>,
+    ) {
+        let (status, bytes, cache_hit, error) = match response {
+            Ok((status, bytes, headers)) => (Some(status), bytes, served_from_cache(headers), None),
+            Err(e) => (None, 0, false, Some(e.to_string())),
+        };
+        let entry = AuditEntry {
+            timestamp: Utc::now(),
+            caller: AuditCaller::current(),
+            url: url.to_string(),
+            status,
+            bytes,
+            cache_hit,
+            error,
+        };
+
+        if let Some(file) = &self.file {
+            let mut file = file.lock().unwrap();
+            if let Err(e) = writeln!(file, "{}", entry.to_json()) {
+                eprintln!("Error writing audit log: {}", e);
+            }
+        }
+
+        let mut recent = self.recent.lock().unwrap();
+        recent.push_front(entry);
+        recent.truncate(self.capacity);
+    }
+
+    /// The most recent entries, newest first
+    pub fn recent(&self) -> Vec<AuditEntry> {
+        self.recent.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Whether a cache in front of the origin answered, going by the headers CDNs
+/// and caching proxies add
+fn served_from_cache(headers: &HeaderMap) -> bool {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    [
+        "x-cache",
+        "cf-cache-status",
+        "x-cache-status",
+        "x-proxy-cache",
+    ]
+    .into_iter()
+    .filter_map(header)
+    .any(|value| value.to_ascii_uppercase().contains("HIT"))
+        || header("age")
+            .and_then(|age| age.trim().parse::<u64>().ok())
+            .is_some_and(|age| age > 0)
+}
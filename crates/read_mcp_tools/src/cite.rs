@@ -0,0 +1,572 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use context_server::{Tool, ToolContent, ToolExecutor};
+use serde_json::{Value, json};
+
+use crate::{
+    Extraction, ReadUrlTool, StructuredToolExecutor, ToolAnnotations, ToolOutput, extract_url,
+    optional_string,
+};
+
+const MLA_MONTHS: [&str; 12] = [
+    "Jan.", "Feb.", "Mar.", "Apr.", "May", "June", "July", "Aug.", "Sept.", "Oct.", "Nov.", "Dec.",
+];
+
+/// Formats citations for web pages from the metadata read_url extracts
+pub struct CiteUrlTool {
+    read_url: Arc<ReadUrlTool>,
+}
+
+/// What a citation is built from
+struct Source {
+    url: String,
+    title: Option<String>,
+    authors: Vec<Name>,
+    site_name: Option<String>,
+    published: Option<NaiveDate>,
+    accessed: NaiveDate,
+}
+
+/// An author, split for styles that invert names; organizations only have a family name
+struct Name {
+    given: Vec<String>,
+    family: String,
+}
+
+impl CiteUrlTool {
+    pub const ANNOTATIONS: ToolAnnotations = ToolAnnotations {
+        read_only: Some(true),
+        destructive: Some(false),
+        idempotent: Some(true),
+        open_world: Some(true),
+    };
+
+    pub fn new(read_url: Arc<ReadUrlTool>) -> Self {
+        CiteUrlTool { read_url }
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for CiteUrlTool {
+    async fn execute(&self, arguments: Option<Value>) -> Result<Vec<ToolContent>> {
+        Ok(self.execute_structured(arguments).await?.content)
+    }
+
+    fn to_tool(&self) -> Tool {
+        Tool {
+            name: "cite_url".into(),
+            description: Some(indoc::formatdoc! {"
+                    Fetches a web page and formats a citation for it in APA, MLA or BibTeX style, from the title, authors, site name and publication date found on the page, with today as the access date.
+
+                    Metadata the page doesn't provide is left out, so check the citation before publishing it.
+                "}),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "format": "uri",
+                        "description": "The URL of the page to cite."
+                    },
+                    "style": {
+                        "type": "string",
+                        "enum": ["apa", "mla", "bibtex"],
+                        "default": "apa",
+                        "description": "The citation style: APA 7th edition, MLA 9th edition, or a biblatex @online entry."
+                    }
+                },
+                "required": ["url"]
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl StructuredToolExecutor for CiteUrlTool {
+    fn output_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string" },
+                "style": { "type": "string", "enum": ["apa", "mla", "bibtex"] },
+                "citation": { "type": "string" },
+                "title": { "type": ["string", "null"] },
+                "authors": { "type": "array", "items": { "type": "string" } },
+                "site_name": { "type": ["string", "null"] },
+                "date_published": { "type": ["string", "null"], "format": "date" },
+                "date_accessed": { "type": "string", "format": "date" }
+            },
+            "required": [
+                "url",
+                "style",
+                "citation",
+                "title",
+                "authors",
+                "site_name",
+                "date_published",
+                "date_accessed"
+            ]
+        })
+    }
+
+    async fn execute_structured(&self, arguments: Option<Value>) -> Result<ToolOutput> {
+        let style = optional_string(&arguments, "style")?.unwrap_or_else(|| "apa".to_string());
+        let format: fn(&Source) -> String = match style.as_str() {
+            "apa" => apa,
+            "mla" => mla,
+            "bibtex" => bibtex,
+            other => return Err(anyhow!("unknown citation style \"{}\"", other)),
+        };
+        let url = extract_url(arguments)?;
+
+        let (extraction, fetched_at) = self.read_url.read(&url, false).await?;
+        let source = Source::new(&url, &extraction, fetched_at);
+        let citation = format(&source);
+
+        Ok(ToolOutput {
+            content: vec![ToolContent::Text {
+                text: citation.clone(),
+            }],
+            structured_content: Some(json!({
+                "url": url,
+                "style": style,
+                "citation": citation,
+                "title": source.title,
+                "authors": source.authors.iter().map(Name::full).collect::<Vec<_>>(),
+                "site_name": source.site_name,
+                "date_published": source.published.map(|date| date.to_string()),
+                "date_accessed": source.accessed.to_string(),
+            })),
+        })
+    }
+}
+
+impl Source {
+    fn new(url: &str, extraction: &Extraction, fetched_at: DateTime<Utc>) -> Self {
+        let metadata = &extraction.metadata;
+        let names = if metadata.authors.is_empty() {
+            metadata
+                .author
+                .iter()
+                .flat_map(|byline| split_byline(byline))
+                .collect()
+        } else {
+            metadata
+                .authors
+                .iter()
+                .map(|author| author.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        Source {
+            url: url.to_string(),
+            title: metadata
+                .title
+                .clone()
+                .filter(|title| !title.trim().is_empty()),
+            authors: names.iter().filter_map(|name| Name::parse(name)).collect(),
+            site_name: metadata.site_name.clone(),
+            published: metadata.date_published.map(|date| date.date_naive()),
+            accessed: fetched_at.date_naive(),
+        }
+    }
+}
+
+impl Name {
+    fn parse(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        // "Family, Given" is already inverted
+        if let Some((family, given)) = name.split_once(',') {
+            return Some(Name {
+                given: given.split_whitespace().map(str::to_string).collect(),
+                family: family.trim().to_string(),
+            });
+        }
+
+        let mut words = name
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let family = words.pop()?;
+        Some(Name {
+            given: words,
+            family,
+        })
+    }
+
+    fn full(&self) -> String {
+        let mut words = self.given.clone();
+        words.push(self.family.clone());
+        words.join(" ")
+    }
+
+    /// "Family, Given"
+    fn inverted(&self) -> String {
+        if self.given.is_empty() {
+            self.family.clone()
+        } else {
+            format!("{}, {}", self.family, self.given.join(" "))
+        }
+    }
+
+    /// "Family, G. M."
+    fn with_initials(&self) -> String {
+        if self.given.is_empty() {
+            return self.family.clone();
+        }
+        let initials = self
+            .given
+            .iter()
+            .filter_map(|given| given.chars().next())
+            .map(|initial| format!("{}.", initial))
+            .collect::<Vec<_>>();
+        format!("{}, {}", self.family, initials.join(" "))
+    }
+}
+
+/// Bylines such as "By Jane Doe and John Smith" name their authors in prose
+fn split_byline(byline: &str) -> Vec<String> {
+    let byline = byline.trim();
+    let byline = byline
+        .strip_prefix("By ")
+        .or_else(|| byline.strip_prefix("by "))
+        .unwrap_or(byline);
+    // Commas only separate authors when the byline isn't a single inverted name
+    let inverted = byline.matches(',').count() == 1
+        && !byline.contains(" and ")
+        && byline
+            .split_once(',')
+            .is_some_and(|(family, _)| !family.trim().contains(' '));
+    let separators: &[&str] = if inverted {
+        &[" and ", " & "]
+    } else {
+        &[",", " and ", " & "]
+    };
+
+    let mut names = vec![byline.to_string()];
+    for separator in separators {
+        names = names
+            .iter()
+            .flat_map(|name| {
+                name.split(separator)
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+    }
+    names
+        .into_iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// APA 7th edition: Family, G. (2024, March 1). *Title*. Site. URL
+fn apa(source: &Source) -> String {
+    let date = match source.published {
+        Some(date) => format!("({}, {})", date.year(), date.format("%B %-d")),
+        None => "(n.d.)".to_string(),
+    };
+    let title = source.title.as_deref().map(|title| {
+        let title = title.trim();
+        if title.ends_with(['?', '!']) {
+            format!("*{}*", title)
+        } else {
+            format!("*{}*.", title.trim_end_matches('.'))
+        }
+    });
+
+    let mut parts = Vec::new();
+    match source.authors.as_slice() {
+        [] => {
+            // Without authors the title moves into their place
+            parts.extend(title.clone());
+            parts.push(format!("{}.", date));
+        }
+        authors => {
+            let names = authors.iter().map(Name::with_initials).collect::<Vec<_>>();
+            let names = match names.as_slice() {
+                [only] => only.clone(),
+                [rest @ .., last] => format!("{}, & {}", rest.join(", "), last),
+                [] => unreachable!(),
+            };
+            parts.push(ending_with_period(&names));
+            parts.push(format!("{}.", date));
+            parts.extend(title.clone());
+        }
+    }
+    // The site is left out when it is also the author
+    if let Some(site_name) = &source.site_name
+        && !source
+            .authors
+            .iter()
+            .any(|author| author.full() == *site_name)
+    {
+        parts.push(ending_with_period(site_name));
+    }
+    if source.published.is_none() {
+        parts.push(format!(
+            "Retrieved {}, from {}",
+            source.accessed.format("%B %-d, %Y"),
+            source.url
+        ));
+    } else {
+        parts.push(source.url.clone());
+    }
+    parts.join(" ")
+}
+
+/// MLA 9th edition: Family, Given. "Title." *Site*, 1 Mar. 2024, example.com/page. Accessed 16 Oct. 2026.
+fn mla(source: &Source) -> String {
+    let mut parts = Vec::new();
+    match source.authors.as_slice() {
+        [] => {}
+        [only] => parts.push(ending_with_period(&only.inverted())),
+        [first, second] => parts.push(ending_with_period(&format!(
+            "{}, and {}",
+            first.inverted(),
+            second.full()
+        ))),
+        [first, ..] => parts.push(format!("{}, et al.", first.inverted())),
+    }
+    if let Some(title) = &source.title {
+        parts.push(format!("\u{201c}{}\u{201d}", ending_with_period(title)));
+    }
+
+    let mut container = Vec::new();
+    if let Some(site_name) = &source.site_name {
+        container.push(format!("*{}*", site_name));
+    }
+    if let Some(date) = source.published {
+        container.push(mla_date(date));
+    }
+    let url = source
+        .url
+        .strip_prefix("https://")
+        .or_else(|| source.url.strip_prefix("http://"))
+        .unwrap_or(&source.url);
+    container.push(format!("{}.", url));
+    parts.push(container.join(", "));
+
+    parts.push(format!("Accessed {}.", mla_date(source.accessed)));
+    parts.join(" ")
+}
+
+/// A biblatex `@online` entry
+fn bibtex(source: &Source) -> String {
+    let mut fields = Vec::new();
+    if !source.authors.is_empty() {
+        let authors = source
+            .authors
+            .iter()
+            .map(|author| {
+                if author.given.is_empty() {
+                    // Braces keep organizations from being split into names
+                    format!("{{{}}}", escape_bibtex(&author.family))
+                } else {
+                    escape_bibtex(&author.inverted())
+                }
+            })
+            .collect::<Vec<_>>();
+        fields.push(("author", authors.join(" and ")));
+    }
+    if let Some(title) = &source.title {
+        fields.push(("title", format!("{{{}}}", escape_bibtex(title))));
+    }
+    if let Some(site_name) = &source.site_name {
+        fields.push(("organization", escape_bibtex(site_name)));
+    }
+    if let Some(date) = source.published {
+        fields.push(("date", date.to_string()));
+    }
+    fields.push(("url", source.url.clone()));
+    fields.push(("urldate", source.accessed.to_string()));
+
+    let mut entry = format!("@online{{{},\n", citation_key(source));
+    for (name, value) in fields {
+        entry.push_str(&format!("  {} = {{{}}},\n", name, value));
+    }
+    entry.push('}');
+    entry
+}
+
+/// First author's family name, year and first title word, e.g. `doe2024rust`
+fn citation_key(source: &Source) -> String {
+    let ascii = |text: &str| {
+        text.chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_lowercase()
+    };
+    let author = source
+        .authors
+        .first()
+        .map(|author| ascii(&author.family))
+        .or_else(|| source.site_name.as_deref().map(ascii))
+        .unwrap_or_default();
+    let year = source
+        .published
+        .map(|date| date.year().to_string())
+        .unwrap_or_default();
+    let word = source
+        .title
+        .as_deref()
+        .and_then(|title| {
+            title
+                .split_whitespace()
+                .map(ascii)
+                .find(|word| word.len() > 3)
+        })
+        .unwrap_or_default();
+
+    let key = format!("{}{}{}", author, year, word);
+    if key.is_empty() {
+        "online".to_string()
+    } else {
+        key
+    }
+}
+
+fn mla_date(date: NaiveDate) -> String {
+    format!(
+        "{} {} {}",
+        date.day(),
+        MLA_MONTHS[date.month0() as usize],
+        date.year()
+    )
+}
+
+fn ending_with_period(text: &str) -> String {
+    let text = text.trim();
+    if text.ends_with(['.', '?', '!']) {
+        text.to_string()
+    } else {
+        format!("{}.", text)
+    }
+}
+
+fn escape_bibtex(text: &str) -> String {
+    text.replace('\\', "\\textbackslash{}")
+        .replace(['{', '}'], "")
+        .replace('&', "\\&")
+        .replace('%', "\\%")
+        .replace('$', "\\$")
+        .replace('#', "\\#")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(authors: &[&str], title: &str, published: Option<NaiveDate>) -> Source {
+        Source {
+            url: "https://example.com/posts/rust".to_string(),
+            title: Some(title.to_string()),
+            authors: authors
+                .iter()
+                .filter_map(|name| Name::parse(name))
+                .collect(),
+            site_name: Some("Example Blog".to_string()),
+            published,
+            accessed: NaiveDate::from_ymd_opt(2026, 10, 16).unwrap(),
+        }
+    }
+
+    fn march_first() -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(2024, 3, 1)
+    }
+
+    #[test]
+    fn test_split_byline() {
+        assert_eq!(
+            split_byline("By Jane Doe and John Smith"),
+            vec!["Jane Doe", "John Smith"]
+        );
+        assert_eq!(
+            split_byline("Jane Doe, John Smith & Ann Lee"),
+            vec!["Jane Doe", "John Smith", "Ann Lee"]
+        );
+        assert_eq!(split_byline("Doe, Jane"), vec!["Doe, Jane"]);
+
+        let name = Name::parse("Doe, Jane Mary").unwrap();
+        assert_eq!(name.full(), "Jane Mary Doe");
+        assert_eq!(name.with_initials(), "Doe, J. M.");
+        assert!(Name::parse("  ").is_none());
+    }
+
+    #[test]
+    fn test_apa() {
+        assert_eq!(
+            apa(&source(
+                &["Jane Mary Doe", "John Smith"],
+                "Learning Rust",
+                march_first()
+            )),
+            "Doe, J. M., & Smith, J. (2024, March 1). *Learning Rust*. Example Blog. https://example.com/posts/rust"
+        );
+        // Without authors the title leads, and without a date the access date is given
+        assert_eq!(
+            apa(&source(&[], "Why Rust?", None)),
+            "*Why Rust?* (n.d.). Example Blog. Retrieved October 16, 2026, from https://example.com/posts/rust"
+        );
+        // A site that is also the author is named once
+        assert_eq!(
+            apa(&source(&["Example Blog"], "Learning Rust", march_first())),
+            "Blog, E. (2024, March 1). *Learning Rust*. https://example.com/posts/rust"
+        );
+    }
+
+    #[test]
+    fn test_mla() {
+        assert_eq!(
+            mla(&source(
+                &["Jane Mary Doe", "John Smith"],
+                "Learning Rust",
+                march_first()
+            )),
+            "Doe, Jane Mary, and John Smith. \u{201c}Learning Rust.\u{201d} *Example Blog*, 1 Mar. 2024, example.com/posts/rust. Accessed 16 Oct. 2026."
+        );
+        assert_eq!(
+            mla(&source(
+                &["Jane Doe", "John Smith", "Ann Lee"],
+                "Why Rust?",
+                None
+            )),
+            "Doe, Jane, et al. \u{201c}Why Rust?\u{201d} *Example Blog*, example.com/posts/rust. Accessed 16 Oct. 2026."
+        );
+    }
+
+    #[test]
+    fn test_bibtex() {
+        assert_eq!(
+            bibtex(&source(
+                &["Jane Mary Doe", "Mozilla"],
+                "Learning Rust & C_FFI",
+                march_first()
+            )),
+            indoc::indoc! {r"
+                @online{doe2024learning,
+                  author = {Doe, Jane Mary and {Mozilla}},
+                  title = {{Learning Rust \& C\_FFI}},
+                  organization = {Example Blog},
+                  date = {2024-03-01},
+                  url = {https://example.com/posts/rust},
+                  urldate = {2026-10-16},
+                }"
+            }
+        );
+
+        let mut anonymous = source(&[], "", None);
+        anonymous.title = None;
+        anonymous.site_name = None;
+        assert_eq!(citation_key(&anonymous), "online");
+    }
+}
@@ -0,0 +1,417 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use context_server::{Tool, ToolContent, ToolExecutor};
+use serde_json::{Value, json};
+
+use crate::ToolAnnotations;
+
+tokio::task_local! {
+    static CURRENT: Arc<CookieJar>;
+}
+
+/// Session cookies for the domains a user has logged in to, sent with every
+/// fetch of those domains and their subdomains
+#[derive(Default)]
+pub struct CookieJar {
+    /// Cookies shared with other jars, sent too but never changed through this one
+    base: Option<Arc<CookieJar>>,
+    /// Cookies by domain; `None` marks one the site deleted, hiding that of the base
+    domains: RwLock<HashMap<String, BTreeMap<String, Option<String>>>>,
+}
+
+impl CookieJar {
+    /// Send the cookies of `base` as well, such as those the operator configured.
+    /// Cookies set here, or rotated by the site, take precedence over them;
+    /// clearing a domain leaves the base cookies in place.
+    pub fn with_base(mut self, base: Arc<CookieJar>) -> Self {
+        self.base = Some(base);
+        self
+    }
+
+    /// Use this jar for the fetches `future` makes, rather than the fetcher's own
+    pub async fn scope<F: Future>(self: Arc<Self>, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    pub(crate) fn current() -> Option<Arc<CookieJar>> {
+        CURRENT.try_with(Arc::clone).ok()
+    }
+
+    /// Add or replace cookies for `domain`, starting a session for it
+    pub fn set<I>(&self, domain: &str, cookies: I)
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.domains
+            .write()
+            .unwrap()
+            .entry(normalize_domain(domain))
+            .or_default()
+            .extend(cookies.into_iter().map(|(name, value)| (name, Some(value))));
+    }
+
+    /// End the session for `domain`, returning whether there was one
+    pub fn clear(&self, domain: &str) -> bool {
+        self.domains
+            .write()
+            .unwrap()
+            .remove(&normalize_domain(domain))
+            .is_some()
+    }
+
+    /// Domains with a session, including those of the base jar, sorted
+    pub fn domains(&self) -> Vec<String> {
+        let mut domains = self
+            .domains
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        if let Some(base) = &self.base {
+            domains.extend(base.domains());
+        }
+        domains.sort();
+        domains.dedup();
+        domains
+    }
+
+    /// The `Cookie` header to send to `host`, if it belongs to a domain with a session
+    pub fn header_for(&self, host: &str) -> Option<String> {
+        let cookies = self.cookies_for(host);
+        (!cookies.is_empty()).then(|| {
+            cookies
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+    }
+
+    fn cookies_for(&self, host: &str) -> BTreeMap<String, String> {
+        let mut cookies = self
+            .base
+            .as_ref()
+            .map(|base| base.cookies_for(host))
+            .unwrap_or_default();
+        let domains = self.domains.read().unwrap();
+        for (_, own) in domains
+            .iter()
+            .filter(|(domain, _)| domain_matches(host, domain))
+        {
+            for (name, value) in own {
+                match value {
+                    Some(value) => cookies.insert(name.clone(), value.clone()),
+                    None => cookies.remove(name),
+                };
+            }
+        }
+        cookies
+    }
+
+    /// Apply `Set-Cookie` headers received from `host`, so sessions that rotate their
+    /// cookies stay logged in. Hosts without a session are ignored; cookies rotated
+    /// for a domain of the base jar are kept in this one, as are deletions, which
+    /// stop the base cookie being sent for the rest of the session.
+    pub fn update_from_response<'a, I>(&self, host: &str, set_cookie_headers: I)
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let Some(domain) = self
+            .domains()
+            .into_iter()
+            .filter(|domain| domain_matches(host, domain))
+            .max_by_key(String::len)
+        else {
+            return;
+        };
+        let mut domains = self.domains.write().unwrap();
+        let cookies = domains.entry(domain).or_default();
+
+        for header in set_cookie_headers {
+            let mut attributes = header.split(';');
+            let Some((name, value)) = attributes.next().and_then(|pair| pair.split_once('='))
+            else {
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            if name.is_empty() {
+                continue;
+            }
+
+            let value = (!deletes_cookie(attributes)).then(|| value.to_string());
+            cookies.insert(name.to_string(), value);
+        }
+    }
+}
+
+/// Whether the attributes of a `Set-Cookie` header delete the cookie: a `Max-Age`
+/// of zero or less or, without one, an `Expires` date that has passed
+fn deletes_cookie<'a>(attributes: impl Iterator<Item = &'a str>) -> bool {
+    let mut max_age = None;
+    let mut expires = None;
+    for attribute in attributes {
+        let Some((key, value)) = attribute.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        if key.eq_ignore_ascii_case("max-age") {
+            max_age = value.parse::<i64>().ok();
+        } else if key.eq_ignore_ascii_case("expires") {
+            expires = parse_cookie_date(value);
+        }
+    }
+
+    match max_age {
+        Some(age) => age <= 0,
+        None => expires.is_some_and(|expires| expires <= Utc::now()),
+    }
+}
+
+/// `Expires` dates, as in `Thu, 01 Jan 1970 00:00:00 GMT` or the older
+/// `Thu, 01-Jan-1970 00:00:00 GMT`
+fn parse_cookie_date(date: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(date)
+        .or_else(|_| DateTime::parse_from_rfc2822(&date.replace('-', " ")))
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+fn normalize_domain(domain: &str) -> String {
+    domain.trim().trim_start_matches('.').to_lowercase()
+}
+
+/// Whether `host` is `domain` or one of its subdomains; `domain` must be lowercase
+pub(crate) fn domain_matches(host: &str, domain: &str) -> bool {
+    let host = host.to_lowercase();
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Lets clients log in to a site elsewhere and hand its session cookies to the fetch tools
+pub struct SetCookiesTool {
+    jar: Arc<CookieJar>,
+}
+
+impl SetCookiesTool {
+    pub const ANNOTATIONS: ToolAnnotations = ToolAnnotations {
+        read_only: Some(false),
+        destructive: Some(false),
+        idempotent: Some(true),
+        open_world: Some(false),
+    };
+
+    pub fn new(jar: Arc<CookieJar>) -> Self {
+        SetCookiesTool { jar }
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for SetCookiesTool {
+    async fn execute(&self, arguments: Option<Value>) -> Result<Vec<ToolContent>> {
+        let arguments = arguments.ok_or_else(|| anyhow!("missing arguments"))?;
+        let domain = arguments
+            .get("domain")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|domain| !domain.is_empty())
+            .ok_or_else(|| anyhow!("missing domain"))?;
+
+        if arguments.get("clear").and_then(Value::as_bool) == Some(true) {
+            let mut text = if self.jar.clear(domain) {
+                format!("Cleared the session for {}", domain)
+            } else {
+                format!("There was no session for {}", domain)
+            };
+            if self.jar.domains().contains(&normalize_domain(domain)) {
+                text.push_str("; cookies the server is configured with are still sent");
+            }
+            return Ok(vec![ToolContent::Text { text }]);
+        }
+
+        let cookies = match arguments.get("cookies") {
+            Some(Value::Object(cookies)) => cookies
+                .iter()
+                .map(|(name, value)| {
+                    value
+                        .as_str()
+                        .map(|value| (name.clone(), value.to_string()))
+                        .ok_or_else(|| anyhow!("cookie {} is not a string", name))
+                })
+                .collect::<Result<Vec<_>>>()?,
+            // The value of a Cookie header copied from a browser
+            Some(Value::String(header)) => header
+                .split(';')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                .filter(|(name, _)| !name.is_empty())
+                .collect(),
+            Some(_) => return Err(anyhow!("cookies must be an object or a string")),
+            None => return Err(anyhow!("missing cookies")),
+        };
+        if cookies.is_empty() {
+            return Err(anyhow!("no cookies given"));
+        }
+
+        // Only names are echoed back; values are credentials
+        let names = cookies
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        self.jar.set(domain, cookies);
+
+        Ok(vec![ToolContent::Text {
+            text: format!(
+                "Set {} for {} and its subdomains; read_url and fetch_raw will send them from now on",
+                names.join(", "),
+                domain
+            ),
+        }])
+    }
+
+    fn to_tool(&self) -> Tool {
+        Tool {
+            name: "set_cookies".into(),
+            description: Some(indoc::formatdoc! {"
+                    Stores session cookies for a domain so that read_url and fetch_raw send them with every request to that domain and its subdomains, for this session only. Use it to read pages behind a login the user is entitled to: log in with a browser, then pass the session cookies here.
+
+                    Cookies the site sets or rotates in later responses are kept up to date. Pass clear to end the session.
+                "}),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "domain": {
+                        "type": "string",
+                        "description": "The domain the cookies belong to, e.g. example.com."
+                    },
+                    "cookies": {
+                        "type": ["object", "string"],
+                        "additionalProperties": { "type": "string" },
+                        "description": "Cookie names and values, either as an object or as a Cookie header such as \"session=abc; token=xyz\"."
+                    },
+                    "clear": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Forget all cookies for the domain instead of setting any."
+                    }
+                },
+                "required": ["domain"]
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cookies(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_session_jars_share_only_the_base() {
+        let base = Arc::new(CookieJar::default());
+        base.set("example.com", cookies(&[("sid", "operator")]));
+        let alice = CookieJar::default().with_base(base.clone());
+        let bob = CookieJar::default().with_base(base.clone());
+
+        alice.set("news.test", cookies(&[("token", "alice")]));
+        assert_eq!(
+            alice.header_for("news.test").as_deref(),
+            Some("token=alice")
+        );
+        assert_eq!(bob.header_for("news.test"), None);
+
+        // A session's own cookies override the base, and clearing them leaves it in place
+        bob.set("example.com", cookies(&[("sid", "bob")]));
+        assert_eq!(
+            bob.header_for("www.example.com").as_deref(),
+            Some("sid=bob")
+        );
+        assert!(bob.clear("example.com"));
+        assert_eq!(
+            bob.header_for("example.com").as_deref(),
+            Some("sid=operator")
+        );
+        assert_eq!(
+            alice.header_for("example.com").as_deref(),
+            Some("sid=operator")
+        );
+        assert_eq!(base.domains(), vec!["example.com"]);
+    }
+
+    #[test]
+    fn test_rotated_cookies_stay_in_the_session() {
+        let base = Arc::new(CookieJar::default());
+        base.set("example.com", cookies(&[("sid", "one"), ("theme", "dark")]));
+        let session = CookieJar::default().with_base(base.clone());
+
+        session.update_from_response("www.example.com", ["sid=two; Path=/; HttpOnly"]);
+        assert_eq!(
+            session.header_for("example.com").as_deref(),
+            Some("sid=two; theme=dark")
+        );
+        assert_eq!(
+            base.header_for("example.com").as_deref(),
+            Some("sid=one; theme=dark")
+        );
+
+        // Hosts without a session are ignored
+        session.update_from_response("other.test", ["sid=three"]);
+        assert_eq!(session.header_for("other.test"), None);
+    }
+
+    #[test]
+    fn test_deleted_cookies_hide_the_base() {
+        let base = Arc::new(CookieJar::default());
+        base.set(
+            "example.com",
+            cookies(&[("sid", "one"), ("theme", "dark"), ("lang", "en")]),
+        );
+        let session = CookieJar::default().with_base(base.clone());
+
+        session.update_from_response(
+            "example.com",
+            [
+                "sid=; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Path=/",
+                "theme=deleted; expires=Thu, 01-Jan-1970 00:00:01 GMT",
+                // Max-Age wins over Expires
+                "lang=fr; Max-Age=3600; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+                "token=new; Expires=Fri, 01 Jan 2100 00:00:00 GMT",
+            ],
+        );
+        assert_eq!(
+            session.header_for("example.com").as_deref(),
+            Some("lang=fr; token=new")
+        );
+        assert_eq!(
+            base.header_for("example.com").as_deref(),
+            Some("lang=en; sid=one; theme=dark")
+        );
+
+        session.update_from_response("example.com", ["token=gone; Max-Age=0"]);
+        assert_eq!(
+            session.header_for("example.com").as_deref(),
+            Some("lang=fr")
+        );
+
+        // Clearing the session brings back the configured cookies
+        assert!(session.clear("example.com"));
+        assert_eq!(
+            session.header_for("example.com").as_deref(),
+            Some("lang=en; sid=one; theme=dark")
+        );
+    }
+}
@@ -0,0 +1,438 @@
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use context_server::{Tool, ToolContent, ToolExecutor};
+use http_client::http::header;
+use serde_json::{Value, json};
+
+use crate::{
+    Fetcher, StructuredToolExecutor, ToolAnnotations, ToolOutput, extract_url, optional_string,
+    optional_usize,
+};
+
+const DEFAULT_MAX_ROWS: usize = 20;
+
+/// Columns shown in the table and listed in the text; wider files are described
+/// in full only in the structured result
+const MAX_COLUMNS: usize = 30;
+
+/// Fetches CSV and TSV files and renders them as markdown tables with column statistics
+pub struct ReadCsvTool {
+    fetcher: Arc<Fetcher>,
+}
+
+/// Summary of one column across all rows
+struct ColumnStats {
+    name: String,
+    non_empty: usize,
+    distinct: usize,
+    /// Only set when every non-empty value is a number
+    numeric: Option<(f64, f64, f64)>,
+}
+
+impl ReadCsvTool {
+    pub const ANNOTATIONS: ToolAnnotations = ToolAnnotations {
+        read_only: Some(true),
+        destructive: Some(false),
+        idempotent: Some(true),
+        open_world: Some(true),
+    };
+
+    pub fn new(fetcher: Arc<Fetcher>) -> Self {
+        ReadCsvTool { fetcher }
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for ReadCsvTool {
+    async fn execute(&self, arguments: Option<Value>) -> Result<Vec<ToolContent>> {
+        Ok(self.execute_structured(arguments).await?.content)
+    }
+
+    fn to_tool(&self) -> Tool {
+        Tool {
+            name: "read_csv".into(),
+            description: Some(indoc::formatdoc! {"
+                    Fetches a CSV or TSV file and returns its first rows as a markdown table, followed by statistics for every column over the whole file: how many values are filled in, how many are distinct, and the minimum, maximum and mean of numeric columns.
+
+                    Quoted fields are parsed properly. The delimiter is detected from the URL, the content type or the header line unless given. Rows shorter than the widest are padded with empty cells. The text shows at most {MAX_COLUMNS} columns; the structured result describes them all.
+                "}),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "format": "uri",
+                        "description": "The URL of the CSV or TSV file."
+                    },
+                    "max_rows": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "default": DEFAULT_MAX_ROWS,
+                        "description": "Rows shown in the table. Statistics always cover every row."
+                    },
+                    "delimiter": {
+                        "type": "string",
+                        "description": "The field delimiter, e.g. \",\", \";\" or \"\\t\"."
+                    },
+                    "has_header": {
+                        "type": "boolean",
+                        "default": true,
+                        "description": "Whether the first row names the columns."
+                    }
+                },
+                "required": ["url"]
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl StructuredToolExecutor for ReadCsvTool {
+    fn output_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string" },
+                "rows": { "type": "integer", "minimum": 0 },
+                "shown_rows": { "type": "integer", "minimum": 0 },
+                "columns": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "type": { "type": "string", "enum": ["number", "text", "empty"] },
+                            "non_empty": { "type": "integer", "minimum": 0 },
+                            "distinct": { "type": "integer", "minimum": 0 },
+                            "min": { "type": ["number", "null"] },
+                            "max": { "type": ["number", "null"] },
+                            "mean": { "type": ["number", "null"] }
+                        },
+                        "required": ["name", "type", "non_empty", "distinct", "min", "max", "mean"]
+                    }
+                }
+            },
+            "required": ["url", "rows", "shown_rows", "columns"]
+        })
+    }
+
+    async fn execute_structured(&self, arguments: Option<Value>) -> Result<ToolOutput> {
+        let max_rows = optional_usize(&arguments, "max_rows")?.unwrap_or(DEFAULT_MAX_ROWS);
+        let delimiter = match optional_string(&arguments, "delimiter")?.as_deref() {
+            None => None,
+            Some("\\t" | "tab") => Some(b'\t'),
+            Some(delimiter) if delimiter.len() == 1 => Some(delimiter.as_bytes()[0]),
+            Some(_) => return Err(anyhow!("delimiter must be a single ASCII character")),
+        };
+        let has_header = arguments
+            .as_ref()
+            .and_then(|arguments| arguments.get("has_header"))
+            .and_then(Value::as_bool)
+            .unwrap_or(true);
+        let url = extract_url(arguments)?;
+        let expanded_url = self.fetcher.unwrap_short_url(&url).await;
+        let target = expanded_url.as_deref().unwrap_or(&url);
+
+        let page = self.fetcher.fetch(target).await?;
+        if page.status >= 400 {
+            return Err(anyhow!("{} answered with HTTP {}", url, page.status));
+        }
+        let content_type = page
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let delimiter =
+            delimiter.unwrap_or_else(|| detect_delimiter(target, content_type, &page.body));
+
+        let mut records = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(page.body.as_bytes())
+            .into_records()
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Failed to parse {} as CSV", url))?;
+        let width = records.iter().map(|record| record.len()).max().unwrap_or(0);
+        let header = if has_header && !records.is_empty() {
+            let header = records.remove(0);
+            (0..width)
+                .map(|index| match header.get(index).map(str::trim) {
+                    Some(name) if !name.is_empty() => name.to_string(),
+                    _ => format!("column {}", index + 1),
+                })
+                .collect::<Vec<_>>()
+        } else {
+            (1..=width)
+                .map(|index| format!("column {}", index))
+                .collect::<Vec<_>>()
+        };
+
+        let rows = records
+            .iter()
+            .map(|record| {
+                (0..width)
+                    .map(|index| record.get(index).unwrap_or_default().trim())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let stats = header
+            .iter()
+            .enumerate()
+            .map(|(index, name)| column_stats(name, rows.iter().map(|row| row[index])))
+            .collect::<Vec<_>>();
+        let shown_rows = rows.len().min(max_rows);
+        let shown_columns = width.min(MAX_COLUMNS);
+
+        let mut text = format!("URL: {}\nRows: {}\n\n", url, rows.len());
+        if width > shown_columns {
+            text.push_str(&format!(
+                "> Showing the first {} of {} columns.\n\n",
+                shown_columns, width
+            ));
+        }
+        if width > 0 && shown_rows > 0 {
+            text.push_str(&table_row(
+                header.iter().take(shown_columns).map(String::as_str),
+            ));
+            text.push_str(&format!("|{}\n", " --- |".repeat(shown_columns)));
+            for row in rows.iter().take(shown_rows) {
+                text.push_str(&table_row(row.iter().take(shown_columns).copied()));
+            }
+            if rows.len() > shown_rows {
+                text.push_str(&format!(
+                    "\n… {} more rows; raise max_rows to see them\n",
+                    rows.len() - shown_rows
+                ));
+            }
+            text.push('\n');
+        }
+
+        text.push_str("## Columns\n\n");
+        for column in stats.iter().take(shown_columns) {
+            text.push_str(&format!(
+                "- **{}** ({}): {} filled in, {} distinct",
+                column.name,
+                column.kind(),
+                column.non_empty,
+                column.distinct
+            ));
+            if let Some((min, max, mean)) = column.numeric {
+                text.push_str(&format!(", min {}, max {}, mean {:.2}", min, max, mean));
+            }
+            text.push('\n');
+        }
+
+        Ok(ToolOutput {
+            content: vec![ToolContent::Text { text }],
+            structured_content: Some(json!({
+                "url": url,
+                "rows": rows.len(),
+                "shown_rows": shown_rows,
+                "columns": stats
+                    .iter()
+                    .map(|column| json!({
+                        "name": column.name,
+                        "type": column.kind(),
+                        "non_empty": column.non_empty,
+                        "distinct": column.distinct,
+                        "min": column.numeric.map(|(min, _, _)| min),
+                        "max": column.numeric.map(|(_, max, _)| max),
+                        "mean": column.numeric.map(|(_, _, mean)| mean),
+                    }))
+                    .collect::<Vec<_>>(),
+            })),
+        })
+    }
+}
+
+impl ColumnStats {
+    fn kind(&self) -> &'static str {
+        if self.non_empty == 0 {
+            "empty"
+        } else if self.numeric.is_some() {
+            "number"
+        } else {
+            "text"
+        }
+    }
+}
+
+fn column_stats<'a, I>(name: &str, values: I) -> ColumnStats
+where
+    I: Iterator<Item = &'a str>,
+{
+    let mut non_empty = 0;
+    let mut distinct = HashSet::new();
+    let mut numbers = Some(Vec::new());
+    for value in values.filter(|value| !value.is_empty()) {
+        non_empty += 1;
+        distinct.insert(value);
+        if let Some(parsed) = &mut numbers {
+            match value.parse::<f64>() {
+                Ok(number) if number.is_finite() => parsed.push(number),
+                _ => numbers = None,
+            }
+        }
+    }
+
+    let numeric = numbers
+        .filter(|numbers| !numbers.is_empty())
+        .map(|numbers| {
+            let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let mean = numbers.iter().sum::<f64>() / numbers.len() as f64;
+            (min, max, mean)
+        });
+
+    ColumnStats {
+        name: name.to_string(),
+        non_empty,
+        distinct: distinct.len(),
+        numeric,
+    }
+}
+
+/// Tabs for TSV files, otherwise whichever of comma, semicolon and tab the header line uses most
+fn detect_delimiter(url: &str, content_type: &str, body: &str) -> u8 {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    if path.to_lowercase().ends_with(".tsv") || content_type.contains("tab-separated-values") {
+        return b'\t';
+    }
+
+    // The last of tied delimiters wins, so a header without any is read as CSV
+    let first_line = body.lines().next().unwrap_or_default();
+    [b'\t', b';', b',']
+        .into_iter()
+        .max_by_key(|delimiter| first_line.bytes().filter(|byte| byte == delimiter).count())
+        .unwrap_or(b',')
+}
+
+fn table_row<'a, I>(cells: I) -> String
+where
+    I: Iterator<Item = &'a str>,
+{
+    let cells = cells
+        .map(|cell| {
+            cell.split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .replace('|', "\\|")
+        })
+        .collect::<Vec<_>>();
+    format!("| {} |\n", cells.join(" | "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockHttpClient, Reply};
+
+    async fn read_csv(url: &str, reply: Reply, arguments: Value) -> ToolOutput {
+        let client = MockHttpClient::new().with_get(url, reply);
+        let mut arguments = arguments;
+        arguments["url"] = json!(url);
+        ReadCsvTool::new(Arc::new(Fetcher::new(Arc::new(client))))
+            .execute_structured(Some(arguments))
+            .await
+            .unwrap()
+    }
+
+    fn text(output: &ToolOutput) -> &str {
+        match &output.content[0] {
+            ToolContent::Text { text } => text,
+            _ => panic!("expected text"),
+        }
+    }
+
+    #[test]
+    fn test_detect_delimiter() {
+        let url = "https://example.com/data.csv";
+        assert_eq!(detect_delimiter(url, "text/csv", "a,b,c\n1,2,3"), b',');
+        assert_eq!(detect_delimiter(url, "text/csv", "a;b;c\n1,5;2;3"), b';');
+        assert_eq!(detect_delimiter(url, "", "a\tb\n"), b'\t');
+        assert_eq!(
+            detect_delimiter("https://example.com/data.tsv?v=2", "", "a,b"),
+            b'\t'
+        );
+        assert_eq!(
+            detect_delimiter(url, "text/tab-separated-values", "a,b"),
+            b'\t'
+        );
+        assert_eq!(detect_delimiter(url, "", ""), b',');
+    }
+
+    #[tokio::test]
+    async fn test_table_and_statistics() {
+        let body = "name,score,note\nAda,3,\"first, and | best\"\nGrace,5,\nAlan,4,\n";
+        let output = read_csv(
+            "https://example.com/scores.csv",
+            Reply::ok(body),
+            json!({ "max_rows": 2 }),
+        )
+        .await;
+
+        assert_eq!(
+            text(&output),
+            indoc::indoc! {r"
+                URL: https://example.com/scores.csv
+                Rows: 3
+
+                | name | score | note |
+                | --- | --- | --- |
+                | Ada | 3 | first, and \| best |
+                | Grace | 5 |  |
+
+                … 1 more rows; raise max_rows to see them
+
+                ## Columns
+
+                - **name** (text): 3 filled in, 3 distinct
+                - **score** (number): 3 filled in, 3 distinct, min 3, max 5, mean 4.00
+                - **note** (text): 1 filled in, 1 distinct
+            "}
+        );
+        let structured = output.structured_content.unwrap();
+        assert_eq!(structured["rows"], 3);
+        assert_eq!(structured["shown_rows"], 2);
+        assert_eq!(structured["columns"][1]["mean"], 4.0);
+    }
+
+    #[tokio::test]
+    async fn test_ragged_rows() {
+        // Short rows are padded; extra cells get columns of their own
+        let body = "a;b\n1\n1;2;3\n";
+        let output = read_csv("https://example.com/ragged", Reply::ok(body), json!({})).await;
+
+        assert!(text(&output).contains("| a | b | column 3 |\n"));
+        assert!(text(&output).contains("| 1 |  |  |\n"));
+        assert!(text(&output).contains("| 1 | 2 | 3 |\n"));
+        let structured = output.structured_content.unwrap();
+        assert_eq!(structured["columns"][2]["non_empty"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_wide_files_show_capped_columns() {
+        let width = MAX_COLUMNS + 5;
+        let header = (1..=width)
+            .map(|index| format!("c{}", index))
+            .collect::<Vec<_>>()
+            .join(",");
+        let row = vec!["1"; width].join(",");
+        let output = read_csv(
+            "https://example.com/wide.csv",
+            Reply::ok(format!("{}\n{}\n", header, row)),
+            json!({ "has_header": true }),
+        )
+        .await;
+
+        let text = text(&output);
+        assert!(text.contains(&format!("first {} of {} columns", MAX_COLUMNS, width)));
+        assert!(text.contains(&format!("| c{} |\n", MAX_COLUMNS)));
+        assert!(!text.contains(&format!("c{}", MAX_COLUMNS + 1)));
+        let structured = output.structured_content.unwrap();
+        assert_eq!(structured["columns"].as_array().unwrap().len(), width);
+    }
+}
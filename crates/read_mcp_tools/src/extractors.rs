@@ -0,0 +1,183 @@
+use anyhow::{Result, anyhow};
+use readability::{
+    HeadingLevels, Readability, default_boilerplate_phrases, is_probably_readerable,
+    normalize_text, strip_boilerplate, strip_hidden_content,
+};
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+
+use crate::{Extraction, FallbackOptions, evaluate_quality, fallback};
+
+/// Names of the engines read_url can extract pages with, the default first
+pub const ENGINES: &[&str] = &["readability", "semantic", "page", "auto"];
+
+/// Readability articles scoring this much or less are treated as a failed extraction
+const MIN_READABILITY_QUALITY: f32 = 10.0;
+
+static EXTRACTORS: &[&dyn Extractor] = &[
+    &ReadabilityExtractor,
+    &SemanticExtractor,
+    &PageExtractor,
+    &EnsembleExtractor,
+];
+
+/// Settings every engine extracts with
+pub(crate) struct ExtractionSettings<'a> {
+    pub(crate) boilerplate_phrases: &'a [String],
+    pub(crate) heading_levels: HeadingLevels,
+    pub(crate) fallback: &'a FallbackOptions,
+}
+
+/// A way of finding a page's content and converting it to markdown
+pub(crate) trait Extractor: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Fails when the engine finds nothing it can call the page's content
+    fn extract(&self, html: &str, url: &Url, settings: &ExtractionSettings) -> Result<Extraction>;
+}
+
+/// The engine called `name`
+pub(crate) fn extractor(name: &str) -> Result<&'static dyn Extractor> {
+    EXTRACTORS
+        .iter()
+        .copied()
+        .find(|extractor| extractor.name() == name)
+        .ok_or_else(|| {
+            anyhow!(
+                "unknown extraction engine \"{}\", expected one of {}",
+                name,
+                ENGINES.join(", ")
+            )
+        })
+}
+
+/// The engine pages are converted with when the chosen one finds no content
+pub(crate) fn page_extractor() -> &'static dyn Extractor {
+    &PageExtractor
+}
+
+/// The in-house readability port, scoring the article it finds
+struct ReadabilityExtractor;
+
+/// The page's `<main>` landmark, or its largest `<article>`
+struct SemanticExtractor;
+
+/// The whole page, for pages that aren't articles, such as indexes and listings
+struct PageExtractor;
+
+/// Every other engine, keeping the result that scores best
+struct EnsembleExtractor;
+
+impl Extractor for ReadabilityExtractor {
+    fn name(&self) -> &'static str {
+        "readability"
+    }
+
+    fn extract(&self, html: &str, url: &Url, settings: &ExtractionSettings) -> Result<Extraction> {
+        // Scoring index and landing pages is slow and finds nothing worth keeping
+        if !is_probably_readerable(html) {
+            return Err(anyhow!("{} doesn't look like an article", url));
+        }
+
+        let article = Readability::new(html)
+            .with_url(url.clone())
+            .with_boilerplate_stripping(true)
+            .with_boilerplate_phrases(settings.boilerplate_phrases.iter().cloned())
+            .with_heading_levels(settings.heading_levels)
+            .parse()?;
+        let extraction = Extraction::from_article(article, url.as_str());
+
+        let quality_score = evaluate_quality(&extraction, html);
+        if quality_score <= MIN_READABILITY_QUALITY {
+            return Err(anyhow!(
+                "readability found no convincing article (quality score {:.0})",
+                quality_score
+            ));
+        }
+        Ok(extraction)
+    }
+}
+
+impl Extractor for SemanticExtractor {
+    fn name(&self) -> &'static str {
+        "semantic"
+    }
+
+    fn extract(&self, html: &str, url: &Url, settings: &ExtractionSettings) -> Result<Extraction> {
+        let document = Html::parse_document(&strip_hidden_content(html));
+        let main = Selector::parse("main, [role=\"main\"]").unwrap();
+        let article = Selector::parse("article").unwrap();
+        let text_length = |element: &ElementRef| element.text().map(str::len).sum::<usize>();
+
+        let content = document
+            .select(&main)
+            .next()
+            .or_else(|| document.select(&article).max_by_key(text_length))
+            .filter(|element| text_length(element) > 0)
+            .ok_or_else(|| anyhow!("{} has no <main> or <article> element", url))?;
+
+        let markdown = settings.fallback.convert(&content.html())?;
+        Ok(Extraction::from_markdown(
+            clean_markdown(&markdown, settings),
+            html,
+            url.as_str(),
+        ))
+    }
+}
+
+impl Extractor for PageExtractor {
+    fn name(&self) -> &'static str {
+        "page"
+    }
+
+    fn extract(&self, html: &str, url: &Url, settings: &ExtractionSettings) -> Result<Extraction> {
+        // Readability drops hidden content itself; converters need it done up front
+        let markdown = settings.fallback.convert(&strip_hidden_content(html))?;
+        Ok(fallback(
+            clean_markdown(&markdown, settings),
+            html,
+            url.as_str(),
+        ))
+    }
+}
+
+impl Extractor for EnsembleExtractor {
+    fn name(&self) -> &'static str {
+        "auto"
+    }
+
+    fn extract(&self, html: &str, url: &Url, settings: &ExtractionSettings) -> Result<Extraction> {
+        let mut best: Option<(f32, &'static str, Extraction)> = None;
+        let mut scores = Vec::new();
+        for extractor in EXTRACTORS
+            .iter()
+            .filter(|extractor| extractor.name() != self.name())
+        {
+            let _span = tracing::info_span!("extract", engine = extractor.name()).entered();
+            let Ok(extraction) = extractor.extract(html, url, settings) else {
+                continue;
+            };
+            let score = evaluate_quality(&extraction, html);
+            scores.push((extractor.name(), score));
+            // Ties go to the engine listed first
+            if best
+                .as_ref()
+                .is_none_or(|(best_score, _, _)| score > *best_score)
+            {
+                best = Some((score, extractor.name(), extraction));
+            }
+        }
+
+        let (_, winner, mut extraction) =
+            best.ok_or_else(|| anyhow!("no extraction engine found content in {}", url))?;
+        extraction.metadata.engine = Some(winner);
+        extraction.metadata.engine_scores = scores;
+        Ok(extraction)
+    }
+}
+
+fn clean_markdown(markdown: &str, settings: &ExtractionSettings) -> String {
+    let mut phrases = default_boilerplate_phrases(None);
+    phrases.extend(settings.boilerplate_phrases.iter().cloned());
+    strip_boilerplate(&normalize_text(markdown), &phrases)
+}
@@ -0,0 +1,147 @@
+use std::sync::LazyLock;
+
+use anyhow::{Result, anyhow};
+use htmd::{
+    HtmlToMarkdown,
+    options::{LinkStyle, Options},
+};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Elements left out of the fallback conversion unless configured otherwise
+const DEFAULT_SKIP_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "nav", "footer", "aside",
+];
+
+static MARKDOWN_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(!?)\[([^\]\n]*)\]\([^)\n]*\)").unwrap());
+
+/// How links are written in fallback markdown
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkHandling {
+    /// `[text](url)` where the link appears
+    #[default]
+    Inline,
+    /// `[text][1]`, with the URLs listed at the end
+    Referenced,
+    /// Only the link text
+    Text,
+}
+
+/// How a whole page is converted to markdown when readability can't find an article in it
+#[derive(Debug, Clone)]
+pub struct FallbackOptions {
+    skip_tags: Vec<String>,
+    links: LinkHandling,
+    tables: bool,
+}
+
+impl Default for FallbackOptions {
+    fn default() -> Self {
+        FallbackOptions {
+            skip_tags: DEFAULT_SKIP_TAGS
+                .iter()
+                .map(|tag| tag.to_string())
+                .collect(),
+            links: LinkHandling::default(),
+            tables: true,
+        }
+    }
+}
+
+impl FallbackOptions {
+    /// Leave out these elements and everything in them, instead of the default list
+    pub fn with_skip_tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.skip_tags = tags
+            .into_iter()
+            .map(|tag| tag.as_ref().trim().to_lowercase())
+            .collect();
+        self
+    }
+
+    pub fn with_links(mut self, links: LinkHandling) -> Self {
+        self.links = links;
+        self
+    }
+
+    /// Tables are kept by default; pages laying out navigation with them read better without
+    pub fn with_tables(mut self, tables: bool) -> Self {
+        self.tables = tables;
+        self
+    }
+
+    /// These options with the `skip_tags`, `links` and `tables` given in a tool's arguments
+    pub(crate) fn merged(&self, arguments: Option<&Value>) -> Result<Self> {
+        let mut options = self.clone();
+        let Some(arguments) = arguments else {
+            return Ok(options);
+        };
+        let arguments = arguments
+            .as_object()
+            .ok_or_else(|| anyhow!("fallback must be an object"))?;
+
+        if let Some(skip_tags) = arguments.get("skip_tags") {
+            let tags = skip_tags
+                .as_array()
+                .and_then(|tags| tags.iter().map(Value::as_str).collect::<Option<Vec<_>>>())
+                .ok_or_else(|| anyhow!("fallback.skip_tags must be an array of strings"))?;
+            options = options.with_skip_tags(tags);
+        }
+        if let Some(links) = arguments.get("links") {
+            options = options.with_links(serde_json::from_value(links.clone()).map_err(|_| {
+                anyhow!("fallback.links must be \"inline\", \"referenced\" or \"text\"")
+            })?);
+        }
+        if let Some(tables) = arguments.get("tables") {
+            options = options.with_tables(
+                tables
+                    .as_bool()
+                    .ok_or_else(|| anyhow!("fallback.tables must be a boolean"))?,
+            );
+        }
+        Ok(options)
+    }
+
+    pub(crate) fn convert(&self, html: &str) -> Result<String> {
+        let _span = tracing::info_span!("markdown.convert", bytes = html.len()).entered();
+        let mut skip_tags = self
+            .skip_tags
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !self.tables {
+            skip_tags.push("table");
+        }
+        let converter = HtmlToMarkdown::builder()
+            .skip_tags(skip_tags)
+            .options(Options {
+                link_style: match self.links {
+                    LinkHandling::Referenced => LinkStyle::Referenced,
+                    LinkHandling::Inline | LinkHandling::Text => LinkStyle::Inlined,
+                },
+                ..Default::default()
+            })
+            .build();
+
+        let markdown = converter.convert(html)?;
+        Ok(match self.links {
+            LinkHandling::Text => MARKDOWN_LINK
+                .replace_all(&markdown, |captures: &Captures| {
+                    // Images keep their markup
+                    if captures[1].is_empty() {
+                        captures[2].to_string()
+                    } else {
+                        captures[0].to_string()
+                    }
+                })
+                .into_owned(),
+            LinkHandling::Inline | LinkHandling::Referenced => markdown,
+        })
+    }
+}
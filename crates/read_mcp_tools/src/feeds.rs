@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use context_server::{Tool, ToolContent, ToolExecutor};
+use scraper::{Html, Selector};
+use serde_json::{Value, json};
+use url::Url;
+
+use crate::{
+    Fetcher, StructuredToolExecutor, ToolAnnotations, ToolOutput, extract_title, extract_url,
+};
+
+/// Lists the RSS, Atom and JSON feeds a page advertises
+pub struct ListFeedsTool {
+    fetcher: Arc<Fetcher>,
+}
+
+/// A feed advertised with `<link rel="alternate">`
+struct Feed {
+    url: String,
+    title: Option<String>,
+    kind: &'static str,
+}
+
+impl ListFeedsTool {
+    pub const ANNOTATIONS: ToolAnnotations = ToolAnnotations {
+        read_only: Some(true),
+        destructive: Some(false),
+        idempotent: Some(true),
+        open_world: Some(true),
+    };
+
+    pub fn new(fetcher: Arc<Fetcher>) -> Self {
+        ListFeedsTool { fetcher }
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for ListFeedsTool {
+    async fn execute(&self, arguments: Option<Value>) -> Result<Vec<ToolContent>> {
+        Ok(self.execute_structured(arguments).await?.content)
+    }
+
+    fn to_tool(&self) -> Tool {
+        Tool {
+            name: "list_feeds".into(),
+            description: Some(indoc::formatdoc! {"
+                    Fetches a web page and lists the RSS, Atom and JSON feeds it advertises, with their titles and absolute URLs. Use it to go from a blog or news site to a feed that can be subscribed to or monitored, instead of guessing feed URLs.
+
+                    If the URL is itself a feed, it is listed on its own.
+                "}),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "format": "uri",
+                        "description": "The URL of the page, usually a site's home page or a blog index."
+                    }
+                },
+                "required": ["url"]
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl StructuredToolExecutor for ListFeedsTool {
+    fn output_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string" },
+                "feeds": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "url": { "type": "string" },
+                            "title": { "type": ["string", "null"] },
+                            "type": { "type": "string", "enum": ["rss", "atom", "json", "rdf"] }
+                        },
+                        "required": ["url", "title", "type"]
+                    }
+                }
+            },
+            "required": ["url", "feeds"]
+        })
+    }
+
+    async fn execute_structured(&self, arguments: Option<Value>) -> Result<ToolOutput> {
+        let url = extract_url(arguments)?;
+        let expanded_url = self.fetcher.unwrap_short_url(&url).await;
+        let target = expanded_url.as_deref().unwrap_or(&url);
+
+        let page = self.fetcher.fetch_document(target, false).await?;
+        if let Some(blocked) = self.fetcher.blocked(&page) {
+            return Err(blocked.into());
+        }
+        let final_url = page.redirects.last().map_or(target, String::as_str);
+        let base = Url::parse(final_url)?;
+
+        let feeds = match feed_kind(&page.body) {
+            Some(kind) => vec![Feed {
+                url: final_url.to_string(),
+                title: extract_title(&page.body),
+                kind,
+            }],
+            None => discover_feeds(&page.body, &base),
+        };
+
+        let text = if feeds.is_empty() {
+            format!("{} doesn't advertise any feeds", url)
+        } else {
+            let mut text = format!("Feeds advertised by {}:\n\n", url);
+            for feed in &feeds {
+                match &feed.title {
+                    Some(title) => {
+                        text.push_str(&format!("- {} ({}): {}\n", title, feed.kind, feed.url))
+                    }
+                    None => text.push_str(&format!("- {}: {}\n", feed.kind, feed.url)),
+                }
+            }
+            text
+        };
+
+        Ok(ToolOutput {
+            content: vec![ToolContent::Text { text }],
+            structured_content: Some(json!({
+                "url": url,
+                "feeds": feeds
+                    .iter()
+                    .map(|feed| json!({ "url": feed.url, "title": feed.title, "type": feed.kind }))
+                    .collect::<Vec<_>>(),
+            })),
+        })
+    }
+}
+
+/// Feeds linked from the page's `<link rel="alternate">` elements, without duplicates
+fn discover_feeds(html: &str, base: &Url) -> Vec<Feed> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("link[rel~=\"alternate\" i][href][type]").unwrap();
+
+    let mut feeds: Vec<Feed> = Vec::new();
+    for link in document.select(&selector) {
+        let kind = match link
+            .value()
+            .attr("type")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "application/rss+xml" => "rss",
+            "application/atom+xml" => "atom",
+            "application/feed+json" | "application/json" => "json",
+            "application/rdf+xml" => "rdf",
+            _ => continue,
+        };
+        let Some(url) = link
+            .value()
+            .attr("href")
+            .and_then(|href| base.join(href.trim()).ok())
+            .map(String::from)
+        else {
+            continue;
+        };
+        if feeds.iter().any(|feed| feed.url == url) {
+            continue;
+        }
+
+        let title = link
+            .value()
+            .attr("title")
+            .map(str::trim)
+            .filter(|title| !title.is_empty())
+            .map(str::to_string);
+        feeds.push(Feed { url, title, kind });
+    }
+    feeds
+}
+
+/// The kind of feed `body` is, if it is one rather than a page
+fn feed_kind(body: &str) -> Option<&'static str> {
+    let start = body.trim_start();
+    if start.starts_with('{') {
+        return start
+            .contains("https://jsonfeed.org/version/")
+            .then_some("json");
+    }
+
+    // The root element follows at most an XML declaration, comments and stylesheet instructions
+    let head = start.get(..start.len().min(1024)).unwrap_or(start);
+    if head.contains("<rss") {
+        Some("rss")
+    } else if head.contains("<feed") && head.contains("http://www.w3.org/2005/Atom") {
+        Some("atom")
+    } else if head.contains("<rdf:RDF") {
+        Some("rdf")
+    } else {
+        None
+    }
+}
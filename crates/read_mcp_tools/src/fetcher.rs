@@ -0,0 +1,831 @@
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use http_client::{
+    HttpClient, Request, RequestBuilderExt, ResponseAsyncBodyExt,
+    http::{HeaderMap, HeaderName, HeaderValue, Method, header},
+};
+use scraper::{Html, Selector};
+use url::Url;
+
+use crate::{
+    audit::AuditLog,
+    cookies::{CookieJar, domain_matches},
+    policy::ContentPolicy,
+    stats::ServerStats,
+};
+
+/// A fetched response body along with its HTTP status
+pub struct Page {
+    pub status: u16,
+    pub body: String,
+    /// When the response was received
+    pub fetched_at: DateTime<Utc>,
+    /// URLs reached through meta refresh or script redirects, in the order they were followed
+    pub redirects: Vec<String>,
+    /// How long the origin asked clients to wait before retrying
+    pub retry_after: Option<Duration>,
+    pub headers: HeaderMap,
+}
+
+/// Why the origin refused a request, and what the caller can do about it
+#[derive(Debug)]
+pub struct Blocked {
+    pub status: u16,
+    pub retry_after: Option<Duration>,
+    pub advice: Vec<String>,
+}
+
+impl fmt::Display for Blocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The site refused the request with HTTP {}. {}",
+            self.status,
+            self.advice.join(" ")
+        )
+    }
+}
+
+impl std::error::Error for Blocked {}
+
+/// Attempts repeated after a 429 or 503 that came with a short enough Retry-After
+const MAX_RETRIES: usize = 2;
+
+const DEFAULT_MAX_RETRY_WAIT: Duration = Duration::from_secs(10);
+
+/// Link shorteners whose links are expanded before fetching
+const SHORTENER_HOSTS: &[&str] = &[
+    "t.co",
+    "bit.ly",
+    "bitly.com",
+    "lnkd.in",
+    "tinyurl.com",
+    "goo.gl",
+    "ow.ly",
+    "buff.ly",
+    "is.gd",
+    "dlvr.it",
+    "rb.gy",
+    "cutt.ly",
+    "t.ly",
+    "tiny.cc",
+    "trib.al",
+    "fb.me",
+];
+
+/// Shortener hops followed before giving up, for links shortened more than once
+const MAX_SHORT_URL_HOPS: usize = 5;
+
+/// Markers of the JavaScript challenges bot protection serves instead of the page
+const CHALLENGE_MARKERS: &[&str] = &[
+    "challenge-platform",
+    "cf-chl",
+    "just a moment...",
+    "_incapsula_resource",
+    "enable javascript and cookies to continue",
+    "please enable js and disable any ad blocker",
+];
+
+/// HTTP redirects followed for one fetch before giving up
+const MAX_HTTP_REDIRECTS: usize = 10;
+
+/// Client-side redirects followed before giving up on reaching the content
+const MAX_CLIENT_REDIRECTS: usize = 5;
+
+/// Meta refreshes with a longer delay are treated as periodic reloads, not redirects
+const MAX_REFRESH_DELAY_SECONDS: f32 = 10.0;
+
+/// Script redirects are only followed on pages with less visible text than this,
+/// so articles whose scripts happen to assign `location` are left alone
+const MAX_REDIRECT_PAGE_WORDS: usize = 50;
+
+/// The HTTP layer shared by every tool that retrieves web content
+pub struct Fetcher {
+    http_client: Arc<dyn HttpClient>,
+    stats: Option<Arc<ServerStats>>,
+    audit: Option<Arc<AuditLog>>,
+    cookies: Option<Arc<CookieJar>>,
+    /// Extra headers for a domain and its subdomains
+    domain_headers: RwLock<Vec<DomainHeaders>>,
+    impersonate_browser: bool,
+    max_retry_wait: Duration,
+    policy: RwLock<ContentPolicy>,
+}
+
+type DomainHeaders = (String, Vec<(HeaderName, HeaderValue)>);
+
+/// The headers a desktop Chrome sends when navigating to a page, in the order it
+/// sends them. Bot detection commonly rejects requests missing the client hints.
+const BROWSER_HEADERS: &[(&str, &str)] = &[
+    (
+        "sec-ch-ua",
+        "\"Chromium\";v=\"130\", \"Google Chrome\";v=\"130\", \"Not?A_Brand\";v=\"99\"",
+    ),
+    ("sec-ch-ua-mobile", "?0"),
+    ("sec-ch-ua-platform", "\"Windows\""),
+    ("upgrade-insecure-requests", "1"),
+    (
+        "user-agent",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/130.0.0.0 Safari/537.36",
+    ),
+    (
+        "accept",
+        "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8",
+    ),
+    ("sec-fetch-site", "none"),
+    ("sec-fetch-mode", "navigate"),
+    ("sec-fetch-user", "?1"),
+    ("sec-fetch-dest", "document"),
+    ("accept-language", "en-US,en;q=0.9"),
+];
+
+impl Fetcher {
+    pub fn new(http_client: Arc<dyn HttpClient>) -> Self {
+        Fetcher {
+            http_client,
+            stats: None,
+            audit: None,
+            cookies: None,
+            domain_headers: RwLock::new(Vec::new()),
+            impersonate_browser: false,
+            max_retry_wait: DEFAULT_MAX_RETRY_WAIT,
+            policy: RwLock::new(ContentPolicy::default()),
+        }
+    }
+
+    /// Report latency, size and status of every fetch to `stats`
+    pub fn with_stats(mut self, stats: Arc<ServerStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Record every request sent to an origin in `audit`
+    pub fn with_audit_log(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    pub async fn fetch(&self, url: &str) -> Result<Page> {
+        self.fetch_with(url, false).await
+    }
+
+    /// Send session cookies from `jar` to the domains it holds, keeping them up to date,
+    /// for fetches made outside a [`CookieJar::scope`]
+    pub fn with_cookies(mut self, jar: Arc<CookieJar>) -> Self {
+        self.cookies = Some(jar);
+        self
+    }
+
+    /// Send `headers` with every request to `domain` and its subdomains, e.g. an API key
+    pub fn with_domain_headers<I>(mut self, domain: &str, headers: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let domain_headers = parse_domain_headers(domain, headers)?;
+        self.domain_headers.get_mut().unwrap().push(domain_headers);
+        Ok(self)
+    }
+
+    /// Replace the headers sent to every domain, leaving them as they were if any is invalid
+    pub fn set_domain_headers<'a, I, H>(&self, domains: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (&'a str, H)>,
+        H: IntoIterator<Item = (String, String)>,
+    {
+        let domain_headers = domains
+            .into_iter()
+            .map(|(domain, headers)| parse_domain_headers(domain, headers))
+            .collect::<Result<Vec<_>>>()?;
+        *self.domain_headers.write().unwrap() = domain_headers;
+        Ok(())
+    }
+
+    /// Send the headers of a desktop browser, in a browser's order, so sites with basic
+    /// bot detection serve the page instead of a challenge. Domain headers take precedence.
+    pub fn with_browser_headers(mut self, impersonate_browser: bool) -> Self {
+        self.impersonate_browser = impersonate_browser;
+        self
+    }
+
+    /// Wait and retry when a 429 or 503 asks for at most `max_retry_wait` before retrying;
+    /// longer waits are left to the caller
+    pub fn with_max_retry_wait(mut self, max_retry_wait: Duration) -> Self {
+        self.max_retry_wait = max_retry_wait;
+        self
+    }
+
+    /// Refuse URLs and responses the operator's content policy blocks
+    pub fn with_content_policy(self, policy: ContentPolicy) -> Self {
+        self.set_content_policy(policy);
+        self
+    }
+
+    /// Apply `policy` to fetches from now on, including those of tool calls in progress
+    pub fn set_content_policy(&self, policy: ContentPolicy) {
+        *self.policy.write().unwrap() = policy;
+    }
+
+    /// Whether `page` is a refusal (403 or 429) rather than content, with advice on getting past it
+    pub fn blocked(&self, page: &Page) -> Option<Blocked> {
+        let mut advice = Vec::new();
+        match page.status {
+            429 => advice.push(match page.retry_after {
+                Some(wait) => format!(
+                    "The site is rate limiting requests; retry in {} seconds.",
+                    wait.as_secs()
+                ),
+                None => "The site is rate limiting requests; retry later.".to_string(),
+            }),
+            403 => {
+                let body = page.body.to_lowercase();
+                if CHALLENGE_MARKERS.iter().any(|marker| body.contains(marker)) {
+                    advice.push(
+                        "The site answered with a JavaScript challenge, so the page needs a browser to render it; open it in a browser and pass its HTML to read_url instead."
+                            .to_string(),
+                    );
+                } else if !self.impersonate_browser {
+                    advice.push(
+                        "The site may be blocking this client's User-Agent; the operator can set fetch.impersonate_browser."
+                            .to_string(),
+                    );
+                } else {
+                    advice.push(
+                        "The site may require a login (see set_cookies) or refuse automated clients altogether."
+                            .to_string(),
+                    );
+                }
+                if let Some(wait) = page.retry_after {
+                    advice.push(format!("It asked to retry in {} seconds.", wait.as_secs()));
+                }
+            }
+            _ => return None,
+        }
+
+        Some(Blocked {
+            status: page.status,
+            retry_after: page.retry_after,
+            advice,
+        })
+    }
+
+    /// Where a link from a known URL shortener points, resolved with HEAD requests so no
+    /// body is downloaded. `None` for other URLs, or when the shortener doesn't answer
+    /// with a redirect, in which case the link is fetched as it is. No hop the content
+    /// policy blocks is requested: expansion stops there, leaving the fetch of that
+    /// URL to fail.
+    pub async fn unwrap_short_url(&self, url: &str) -> Option<String> {
+        let is_shortener = |url: &Url| {
+            url.host_str().is_some_and(|host| {
+                SHORTENER_HOSTS
+                    .iter()
+                    .any(|shortener| host.eq_ignore_ascii_case(shortener))
+            })
+        };
+
+        let mut current = Url::parse(url).ok()?;
+        for _ in 0..MAX_SHORT_URL_HOPS {
+            if !is_shortener(&current) || self.check_url(current.as_str()).is_err() {
+                break;
+            }
+            let request = Request::builder()
+                .method(Method::HEAD)
+                .uri(current.as_str())
+                .end()
+                .ok()?;
+            let started_at = Instant::now();
+            let response = self.http_client.send(request).await;
+            if let Some(stats) = &self.stats {
+                match &response {
+                    Ok(response) => {
+                        stats.record_fetch(response.status().as_u16(), 0, started_at.elapsed())
+                    }
+                    Err(_) => stats.record_fetch_error(started_at.elapsed()),
+                }
+            }
+            if let Some(audit) = &self.audit {
+                audit.record(
+                    current.as_str(),
+                    response
+                        .as_ref()
+                        .map(|response| (response.status().as_u16(), 0, response.headers())),
+                );
+            }
+            let response = response.ok()?;
+            let location = response
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .filter(|_| response.status().is_redirection())?;
+            current = current.join(location).ok()?;
+        }
+
+        (current.as_str() != url).then(|| current.to_string())
+    }
+
+    /// Fetch an HTML document, following `<meta http-equiv="refresh">` and trivial
+    /// `location.href = "..."` redirects so interstitial pages aren't mistaken for content
+    pub async fn fetch_document(&self, url: &str, bypass_cache: bool) -> Result<Page> {
+        let mut page = self.fetch_with(url, bypass_cache).await?;
+        let Ok(mut current) = Url::parse(url) else {
+            return Ok(page);
+        };
+
+        let mut redirects: Vec<String> = Vec::new();
+        while redirects.len() < MAX_CLIENT_REDIRECTS
+            && let Some(target) = client_redirect(&page.body, &current)
+        {
+            if target == current || redirects.iter().any(|seen| seen == target.as_str()) {
+                break;
+            }
+            page = self.fetch_with(target.as_str(), bypass_cache).await?;
+            redirects.push(target.to_string());
+            current = target;
+        }
+
+        page.redirects = redirects;
+        Ok(page)
+    }
+
+    /// Fetch from the origin, asking proxies and CDNs not to answer from their caches
+    pub async fn fetch_fresh(&self, url: &str) -> Result<Page> {
+        self.fetch_with(url, true).await
+    }
+
+    /// Fail when the content policy blocks `url`, without fetching it
+    pub fn check_url(&self, url: &str) -> Result<()> {
+        match Url::parse(url) {
+            Ok(parsed) => self.policy.read().unwrap().check_url(&parsed),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Fetch `url`, following HTTP redirects here rather than in the client so the
+    /// content policy is checked before every hop
+    async fn fetch_with(&self, url: &str, bypass_cache: bool) -> Result<Page> {
+        self.check_url(url)?;
+
+        let mut current = url.to_string();
+        for _ in 0..=MAX_HTTP_REDIRECTS {
+            let page = self.fetch_retrying(&current, bypass_cache).await?;
+            let Some(target) = http_redirect(&page, &current) else {
+                return Ok(page);
+            };
+            self.check_url(target.as_str())?;
+            current = target.to_string();
+        }
+        Err(anyhow!(
+            "Gave up on {} after {} redirects",
+            url,
+            MAX_HTTP_REDIRECTS
+        ))
+    }
+
+    /// Fetch `url`, retrying as asked by short Retry-After waits
+    async fn fetch_retrying(&self, url: &str, bypass_cache: bool) -> Result<Page> {
+        let mut retries = 0;
+        loop {
+            let page = self.fetch_once(url, bypass_cache).await?;
+            let wait = page
+                .retry_after
+                .filter(|wait| matches!(page.status, 429 | 503) && *wait <= self.max_retry_wait);
+            match wait {
+                Some(wait) if retries < MAX_RETRIES => {
+                    retries += 1;
+                    tokio::time::sleep(wait).await;
+                }
+                _ => return Ok(page),
+            }
+        }
+    }
+
+    #[tracing::instrument(
+        name = "http.fetch",
+        skip(self, bypass_cache),
+        fields(
+            http.response.status_code = tracing::field::Empty,
+            http.response.body.size = tracing::field::Empty,
+            otel.status_code = tracing::field::Empty,
+        )
+    )]
+    async fn fetch_once(&self, url: &str, bypass_cache: bool) -> Result<Page> {
+        let started_at = Instant::now();
+        let result = self.send(url, bypass_cache).await;
+
+        let span = tracing::Span::current();
+        match &result {
+            Ok(page) => {
+                span.record("http.response.status_code", page.status);
+                span.record("http.response.body.size", page.body.len());
+            }
+            Err(_) => {
+                span.record("otel.status_code", "ERROR");
+            }
+        }
+
+        if let Some(stats) = &self.stats {
+            match &result {
+                Ok(page) => stats.record_fetch(page.status, page.body.len(), started_at.elapsed()),
+                Err(_) => stats.record_fetch_error(started_at.elapsed()),
+            }
+        }
+        if let Some(audit) = &self.audit {
+            audit.record(
+                url,
+                result
+                    .as_ref()
+                    .map(|page| (page.status, page.body.len(), &page.headers)),
+            );
+        }
+
+        result
+    }
+
+    async fn send(&self, url: &str, bypass_cache: bool) -> Result<Page> {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string));
+        let domain_headers = host
+            .as_deref()
+            .map(|host| {
+                self.domain_headers
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .filter(|(domain, _)| domain_matches(host, domain))
+                    .flat_map(|(_, headers)| headers.iter().cloned())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let mut request = Request::builder().method(Method::GET).uri(url);
+        if self.impersonate_browser {
+            for (name, value) in BROWSER_HEADERS {
+                if !domain_headers.iter().any(|(header, _)| header == name) {
+                    request = request.header(*name, *value);
+                }
+            }
+        }
+        if bypass_cache {
+            request = request
+                .header("Cache-Control", "no-cache")
+                .header("Pragma", "no-cache");
+        }
+        for (name, value) in domain_headers {
+            request = request.header(name, value);
+        }
+
+        // The session making the fetch brings its own jar
+        let jar = CookieJar::current().or_else(|| self.cookies.clone());
+        let jar = jar.as_ref().zip(host.as_deref());
+        if let Some(cookie) = jar.and_then(|(jar, host)| jar.header_for(host)) {
+            request = request.header(header::COOKIE, cookie);
+        }
+
+        let response = self.http_client.send(request.end()?).await?;
+
+        if let Some((jar, host)) = jar {
+            jar.update_from_response(
+                host,
+                response
+                    .headers()
+                    .get_all(header::SET_COOKIE)
+                    .iter()
+                    .filter_map(|value| value.to_str().ok()),
+            );
+        }
+
+        if let Some(content_type) = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+        {
+            self.policy
+                .read()
+                .unwrap()
+                .check_content_type(url, content_type)?;
+        }
+
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let retry_after = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        let body = response.text().await?;
+        Ok(Page {
+            status,
+            body,
+            fetched_at: Utc::now(),
+            redirects: Vec::new(),
+            retry_after,
+            headers,
+        })
+    }
+}
+
+/// `headers` for `domain`, validated and marked sensitive so they stay out of logs
+fn parse_domain_headers<I>(domain: &str, headers: I) -> Result<DomainHeaders>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let headers = headers
+        .into_iter()
+        .map(|(name, value)| {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("Invalid header name {} for {}", name, domain))?;
+            let mut header_value = HeaderValue::from_str(&value)
+                .with_context(|| format!("Invalid value for header {} for {}", name, domain))?;
+            header_value.set_sensitive(true);
+            Ok((header_name, header_value))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let domain = domain.trim().trim_start_matches('.').to_lowercase();
+    Ok((domain, headers))
+}
+
+/// A Retry-After value, given either in seconds or as an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Where a redirect response to a request for `url` points, resolved against it
+fn http_redirect(page: &Page, url: &str) -> Option<Url> {
+    if !matches!(page.status, 301 | 302 | 303 | 307 | 308) {
+        return None;
+    }
+    let location = page.headers.get(header::LOCATION)?.to_str().ok()?;
+    Url::parse(url).ok()?.join(location).ok()
+}
+
+/// Where a meta refresh or an inline script sends the browser from `html`, if anywhere
+fn client_redirect(html: &str, base: &Url) -> Option<Url> {
+    let document = Html::parse_document(html);
+
+    let meta = Selector::parse("meta[http-equiv][content]").unwrap();
+    let refresh = document
+        .select(&meta)
+        .filter(|element| {
+            element
+                .attr("http-equiv")
+                .is_some_and(|value| value.trim().eq_ignore_ascii_case("refresh"))
+        })
+        .filter_map(|element| parse_refresh(element.attr("content")?))
+        .find(|(delay, _)| *delay <= MAX_REFRESH_DELAY_SECONDS);
+    if let Some((_, target)) = refresh {
+        return base.join(target).ok();
+    }
+
+    let scripts = Selector::parse("script:not([src])").unwrap();
+    let script_words = document
+        .select(&scripts)
+        .flat_map(|script| script.text())
+        .map(|text| text.split_whitespace().count())
+        .sum::<usize>();
+    let words = document
+        .root_element()
+        .text()
+        .map(|text| text.split_whitespace().count())
+        .sum::<usize>();
+    if words.saturating_sub(script_words) >= MAX_REDIRECT_PAGE_WORDS {
+        return None;
+    }
+
+    document
+        .select(&scripts)
+        .find_map(|script| script_redirect(&script.text().collect::<String>()))
+        .and_then(|target| base.join(&target).ok())
+}
+
+/// The delay and target of a refresh such as `0; url='https://example.com/'`
+fn parse_refresh(content: &str) -> Option<(f32, &str)> {
+    let (delay, target) = content.split_once([';', ','])?;
+    let delay = delay.trim().parse().ok()?;
+
+    let target = target.trim();
+    let target = match target.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url") => target[3..].trim_start(),
+        _ => target,
+    };
+    let target = target
+        .strip_prefix('=')
+        .unwrap_or(target)
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'');
+
+    (!target.is_empty()).then_some((delay, target))
+}
+
+/// The string literal assigned to `location` or `location.href`, or passed to
+/// `location.replace()` or `location.assign()`. Computed targets are ignored.
+fn script_redirect(script: &str) -> Option<String> {
+    script
+        .match_indices("location")
+        .find_map(|(index, keyword)| {
+            let rest = &script[index + keyword.len()..];
+            let rest = rest.strip_prefix(".href").unwrap_or(rest).trim_start();
+            let rest = match rest.strip_prefix('=') {
+                Some(rest) if !rest.starts_with('=') => rest,
+                Some(_) => return None,
+                None => rest
+                    .strip_prefix(".replace(")
+                    .or_else(|| rest.strip_prefix(".assign("))?,
+            }
+            .trim_start();
+
+            let quote = rest.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+            let literal = &rest[1..];
+            let end = literal.find(quote)?;
+            let after = literal[end + 1..].trim_start();
+            let complete = after.is_empty() || after.starts_with([';', ')', '\n']);
+            (complete && end > 0).then(|| literal[..end].to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockHttpClient, Reply};
+
+    #[tokio::test]
+    async fn test_follows_http_redirects() {
+        let client = Arc::new(
+            MockHttpClient::new()
+                .with_get(
+                    "https://example.com/old",
+                    Reply::status(301, "").with_header("location", "/new"),
+                )
+                .with_get(
+                    "https://example.com/new",
+                    Reply::status(302, "").with_header("location", "https://www.example.org/"),
+                )
+                .with_get("https://www.example.org/", Reply::ok("<p>Moved here</p>")),
+        );
+        let fetcher = Fetcher::new(client.clone());
+
+        let page = fetcher.fetch("https://example.com/old").await.unwrap();
+        assert_eq!(page.status, 200);
+        assert_eq!(page.body, "<p>Moved here</p>");
+        assert_eq!(client.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_checks_policy_before_every_redirect() {
+        let client = Arc::new(
+            MockHttpClient::new()
+                .with_get(
+                    "https://example.com/go",
+                    Reply::status(307, "").with_header("location", "https://tracker.test/page"),
+                )
+                .with_get("https://tracker.test/page", Reply::ok("tracked")),
+        );
+        let fetcher = Fetcher::new(client.clone()).with_content_policy(
+            ContentPolicy::default().with_blocked_domains("tracking", ["tracker.test"]),
+        );
+
+        assert!(fetcher.fetch("https://example.com/go").await.is_err());
+        assert_eq!(client.request_count("https://tracker.test/page"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_on_redirect_loops() {
+        let client = Arc::new(MockHttpClient::new().with_get(
+            "https://example.com/loop",
+            Reply::status(302, "").with_header("location", "/loop"),
+        ));
+        let fetcher = Fetcher::new(client.clone());
+
+        assert!(fetcher.fetch("https://example.com/loop").await.is_err());
+        assert_eq!(
+            client.request_count("https://example.com/loop"),
+            MAX_HTTP_REDIRECTS + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unwraps_short_urls_within_policy() {
+        let client = Arc::new(
+            MockHttpClient::new()
+                .with_reply(
+                    "HEAD",
+                    "https://bit.ly/abc",
+                    Reply::status(301, "").with_header("location", "https://t.co/xyz"),
+                )
+                .with_reply(
+                    "HEAD",
+                    "https://t.co/xyz",
+                    Reply::status(301, "").with_header("location", "https://example.com/story"),
+                ),
+        );
+        let stats = Arc::new(ServerStats::default());
+        let fetcher = Fetcher::new(client.clone()).with_stats(stats.clone());
+        assert_eq!(
+            fetcher.unwrap_short_url("https://bit.ly/abc").await,
+            Some("https://example.com/story".to_string())
+        );
+        assert_eq!(stats.to_json()["fetches"]["total"], 2);
+
+        // Expansion stops at a blocked hop, which is never requested
+        let fetcher = Fetcher::new(client.clone()).with_content_policy(
+            ContentPolicy::default().with_blocked_domains("shorteners", ["t.co"]),
+        );
+        assert_eq!(
+            fetcher.unwrap_short_url("https://bit.ly/abc").await,
+            Some("https://t.co/xyz".to_string())
+        );
+        assert_eq!(client.request_count("https://t.co/xyz"), 1);
+        assert!(fetcher.check_url("https://t.co/xyz").is_err());
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        let soon = (Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        let wait = parse_retry_after(&soon).unwrap();
+        assert!(wait > Duration::from_secs(80) && wait <= Duration::from_secs(90));
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after("-5"), None);
+    }
+
+    #[test]
+    fn test_parse_refresh() {
+        assert_eq!(
+            parse_refresh("0; url='https://example.com/'"),
+            Some((0.0, "https://example.com/"))
+        );
+        assert_eq!(parse_refresh("5,URL=/next"), Some((5.0, "/next")));
+        assert_eq!(parse_refresh("3; /plain"), Some((3.0, "/plain")));
+        assert_eq!(parse_refresh("30"), None);
+        assert_eq!(parse_refresh("0; url="), None);
+    }
+
+    #[test]
+    fn test_script_redirect() {
+        for script in [
+            "window.location = 'https://example.com/a';",
+            "location.href=\"https://example.com/a\"",
+            "location.replace('https://example.com/a')",
+            "document.location.assign(\"https://example.com/a\");",
+        ] {
+            assert_eq!(
+                script_redirect(script).as_deref(),
+                Some("https://example.com/a"),
+                "{}",
+                script
+            );
+        }
+        // Comparisons and computed targets are not redirects
+        assert_eq!(script_redirect("if (location == 'x') {}"), None);
+        assert_eq!(script_redirect("location = base + '/a';"), None);
+        assert_eq!(script_redirect("location = '/a' + suffix;"), None);
+    }
+
+    #[test]
+    fn test_client_redirect() {
+        let base = Url::parse("https://example.com/start").unwrap();
+        let refresh =
+            r#"<html><head><meta http-equiv="Refresh" content="0; url=/moved"></head></html>"#;
+        assert_eq!(
+            client_redirect(refresh, &base).map(String::from).as_deref(),
+            Some("https://example.com/moved")
+        );
+
+        let slow_refresh =
+            r#"<html><head><meta http-equiv="refresh" content="60; url=/moved"></head></html>"#;
+        assert_eq!(client_redirect(slow_refresh, &base), None);
+
+        let script = r#"<html><body><script>location.replace("/app")</script></body></html>"#;
+        assert_eq!(
+            client_redirect(script, &base).map(String::from).as_deref(),
+            Some("https://example.com/app")
+        );
+
+        // Pages with content of their own only use scripts to navigate later
+        let article = format!(
+            "<html><body><p>{}</p><script>location.replace('/app')</script></body></html>",
+            "word ".repeat(MAX_REDIRECT_PAGE_WORDS)
+        );
+        assert_eq!(client_redirect(&article, &base), None);
+    }
+}
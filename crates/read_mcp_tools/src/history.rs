@@ -0,0 +1,218 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::store::SqliteStore;
+
+const DEFAULT_CAPACITY: usize = 100;
+
+/// Entries are served as resources under this URI, each at `{HISTORY_URI}/{id}`
+pub const HISTORY_URI: &str = "read://history";
+
+/// A single fetch performed by one of the tools
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub id: u64,
+    /// The tool that made the fetch; unknown for entries stored before it was recorded
+    pub tool: Option<String>,
+    pub url: String,
+    pub title: Option<String>,
+    pub status: u16,
+    pub fetched_at: DateTime<Utc>,
+    /// The text returned to the client for this fetch
+    pub content: String,
+    /// What was extracted along with the content, for reads of articles
+    pub metadata: Option<Value>,
+}
+
+/// Most-recent-first log of the fetches performed in this session
+pub struct FetchHistory {
+    capacity: usize,
+    inner: RwLock<Inner>,
+    store: Option<Arc<SqliteStore>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    next_id: u64,
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl Default for FetchHistory {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl FetchHistory {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: RwLock::new(Inner::default()),
+            store: None,
+        }
+    }
+
+    /// Persist every fetch to `store`, starting from the entries it already holds
+    pub fn with_store(self, store: Arc<SqliteStore>) -> Result<Self> {
+        let inner = Inner {
+            next_id: store.last_id()?,
+            entries: store.recent(self.capacity)?.into(),
+        };
+
+        Ok(Self {
+            inner: RwLock::new(inner),
+            store: Some(store),
+            ..self
+        })
+    }
+
+    /// Record a fetch made by `tool` and return the id assigned to it
+    pub fn record(
+        &self,
+        tool: &str,
+        url: &str,
+        status: u16,
+        title: Option<String>,
+        content: &str,
+        metadata: Option<Value>,
+    ) -> u64 {
+        let entry = {
+            let mut inner = self.inner.write().unwrap();
+            inner.next_id += 1;
+            let entry = HistoryEntry {
+                id: inner.next_id,
+                tool: Some(tool.to_string()),
+                url: url.to_string(),
+                title,
+                status,
+                fetched_at: Utc::now(),
+                content: content.to_string(),
+                metadata,
+            };
+            inner.entries.push_front(entry.clone());
+            inner.entries.truncate(self.capacity);
+            entry
+        };
+        let id = entry.id;
+
+        // Written once the lock is released, and off the async workers when on one,
+        // so readers of the history don't wait on the disk
+        if let Some(store) = self.store.clone() {
+            let insert = move || {
+                if let Err(e) = store.insert(&entry) {
+                    eprintln!("Error persisting fetch of {}: {}", entry.url, e);
+                }
+            };
+            match tokio::runtime::Handle::try_current() {
+                Ok(runtime) => drop(runtime.spawn_blocking(insert)),
+                Err(_) => insert(),
+            }
+        }
+
+        id
+    }
+
+    /// All recorded fetches, most recent first
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        self.inner.read().unwrap().entries.iter().cloned().collect()
+    }
+
+    /// Look up a fetch by id, falling back to the store for entries evicted from memory
+    pub fn get(&self, id: u64) -> Option<HistoryEntry> {
+        let entry = self
+            .inner
+            .read()
+            .unwrap()
+            .entries
+            .iter()
+            .find(|e| e.id == id)
+            .cloned();
+
+        entry.or_else(|| {
+            self.store
+                .as_ref()
+                .and_then(|store| store.get(id).ok().flatten())
+        })
+    }
+
+    /// The most recent fetch of `url` made by `tool`, falling back to the store
+    pub fn latest(&self, url: &str, tool: &str) -> Option<HistoryEntry> {
+        let entry = self
+            .inner
+            .read()
+            .unwrap()
+            .entries
+            .iter()
+            .find(|e| e.url == url && e.tool.as_deref() == Some(tool))
+            .cloned();
+
+        entry.or_else(|| {
+            self.store
+                .as_ref()
+                .and_then(|store| store.latest(url, tool).ok().flatten())
+        })
+    }
+
+    /// Distinct recently fetched URLs starting with `prefix`, most recent first
+    pub fn urls_matching(&self, prefix: &str) -> Vec<String> {
+        let mut urls: Vec<String> = Vec::new();
+        for entry in self.inner.read().unwrap().entries.iter() {
+            if entry.url.starts_with(prefix) && !urls.contains(&entry.url) {
+                urls.push(entry.url.clone());
+            }
+        }
+        urls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_store_keeps_entries_and_metadata() {
+        let path = std::env::temp_dir().join(format!("read-mcp-history-{}.db", std::process::id()));
+        let store = Arc::new(SqliteStore::open(&path).unwrap());
+        let history = FetchHistory::default().with_store(store.clone()).unwrap();
+        let id = history.record(
+            "read_url",
+            "https://example.com/story",
+            200,
+            Some("Story".to_string()),
+            "Title: Story",
+            Some(json!({ "author": "Jane Doe", "word_count": 2 })),
+        );
+        history.record(
+            "fetch_raw",
+            "https://example.com/story",
+            200,
+            None,
+            "<html>",
+            None,
+        );
+
+        // A new session over the same store picks up where this one left off
+        let reopened = FetchHistory::with_capacity(1).with_store(store).unwrap();
+        let entry = reopened.get(id).unwrap();
+        assert_eq!(entry.content, "Title: Story");
+        assert_eq!(entry.metadata.unwrap()["author"], "Jane Doe");
+        let latest = reopened
+            .latest("https://example.com/story", "fetch_raw")
+            .unwrap();
+        assert!(latest.metadata.is_none());
+        assert_eq!(
+            reopened.record("read_url", "https://example.com/", 200, None, "", None),
+            id + 2
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,380 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use context_server::{Tool, ToolContent, ToolExecutor};
+use serde_json::{Map, Value, json};
+
+use crate::{
+    Fetcher, StructuredToolExecutor, ToolAnnotations, ToolOutput, extract_url, optional_string,
+    optional_usize,
+};
+
+const DEFAULT_MAX_DEPTH: usize = 4;
+const DEFAULT_MAX_ITEMS: usize = 10;
+
+/// Fetches JSON endpoints and renders them as markdown lists and tables
+pub struct ReadJsonApiTool {
+    fetcher: Arc<Fetcher>,
+}
+
+/// How much of a document is rendered, and whether anything was left out
+struct Limits {
+    max_depth: usize,
+    max_items: usize,
+    truncated: bool,
+}
+
+impl ReadJsonApiTool {
+    pub const ANNOTATIONS: ToolAnnotations = ToolAnnotations {
+        read_only: Some(true),
+        destructive: Some(false),
+        idempotent: Some(true),
+        open_world: Some(true),
+    };
+
+    pub fn new(fetcher: Arc<Fetcher>) -> Self {
+        ReadJsonApiTool { fetcher }
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for ReadJsonApiTool {
+    async fn execute(&self, arguments: Option<Value>) -> Result<Vec<ToolContent>> {
+        Ok(self.execute_structured(arguments).await?.content)
+    }
+
+    fn to_tool(&self) -> Tool {
+        Tool {
+            name: "read_json_api".into(),
+            description: Some(indoc::formatdoc! {"
+                    Fetches a JSON endpoint, such as a REST API response, and renders it as readable markdown: objects become nested lists, arrays of flat objects become tables. Deeply nested values and long arrays are summarized instead of dumped, so large responses stay digestible.
+
+                    Use path to zoom into part of the response, and raise max_depth or max_items when the summary hides something you need.
+                "}),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "format": "uri",
+                        "description": "The URL of the JSON endpoint."
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "A JSON Pointer selecting part of the response to render, e.g. \"/data/items\"."
+                    },
+                    "max_depth": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "default": DEFAULT_MAX_DEPTH,
+                        "description": "Levels of nesting rendered before values are summarized."
+                    },
+                    "max_items": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "default": DEFAULT_MAX_ITEMS,
+                        "description": "Items rendered from each array before the rest are counted."
+                    }
+                },
+                "required": ["url"]
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl StructuredToolExecutor for ReadJsonApiTool {
+    fn output_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string" },
+                "status": { "type": "integer" },
+                "truncated": { "type": "boolean" }
+            },
+            "required": ["url", "status", "truncated"]
+        })
+    }
+
+    async fn execute_structured(&self, arguments: Option<Value>) -> Result<ToolOutput> {
+        let path = optional_string(&arguments, "path")?;
+        let mut limits = Limits {
+            max_depth: optional_usize(&arguments, "max_depth")?.unwrap_or(DEFAULT_MAX_DEPTH),
+            max_items: optional_usize(&arguments, "max_items")?.unwrap_or(DEFAULT_MAX_ITEMS),
+            truncated: false,
+        };
+        if limits.max_depth == 0 || limits.max_items == 0 {
+            return Err(anyhow!("max_depth and max_items must be at least 1"));
+        }
+        let url = extract_url(arguments)?;
+        let expanded_url = self.fetcher.unwrap_short_url(&url).await;
+
+        let page = self
+            .fetcher
+            .fetch(expanded_url.as_deref().unwrap_or(&url))
+            .await?;
+        // Error responses from APIs are usually JSON too, and worth reading
+        let document: Value = serde_json::from_str(&page.body).map_err(|error| {
+            anyhow!(
+                "{} did not return JSON (HTTP {}): {}",
+                url,
+                page.status,
+                error
+            )
+        })?;
+        let value = match &path {
+            Some(path) => document
+                .pointer(path)
+                .ok_or_else(|| anyhow!("{} does not match anything in the response", path))?,
+            None => &document,
+        };
+
+        let mut text = format!("URL: {}\nStatus: {}\n", url, page.status);
+        if let Some(path) = &path {
+            text.push_str(&format!("Path: {}\n", path));
+        }
+        text.push('\n');
+        text.push_str(&render_document(value, &mut limits));
+        if limits.truncated {
+            text.push_str(
+                "\n> Some values were summarized; raise max_depth or max_items, or narrow down with path, to see them.\n",
+            );
+        }
+
+        Ok(ToolOutput {
+            content: vec![ToolContent::Text { text }],
+            structured_content: Some(json!({
+                "url": url,
+                "status": page.status,
+                "truncated": limits.truncated,
+            })),
+        })
+    }
+}
+
+/// Top-level containers of an object get a heading each, so tables have room to render
+fn render_document(value: &Value, limits: &mut Limits) -> String {
+    let mut output = String::new();
+    match value {
+        Value::Object(map) => {
+            let (scalars, containers): (Vec<_>, Vec<_>) =
+                map.iter().partition(|(_, value)| !is_container(value));
+            let scalars = scalars
+                .into_iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect::<Map<_, _>>();
+            render_list(&Value::Object(scalars), 1, 0, &mut output, limits);
+            for (key, value) in containers {
+                output.push_str(&format!("\n## {}\n\n", key));
+                render_section(value, &mut output, limits);
+            }
+        }
+        _ => render_section(value, &mut output, limits),
+    }
+    output
+}
+
+fn render_section(value: &Value, output: &mut String, limits: &mut Limits) {
+    match value {
+        Value::Array(items) if is_table(items) => render_table(items, output, limits),
+        Value::Array(_) | Value::Object(_) => render_list(value, 1, 0, output, limits),
+        scalar => {
+            output.push_str(&format_scalar(scalar));
+            output.push('\n');
+        }
+    }
+}
+
+/// Render a container as a nested list, `depth` levels into the document
+fn render_list(
+    value: &Value,
+    depth: usize,
+    indent: usize,
+    output: &mut String,
+    limits: &mut Limits,
+) {
+    let prefix = "  ".repeat(indent);
+    let render_entry = |label: String, value: &Value, output: &mut String, limits: &mut Limits| {
+        if !is_container(value) {
+            output.push_str(&format!("{}- {}{}\n", prefix, label, format_scalar(value)));
+        } else if depth >= limits.max_depth {
+            limits.truncated = true;
+            output.push_str(&format!("{}- {}{}\n", prefix, label, summarize(value)));
+        } else {
+            output.push_str(&format!("{}- {}\n", prefix, label.trim_end()));
+            render_list(value, depth + 1, indent + 1, output, limits);
+        }
+    };
+
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                render_entry(format!("**{}**: ", key), value, output, limits);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().take(limits.max_items).enumerate() {
+                let label = if is_container(item) {
+                    format!("[{}]", index)
+                } else {
+                    String::new()
+                };
+                render_entry(label, item, output, limits);
+            }
+            if items.len() > limits.max_items {
+                limits.truncated = true;
+                output.push_str(&format!(
+                    "{}- … {} more\n",
+                    prefix,
+                    plural(items.len() - limits.max_items, "item")
+                ));
+            }
+        }
+        scalar => output.push_str(&format!("{}- {}\n", prefix, format_scalar(scalar))),
+    }
+}
+
+/// Whether `items` are objects whose values are all plain scalars
+fn is_table(items: &[Value]) -> bool {
+    !items.is_empty()
+        && items.iter().all(|item| {
+            item.as_object()
+                .is_some_and(|object| !object.values().any(is_container))
+        })
+}
+
+fn render_table(items: &[Value], output: &mut String, limits: &mut Limits) {
+    let rows = items
+        .iter()
+        .take(limits.max_items)
+        .filter_map(Value::as_object)
+        .collect::<Vec<_>>();
+    let mut columns: Vec<&String> = Vec::new();
+    for row in &rows {
+        for key in row.keys() {
+            if !columns.contains(&key) {
+                columns.push(key);
+            }
+        }
+    }
+
+    let cell = |text: String| text.replace('|', "\\|");
+    output.push_str(&format!(
+        "| {} |\n",
+        columns
+            .iter()
+            .map(|column| cell(column.to_string()))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    ));
+    output.push_str(&format!("|{}\n", " --- |".repeat(columns.len())));
+    for row in rows {
+        let cells = columns
+            .iter()
+            .map(|column| row.get(*column).map(format_scalar).unwrap_or_default())
+            .map(cell)
+            .collect::<Vec<_>>();
+        output.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+
+    if items.len() > limits.max_items {
+        limits.truncated = true;
+        output.push_str(&format!(
+            "\n… {} more\n",
+            plural(items.len() - limits.max_items, "row")
+        ));
+    }
+}
+
+fn is_container(value: &Value) -> bool {
+    matches!(value, Value::Object(_) | Value::Array(_))
+}
+
+fn format_scalar(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.split_whitespace().collect::<Vec<_>>().join(" "),
+        other => other.to_string(),
+    }
+}
+
+/// A one-line stand-in for a container beyond the depth limit
+fn summarize(value: &Value) -> String {
+    match value {
+        Value::Object(map) => format!("{{object with {}}}", plural(map.len(), "key")),
+        Value::Array(items) => format!("[array of {}]", plural(items.len(), "item")),
+        scalar => format_scalar(scalar),
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_depth: usize, max_items: usize) -> Limits {
+        Limits {
+            max_depth,
+            max_items,
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn test_render_document() {
+        let document = json!({
+            "count": 2,
+            "next": null,
+            "results": [
+                { "id": 1, "name": "Ada  Lovelace" },
+                { "id": 2, "name": "a|b" },
+            ],
+        });
+        let mut limits = limits(DEFAULT_MAX_DEPTH, DEFAULT_MAX_ITEMS);
+        assert_eq!(
+            render_document(&document, &mut limits),
+            indoc::indoc! {r"
+                - **count**: 2
+                - **next**: null
+
+                ## results
+
+                | id | name |
+                | --- | --- |
+                | 1 | Ada Lovelace |
+                | 2 | a\|b |
+            "}
+        );
+        assert!(!limits.truncated);
+    }
+
+    #[test]
+    fn test_render_nested_lists_within_limits() {
+        let document = json!({
+            "data": { "tags": ["a", "b", "c"], "deep": { "deeper": { "deepest": 1 } } },
+        });
+        let mut limits = limits(2, 2);
+        assert_eq!(
+            render_document(&document, &mut limits),
+            indoc::indoc! {"
+
+                ## data
+
+                - **deep**:
+                  - **deeper**: {object with 1 key}
+                - **tags**:
+                  - a
+                  - b
+                  - … 1 item more
+            "}
+        );
+        assert!(limits.truncated);
+    }
+}
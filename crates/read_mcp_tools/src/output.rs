@@ -0,0 +1,28 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use context_server::{ToolContent, ToolExecutor};
+use serde_json::Value;
+
+/// The result of a tool call, with optional machine-readable fields next to its content
+pub struct ToolOutput {
+    pub content: Vec<ToolContent>,
+    pub structured_content: Option<Value>,
+}
+
+impl From<Vec<ToolContent>> for ToolOutput {
+    fn from(content: Vec<ToolContent>) -> Self {
+        ToolOutput {
+            content,
+            structured_content: None,
+        }
+    }
+}
+
+/// A tool whose results carry `structuredContent` matching a declared output schema
+#[async_trait]
+pub trait StructuredToolExecutor: ToolExecutor {
+    /// JSON Schema describing the `structuredContent` of every result
+    fn output_schema(&self) -> Value;
+
+    async fn execute_structured(&self, arguments: Option<Value>) -> Result<ToolOutput>;
+}
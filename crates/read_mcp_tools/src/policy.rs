@@ -0,0 +1,129 @@
+use anyhow::{Result, anyhow};
+use url::Url;
+
+use crate::cookies::domain_matches;
+
+/// Operator rules on what may be fetched, checked before any content reaches a client
+#[derive(Default)]
+pub struct ContentPolicy {
+    /// Exact types such as `application/x-msdownload`, or whole families such as `video/*`
+    blocked_mime_types: Vec<String>,
+    /// Lowercase, without the leading dot
+    blocked_extensions: Vec<String>,
+    /// Blocked domains with the category of the list that named them
+    blocked_domains: Vec<(String, String)>,
+}
+
+impl ContentPolicy {
+    /// Refuse responses with these content types
+    pub fn with_blocked_mime_types<I, S>(mut self, mime_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.blocked_mime_types.extend(
+            mime_types
+                .into_iter()
+                .map(|mime_type| mime_type.as_ref().trim().to_lowercase()),
+        );
+        self
+    }
+
+    /// Refuse URLs whose path ends in one of these extensions, e.g. `exe`
+    pub fn with_blocked_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.blocked_extensions
+            .extend(extensions.into_iter().map(|extension| {
+                extension
+                    .as_ref()
+                    .trim()
+                    .trim_start_matches('.')
+                    .to_lowercase()
+            }));
+        self
+    }
+
+    /// Refuse `domains` and their subdomains, reporting them as belonging to `category`
+    pub fn with_blocked_domains<I, S>(mut self, category: &str, domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.blocked_domains
+            .extend(domains.into_iter().map(|domain| {
+                (
+                    domain
+                        .as_ref()
+                        .trim()
+                        .trim_start_matches('.')
+                        .to_lowercase(),
+                    category.to_string(),
+                )
+            }));
+        self
+    }
+
+    /// Fail when `url` is on a blocked domain or names a file with a blocked extension
+    pub fn check_url(&self, url: &Url) -> Result<()> {
+        if let Some(host) = url.host_str()
+            && let Some((domain, category)) = self
+                .blocked_domains
+                .iter()
+                .find(|(domain, _)| domain_matches(host, domain))
+        {
+            return Err(anyhow!(
+                "The content policy blocks {} ({} is listed as {})",
+                url,
+                domain,
+                category
+            ));
+        }
+
+        let extension = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .and_then(|file_name| file_name.rsplit_once('.'))
+            .map(|(_, extension)| extension.to_lowercase());
+        if let Some(extension) = extension
+            && self.blocked_extensions.contains(&extension)
+        {
+            return Err(anyhow!(
+                "The content policy blocks .{} files ({})",
+                extension,
+                url
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Fail when a response's `Content-Type` is blocked
+    pub fn check_content_type(&self, url: &str, content_type: &str) -> Result<()> {
+        let mime_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        let family = mime_type.split('/').next().unwrap_or_default();
+
+        let blocked = self.blocked_mime_types.iter().any(|blocked| {
+            *blocked == mime_type
+                || blocked
+                    .strip_suffix("/*")
+                    .is_some_and(|blocked| blocked == family)
+        });
+        if blocked {
+            return Err(anyhow!(
+                "The content policy blocks {} responses ({})",
+                mime_type,
+                url
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,470 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use context_server::{Tool, ToolContent, ToolExecutor};
+use pulldown_cmark::{Options, Parser, html};
+use serde_json::{Value, json};
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    Extraction, ReadUrlTool, StructuredToolExecutor, ToolAnnotations, ToolOutput, extract_url,
+    optional_bool, optional_string,
+};
+
+/// The directories an MCP client lets the server work in, which it lists in
+/// answer to `roots/list`
+#[async_trait]
+pub trait ClientRoots: Send + Sync {
+    /// The client's roots, or `None` when it doesn't declare any
+    async fn roots(&self) -> Option<Vec<PathBuf>>;
+}
+
+/// Writes extracted articles to files under a fixed set of directories, and
+/// within the client's roots when it has them
+pub struct SaveArticleTool {
+    read_url: Arc<ReadUrlTool>,
+    roots: Vec<PathBuf>,
+    client_roots: Option<Arc<dyn ClientRoots>>,
+}
+
+impl SaveArticleTool {
+    pub const ANNOTATIONS: ToolAnnotations = ToolAnnotations {
+        read_only: Some(false),
+        destructive: Some(true),
+        idempotent: Some(false),
+        open_world: Some(true),
+    };
+
+    /// Only paths inside `roots` can be written; relative paths are resolved against the first
+    pub fn new(read_url: Arc<ReadUrlTool>, roots: Vec<PathBuf>) -> Self {
+        SaveArticleTool {
+            read_url,
+            roots,
+            client_roots: None,
+        }
+    }
+
+    /// Also keep to the roots the client declares
+    pub fn with_client_roots(mut self, client_roots: Arc<dyn ClientRoots>) -> Self {
+        self.client_roots = Some(client_roots);
+        self
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for SaveArticleTool {
+    async fn execute(&self, arguments: Option<Value>) -> Result<Vec<ToolContent>> {
+        Ok(self.execute_structured(arguments).await?.content)
+    }
+
+    fn to_tool(&self) -> Tool {
+        let roots = self
+            .roots
+            .iter()
+            .map(|root| root.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Tool {
+            name: "save_article".into(),
+            description: Some(indoc::formatdoc! {"
+                    Fetches a web page, extracts the article the same way read_url does, and saves it to a local file as Markdown or HTML. The file starts with the article's metadata (title, URL, author, dates) and lists the images it references, so saved articles can be collected into a local corpus.
+
+                    Files can only be written inside these directories: {roots}, and inside the client's roots when it declares any.
+                "}),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "format": "uri",
+                        "description": "The URL of the article to save."
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Where to write the file, either absolute or relative to the first allowed directory. Missing directories are created."
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["markdown", "html"],
+                        "description": "Defaults to html for paths ending in .html or .htm, and markdown otherwise."
+                    },
+                    "overwrite": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Replace the file if it already exists."
+                    }
+                },
+                "required": ["url", "path"]
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl StructuredToolExecutor for SaveArticleTool {
+    fn output_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string" },
+                "path": { "type": "string" },
+                "format": { "type": "string", "enum": ["markdown", "html"] },
+                "bytes": { "type": "integer", "minimum": 0 },
+                "assets": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["url", "path", "format", "bytes", "assets"]
+        })
+    }
+
+    async fn execute_structured(&self, arguments: Option<Value>) -> Result<ToolOutput> {
+        let path = optional_string(&arguments, "path")?.ok_or_else(|| anyhow!("missing path"))?;
+        let overwrite = optional_bool(&arguments, "overwrite")?;
+        let html = match optional_string(&arguments, "format")?.as_deref() {
+            Some("markdown") => false,
+            Some("html") => true,
+            Some(other) => return Err(anyhow!("unknown format \"{}\"", other)),
+            None => path.ends_with(".html") || path.ends_with(".htm"),
+        };
+        let url = extract_url(arguments)?;
+
+        // Check the destination before fetching, so a bad path fails fast
+        let roots = self.roots.clone();
+        let client_roots = match &self.client_roots {
+            Some(client_roots) => client_roots.roots().await,
+            None => None,
+        };
+        let path =
+            tokio::task::spawn_blocking(move || resolve(&roots, client_roots.as_deref(), &path))
+                .await??;
+        if !overwrite && tokio::fs::symlink_metadata(&path).await.is_ok() {
+            return Err(anyhow!(
+                "{} already exists; pass overwrite to replace it",
+                path.display()
+            ));
+        }
+
+        let (extraction, fetched_at) = self.read_url.read(&url, false).await?;
+        let assets = image_urls(&extraction.body);
+        let document = if html {
+            to_html(&extraction, &url, fetched_at, &assets)
+        } else {
+            to_markdown(&extraction, &url, fetched_at, &assets)
+        };
+
+        write_document(&path, &document, overwrite)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        let format = if html { "html" } else { "markdown" };
+        Ok(ToolOutput {
+            content: vec![ToolContent::Text {
+                text: format!(
+                    "Saved {} to {} as {} ({} bytes, {} assets)",
+                    url,
+                    path.display(),
+                    format,
+                    document.len(),
+                    assets.len()
+                ),
+            }],
+            structured_content: Some(json!({
+                "url": url,
+                "path": path.display().to_string(),
+                "format": format,
+                "bytes": document.len(),
+                "assets": assets,
+            })),
+        })
+    }
+}
+
+/// Resolve `path` to a location inside one of `roots`, and one of `client_roots`
+/// when given, relative paths against the first root. Missing directories are
+/// only created once the deepest existing one is known, symlinks resolved, to be
+/// inside.
+fn resolve(roots: &[PathBuf], client_roots: Option<&[PathBuf]>, path: &str) -> Result<PathBuf> {
+    let first_root = roots
+        .first()
+        .ok_or_else(|| anyhow!("no directories are configured for saving articles"))?;
+
+    let path = Path::new(path);
+    if path
+        .components()
+        .any(|component| matches!(component, Component::ParentDir))
+    {
+        return Err(anyhow!("path must not contain '..'"));
+    }
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        first_root.join(path)
+    };
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("path must name a file"))?
+        .to_owned();
+    let parent = path.parent().unwrap_or(first_root);
+    let outside = || {
+        anyhow!(
+            "{} is outside the directories articles can be saved to",
+            path.display()
+        )
+    };
+    if !roots.iter().any(|root| parent.starts_with(root)) {
+        return Err(outside());
+    }
+
+    // Symlinks could still lead outside, so compare real locations
+    let existing = parent
+        .ancestors()
+        .find(|ancestor| fs::symlink_metadata(ancestor).is_ok())
+        .ok_or_else(outside)?;
+    let real = existing
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", existing.display()))?;
+    let inside = roots
+        .iter()
+        .any(|root| root.canonicalize().is_ok_and(|root| real.starts_with(root)));
+    if !inside {
+        return Err(outside());
+    }
+
+    let parent = real.join(parent.strip_prefix(existing)?);
+    if let Some(client_roots) = client_roots
+        && !client_roots.iter().any(|root| parent.starts_with(root))
+    {
+        return Err(anyhow!(
+            "{} is outside the roots the client allows",
+            path.display()
+        ));
+    }
+    fs::create_dir_all(&parent)
+        .with_context(|| format!("Failed to create {}", parent.display()))?;
+    Ok(parent.join(file_name))
+}
+
+/// Write `document` to `path` without following a symlink there, and without
+/// replacing an existing file unless `overwrite` is set
+async fn write_document(path: &Path, document: &str, overwrite: bool) -> Result<()> {
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    options.custom_flags(libc::O_NOFOLLOW);
+
+    let mut file = options.open(path).await?;
+    file.write_all(document.as_bytes()).await?;
+    file.flush().await?;
+    Ok(())
+}
+
+/// Metadata fields shared by both output formats, skipping unknown values
+fn front_matter(
+    extraction: &Extraction,
+    url: &str,
+    fetched_at: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let metadata = &extraction.metadata;
+    let mut fields = vec![
+        ("title", metadata.title.clone()),
+        ("url", Some(url.to_string())),
+        ("author", metadata.author.clone()),
+        ("site_name", metadata.site_name.clone()),
+        (
+            "date_published",
+            metadata.date_published.map(|date| date.to_rfc3339()),
+        ),
+        ("fetched_at", Some(fetched_at.to_rfc3339())),
+    ];
+    fields.retain(|(_, value)| value.is_some());
+    fields
+        .into_iter()
+        .map(|(key, value)| (key, value.unwrap_or_default()))
+        .collect()
+}
+
+fn to_markdown(
+    extraction: &Extraction,
+    url: &str,
+    fetched_at: DateTime<Utc>,
+    assets: &[String],
+) -> String {
+    // JSON strings are valid YAML scalars, which takes care of quoting
+    let quote = |value: &str| Value::String(value.to_string()).to_string();
+
+    let mut document = String::from("---\n");
+    for (key, value) in front_matter(extraction, url, fetched_at) {
+        document.push_str(&format!("{}: {}\n", key, quote(&value)));
+    }
+    if !assets.is_empty() {
+        document.push_str("assets:\n");
+        for asset in assets {
+            document.push_str(&format!("  - {}\n", quote(asset)));
+        }
+    }
+    document.push_str("---\n\n");
+
+    if let Some(title) = &extraction.metadata.title {
+        document.push_str(&format!("# {}\n\n", title));
+    }
+    document.push_str(extraction.body.trim());
+    document.push('\n');
+    document
+}
+
+fn to_html(
+    extraction: &Extraction,
+    url: &str,
+    fetched_at: DateTime<Utc>,
+    assets: &[String],
+) -> String {
+    let title = extraction.metadata.title.as_deref().unwrap_or(url);
+
+    let mut document = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    document.push_str(&format!("<title>{}</title>\n", escape(title)));
+    document.push_str(&format!(
+        "<link rel=\"canonical\" href=\"{}\">\n",
+        escape(url)
+    ));
+    for (key, value) in front_matter(extraction, url, fetched_at) {
+        document.push_str(&format!(
+            "<meta name=\"{}\" content=\"{}\">\n",
+            key,
+            escape(&value)
+        ));
+    }
+    document.push_str("</head>\n<body>\n<article>\n");
+    document.push_str(&format!("<h1>{}</h1>\n", escape(title)));
+    html::push_html(
+        &mut document,
+        Parser::new_ext(&extraction.body, Options::ENABLE_TABLES),
+    );
+    document.push_str("</article>\n");
+
+    if !assets.is_empty() {
+        document.push_str("<footer>\n<h2>Assets</h2>\n<ul>\n");
+        for asset in assets {
+            document.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                escape(asset),
+                escape(asset)
+            ));
+        }
+        document.push_str("</ul>\n</footer>\n");
+    }
+    document.push_str("</body>\n</html>\n");
+    document
+}
+
+/// The distinct image URLs referenced by `![alt](url)` in `markdown`
+fn image_urls(markdown: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    let mut rest = markdown;
+    while let Some(start) = rest.find("![") {
+        rest = &rest[start + 2..];
+        let Some(target) = rest.find("](").map(|end| &rest[end + 2..]) else {
+            break;
+        };
+        let Some(end) = target.find(')') else {
+            break;
+        };
+        let url = target[..end].split_whitespace().next().unwrap_or_default();
+        if !url.is_empty() && !urls.iter().any(|known| known == url) {
+            urls.push(url.to_string());
+        }
+        rest = &target[end..];
+    }
+    urls
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for one test, with a `root` to save into and an
+    /// `outside` one next to it
+    fn scratch(name: &str) -> (PathBuf, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("read-mcp-save-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("root")).unwrap();
+        fs::create_dir_all(dir.join("outside")).unwrap();
+        (dir.join("root"), dir.join("outside"))
+    }
+
+    #[test]
+    fn test_resolve_inside_roots() {
+        let (root, outside) = scratch("resolve");
+        let roots = vec![root.clone()];
+
+        let path = resolve(&roots, None, "notes/article.md").unwrap();
+        assert_eq!(path, root.canonicalize().unwrap().join("notes/article.md"));
+        assert!(root.join("notes").is_dir());
+
+        assert!(resolve(&roots, None, "../outside/article.md").is_err());
+
+        // The client's roots narrow the configured ones
+        let client_roots = vec![root.canonicalize().unwrap().join("notes")];
+        assert!(resolve(&roots, Some(&client_roots), "notes/more/article.md").is_ok());
+        assert!(resolve(&roots, Some(&client_roots), "drafts/article.md").is_err());
+        assert!(!root.join("drafts").exists());
+        assert!(resolve(&roots, Some(&[]), "notes/article.md").is_err());
+        assert!(resolve(&roots, None, outside.join("article.md").to_str().unwrap()).is_err());
+        let _ = fs::remove_dir_all(root.parent().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_refuses_symlink_escapes() {
+        let (root, outside) = scratch("symlink");
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+        let roots = vec![root.clone()];
+
+        assert!(resolve(&roots, None, "link/article.md").is_err());
+        // Nothing is created through the link before the check
+        assert!(resolve(&roots, None, "link/new/article.md").is_err());
+        assert!(!outside.join("new").exists());
+        let _ = fs::remove_dir_all(root.parent().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_write_document() {
+        let (root, outside) = scratch("write");
+        let path = root.join("article.md");
+
+        write_document(&path, "first", false).await.unwrap();
+        assert!(write_document(&path, "second", false).await.is_err());
+        write_document(&path, "second", true).await.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+
+        // A symlink planted where the file goes is not followed
+        let target = outside.join("target.md");
+        fs::write(&target, "untouched").unwrap();
+        let link = root.join("link.md");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        assert!(write_document(&link, "written", true).await.is_err());
+        assert!(write_document(&link, "written", false).await.is_err());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "untouched");
+        let _ = fs::remove_dir_all(root.parent().unwrap());
+    }
+}
@@ -0,0 +1,456 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+};
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use context_server::{Tool, ToolContent, ToolExecutor};
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{Value, json};
+
+use crate::{
+    Fetcher, StructuredToolExecutor, ToolAnnotations, ToolOutput, extract_url, optional_string,
+    optional_usize,
+};
+
+const DEFAULT_MAX_URLS: usize = 200;
+const DEFAULT_MAX_SITEMAPS: usize = 50;
+
+/// Lists the pages in a sitemap, following sitemap indexes into their child sitemaps
+pub struct ReadSitemapTool {
+    fetcher: Arc<Fetcher>,
+}
+
+/// Which sitemap entries are listed
+struct Filter {
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    pattern: Option<Regex>,
+}
+
+/// A page listed in a sitemap
+struct Entry {
+    url: String,
+    lastmod: Option<String>,
+}
+
+impl ReadSitemapTool {
+    pub const ANNOTATIONS: ToolAnnotations = ToolAnnotations {
+        read_only: Some(true),
+        destructive: Some(false),
+        idempotent: Some(true),
+        open_world: Some(true),
+    };
+
+    pub fn new(fetcher: Arc<Fetcher>) -> Self {
+        ReadSitemapTool { fetcher }
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for ReadSitemapTool {
+    async fn execute(&self, arguments: Option<Value>) -> Result<Vec<ToolContent>> {
+        Ok(self.execute_structured(arguments).await?.content)
+    }
+
+    fn to_tool(&self) -> Tool {
+        Tool {
+            name: "read_sitemap".into(),
+            description: Some(indoc::formatdoc! {"
+                    Fetches an XML sitemap and lists the page URLs it contains, with their last modification dates. Sitemap indexes are followed into their child sitemaps, and URLs listed more than once are reported once.
+
+                    Large sites list tens of thousands of pages, so narrow the results down with since, until and pattern; at most max_urls are returned, along with how many matched in total.
+                "}),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "format": "uri",
+                        "description": "The URL of the sitemap or sitemap index, e.g. https://example.com/sitemap.xml."
+                    },
+                    "since": {
+                        "type": "string",
+                        "format": "date",
+                        "description": "Only list pages modified on or after this date (YYYY-MM-DD). Pages without a date are left out."
+                    },
+                    "until": {
+                        "type": "string",
+                        "format": "date",
+                        "description": "Only list pages modified on or before this date (YYYY-MM-DD). Pages without a date are left out."
+                    },
+                    "pattern": {
+                        "type": "string",
+                        "description": "A regular expression page URLs must match, e.g. \"/blog/\"."
+                    },
+                    "max_urls": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "default": DEFAULT_MAX_URLS,
+                        "description": "The most URLs to return."
+                    },
+                    "max_sitemaps": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "default": DEFAULT_MAX_SITEMAPS,
+                        "description": "The most sitemaps to fetch, counting the index and its children."
+                    }
+                },
+                "required": ["url"]
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl StructuredToolExecutor for ReadSitemapTool {
+    fn output_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string" },
+                "sitemaps_read": { "type": "integer", "minimum": 0 },
+                "sitemaps_skipped": { "type": "array", "items": { "type": "string" } },
+                "matched": { "type": "integer", "minimum": 0 },
+                "truncated": { "type": "boolean" },
+                "urls": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "url": { "type": "string" },
+                            "lastmod": { "type": ["string", "null"] }
+                        },
+                        "required": ["url", "lastmod"]
+                    }
+                }
+            },
+            "required": ["url", "sitemaps_read", "sitemaps_skipped", "matched", "truncated", "urls"]
+        })
+    }
+
+    async fn execute_structured(&self, arguments: Option<Value>) -> Result<ToolOutput> {
+        let filter = Filter {
+            since: optional_date(&arguments, "since")?,
+            until: optional_date(&arguments, "until")?,
+            pattern: optional_string(&arguments, "pattern")?
+                .map(|pattern| Regex::new(&pattern))
+                .transpose()
+                .map_err(|error| anyhow!("pattern is not a valid regular expression: {}", error))?,
+        };
+        let max_urls = optional_usize(&arguments, "max_urls")?.unwrap_or(DEFAULT_MAX_URLS);
+        let max_sitemaps =
+            optional_usize(&arguments, "max_sitemaps")?.unwrap_or(DEFAULT_MAX_SITEMAPS);
+        if max_urls == 0 || max_sitemaps == 0 {
+            return Err(anyhow!("max_urls and max_sitemaps must be at least 1"));
+        }
+        let url = extract_url(arguments)?;
+
+        let mut queue = VecDeque::from([url.clone()]);
+        let mut visited = HashSet::from([url.clone()]);
+        let mut seen = HashSet::new();
+        let mut entries = Vec::new();
+        let mut matched = 0;
+        let mut sitemaps_read = 0;
+        let mut sitemaps_skipped = Vec::new();
+
+        while let Some(sitemap_url) = queue.pop_front() {
+            if sitemaps_read == max_sitemaps {
+                sitemaps_skipped.push(sitemap_url);
+                sitemaps_skipped.extend(queue.drain(..));
+                break;
+            }
+
+            let page = match self.fetcher.fetch(&sitemap_url).await {
+                Ok(page) if page.status < 400 => page,
+                // The sitemap asked for must be readable; a broken child shouldn't hide the rest
+                Ok(page) if sitemap_url == url => {
+                    return Err(anyhow!("{} answered with HTTP {}", url, page.status));
+                }
+                Err(error) if sitemap_url == url => return Err(error),
+                _ => {
+                    sitemaps_skipped.push(sitemap_url);
+                    continue;
+                }
+            };
+            if sitemap_url == url
+                && !page.body.contains("<urlset")
+                && !page.body.contains("<sitemapindex")
+            {
+                return Err(anyhow!("{} is not an XML sitemap", url));
+            }
+            sitemaps_read += 1;
+
+            let document = Html::parse_document(&page.body);
+            if page.body.contains("<sitemapindex") {
+                for child in children(&document, "sitemap") {
+                    if visited.insert(child.url.clone()) {
+                        queue.push_back(child.url);
+                    }
+                }
+                continue;
+            }
+
+            for entry in children(&document, "url") {
+                if !seen.insert(entry.url.clone()) || !filter.matches(&entry) {
+                    continue;
+                }
+                matched += 1;
+                if entries.len() < max_urls {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        let truncated = matched > entries.len();
+        let mut text = format!(
+            "Sitemap: {}\nSitemaps read: {}\nURLs matched: {}\n",
+            url, sitemaps_read, matched
+        );
+        if !sitemaps_skipped.is_empty() {
+            text.push_str(&format!(
+                "Sitemaps skipped: {} (unreadable or over max_sitemaps)\n",
+                sitemaps_skipped.len()
+            ));
+        }
+        text.push('\n');
+        for entry in &entries {
+            match &entry.lastmod {
+                Some(lastmod) => text.push_str(&format!("- {} ({})\n", entry.url, lastmod)),
+                None => text.push_str(&format!("- {}\n", entry.url)),
+            }
+        }
+        if truncated {
+            text.push_str(&format!(
+                "\n… {} more; narrow down with since, until or pattern, or raise max_urls\n",
+                matched - entries.len()
+            ));
+        }
+
+        Ok(ToolOutput {
+            content: vec![ToolContent::Text { text }],
+            structured_content: Some(json!({
+                "url": url,
+                "sitemaps_read": sitemaps_read,
+                "sitemaps_skipped": sitemaps_skipped,
+                "matched": matched,
+                "truncated": truncated,
+                "urls": entries
+                    .iter()
+                    .map(|entry| json!({ "url": entry.url, "lastmod": entry.lastmod }))
+                    .collect::<Vec<_>>(),
+            })),
+        })
+    }
+}
+
+impl Filter {
+    fn matches(&self, entry: &Entry) -> bool {
+        if self.since.is_some() || self.until.is_some() {
+            // lastmod is a W3C datetime, which always starts with the date
+            let Some(date) = entry
+                .lastmod
+                .as_deref()
+                .and_then(|lastmod| lastmod.get(..10))
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            else {
+                return false;
+            };
+            if self.since.is_some_and(|since| date < since)
+                || self.until.is_some_and(|until| date > until)
+            {
+                return false;
+            }
+        }
+
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(&entry.url))
+    }
+}
+
+/// The `<loc>` and `<lastmod>` of each `tag` element, i.e. `url` in a sitemap
+/// and `sitemap` in a sitemap index
+fn children(document: &Html, tag: &str) -> Vec<Entry> {
+    let selector = Selector::parse(tag).unwrap();
+    let text = |element: &ElementRef, child: &str| {
+        element
+            .child_elements()
+            .find(|child_element| child_element.value().name() == child)
+            .map(|child_element| child_element.text().collect::<String>().trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+
+    document
+        .select(&selector)
+        .filter_map(|element| {
+            Some(Entry {
+                url: text(&element, "loc")?,
+                lastmod: text(&element, "lastmod"),
+            })
+        })
+        .collect()
+}
+
+fn optional_date(arguments: &Option<Value>, key: &str) -> Result<Option<NaiveDate>> {
+    optional_string(arguments, key)?
+        .map(|date| {
+            NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .map_err(|_| anyhow!("{} must be a date formatted as YYYY-MM-DD", key))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockHttpClient, Reply};
+
+    const INDEX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+          <sitemap><loc>https://example.com/blog.xml</loc></sitemap>
+          <sitemap><loc>https://example.com/docs.xml</loc></sitemap>
+          <sitemap><loc>https://example.com/blog.xml</loc></sitemap>
+          <sitemap><loc>https://example.com/missing.xml</loc></sitemap>
+        </sitemapindex>"#;
+
+    const BLOG: &str = r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+          <url><loc>https://example.com/blog/one</loc><lastmod>2024-01-10</lastmod></url>
+          <url><loc>https://example.com/blog/two</loc><lastmod>2024-03-05T10:00:00+00:00</lastmod></url>
+          <url><loc>https://example.com/blog/three</loc></url>
+        </urlset>"#;
+
+    const DOCS: &str = r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+          <url><loc>https://example.com/docs/intro</loc><lastmod>2024-02-01</lastmod></url>
+          <url><loc>https://example.com/blog/one</loc><lastmod>2024-01-10</lastmod></url>
+        </urlset>"#;
+
+    fn sitemap_tool() -> (Arc<MockHttpClient>, ReadSitemapTool) {
+        let client = Arc::new(
+            MockHttpClient::new()
+                .with_get("https://example.com/sitemap.xml", Reply::ok(INDEX))
+                .with_get("https://example.com/blog.xml", Reply::ok(BLOG))
+                .with_get("https://example.com/docs.xml", Reply::ok(DOCS))
+                .with_get("https://example.com/missing.xml", Reply::status(404, "")),
+        );
+        let tool = ReadSitemapTool::new(Arc::new(Fetcher::new(client.clone())));
+        (client, tool)
+    }
+
+    fn text(output: &ToolOutput) -> &str {
+        match &output.content[0] {
+            ToolContent::Text { text } => text,
+            _ => panic!("expected text"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sitemap_index() {
+        let (client, tool) = sitemap_tool();
+        let output = tool
+            .execute_structured(Some(json!({ "url": "https://example.com/sitemap.xml" })))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            text(&output),
+            indoc::indoc! {"
+                Sitemap: https://example.com/sitemap.xml
+                Sitemaps read: 3
+                URLs matched: 4
+                Sitemaps skipped: 1 (unreadable or over max_sitemaps)
+
+                - https://example.com/blog/one (2024-01-10)
+                - https://example.com/blog/two (2024-03-05T10:00:00+00:00)
+                - https://example.com/blog/three
+                - https://example.com/docs/intro (2024-02-01)
+            "}
+        );
+        let structured = output.structured_content.unwrap();
+        assert_eq!(
+            structured["sitemaps_skipped"],
+            json!(["https://example.com/missing.xml"])
+        );
+        assert_eq!(structured["truncated"], false);
+        // The child listed twice in the index is fetched once
+        assert_eq!(client.request_count("https://example.com/blog.xml"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sitemap_filters_and_limits() {
+        let (_, tool) = sitemap_tool();
+        let output = tool
+            .execute_structured(Some(json!({
+                "url": "https://example.com/sitemap.xml",
+                "since": "2024-01-15",
+                "pattern": "/blog/",
+            })))
+            .await
+            .unwrap();
+        let structured = output.structured_content.unwrap();
+        assert_eq!(
+            structured["urls"],
+            json!([{
+                "url": "https://example.com/blog/two",
+                "lastmod": "2024-03-05T10:00:00+00:00",
+            }])
+        );
+
+        let output = tool
+            .execute_structured(Some(json!({
+                "url": "https://example.com/sitemap.xml",
+                "max_urls": 2,
+                "max_sitemaps": 2,
+            })))
+            .await
+            .unwrap();
+        assert!(text(&output).ends_with(
+            "\n… 1 more; narrow down with since, until or pattern, or raise max_urls\n"
+        ));
+        let structured = output.structured_content.unwrap();
+        assert_eq!(structured["matched"], 3);
+        assert_eq!(structured["truncated"], true);
+        assert_eq!(
+            structured["sitemaps_skipped"],
+            json!([
+                "https://example.com/docs.xml",
+                "https://example.com/missing.xml"
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sitemap_must_be_readable() {
+        let client = MockHttpClient::new()
+            .with_get("https://example.com/", Reply::ok("<html></html>"))
+            .with_get("https://example.com/gone.xml", Reply::status(410, ""));
+        let tool = ReadSitemapTool::new(Arc::new(Fetcher::new(Arc::new(client))));
+
+        let error = tool
+            .execute_structured(Some(json!({ "url": "https://example.com/" })))
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("is not an XML sitemap"));
+
+        let error = tool
+            .execute_structured(Some(json!({ "url": "https://example.com/gone.xml" })))
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("HTTP 410"));
+
+        let error = tool
+            .execute_structured(Some(json!({
+                "url": "https://example.com/",
+                "since": "last week",
+            })))
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("YYYY-MM-DD"));
+    }
+}
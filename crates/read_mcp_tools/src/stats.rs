@@ -0,0 +1,263 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use url::Url;
+
+/// Latencies kept per tool to estimate its percentiles
+const LATENCY_SAMPLES: usize = 1000;
+
+/// Counters describing the server's activity, since startup or, when persisted,
+/// since the file was created
+pub struct ServerStats {
+    started_at: Instant,
+    file: Option<PathBuf>,
+    inner: RwLock<Inner>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct Inner {
+    /// When counting began, as RFC 3339
+    since: String,
+    tools: HashMap<String, ToolCounters>,
+    /// Tool calls by the host of their `url` argument
+    domains: HashMap<String, CallCounters>,
+    fetches: u64,
+    fetch_errors: u64,
+    fetch_latency: Duration,
+    bytes_downloaded: u64,
+    statuses: BTreeMap<u16, u64>,
+}
+
+impl Default for Inner {
+    fn default() -> Self {
+        Inner {
+            since: Utc::now().to_rfc3339(),
+            tools: HashMap::new(),
+            domains: HashMap::new(),
+            fetches: 0,
+            fetch_errors: 0,
+            fetch_latency: Duration::ZERO,
+            bytes_downloaded: 0,
+            statuses: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ToolCounters {
+    calls: u64,
+    errors: u64,
+    /// The most recent call latencies, in milliseconds
+    latencies_ms: VecDeque<u64>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct CallCounters {
+    calls: u64,
+    errors: u64,
+}
+
+impl Default for ServerStats {
+    fn default() -> Self {
+        Self {
+            started_at: Instant::now(),
+            file: None,
+            inner: RwLock::new(Inner::default()),
+        }
+    }
+}
+
+impl ServerStats {
+    /// Carry on from the counters saved at `path`, if any, and save to it from now on
+    pub fn with_file(mut self, path: &Path) -> Result<Self> {
+        if path.exists() {
+            let saved = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read stats file {}", path.display()))?;
+            let inner = serde_json::from_str(&saved)
+                .with_context(|| format!("Failed to parse stats file {}", path.display()))?;
+            self.inner = RwLock::new(inner);
+        }
+        self.file = Some(path.to_path_buf());
+        Ok(self)
+    }
+
+    /// Write the counters to the stats file; does nothing when there is none
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+
+        let json = serde_json::to_string(&*self.inner.read().unwrap())?;
+        // Written aside and renamed, so a crash never leaves a truncated file
+        let partial = path.with_extension("partial");
+        fs::write(&partial, json)
+            .with_context(|| format!("Failed to write stats file {}", partial.display()))?;
+        fs::rename(&partial, path)
+            .with_context(|| format!("Failed to replace stats file {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Record a tool call, attributed to the host of `url` when it was given one
+    pub fn record_tool_call(
+        &self,
+        tool: &str,
+        url: Option<&str>,
+        succeeded: bool,
+        latency: Duration,
+    ) {
+        let mut inner = self.inner.write().unwrap();
+
+        let counters = inner.tools.entry(tool.to_string()).or_default();
+        counters.calls += 1;
+        if !succeeded {
+            counters.errors += 1;
+        }
+        if counters.latencies_ms.len() == LATENCY_SAMPLES {
+            counters.latencies_ms.pop_front();
+        }
+        counters.latencies_ms.push_back(latency.as_millis() as u64);
+
+        let host = url
+            .and_then(|url| Url::parse(url).ok())
+            .and_then(|url| url.host_str().map(str::to_string));
+        if let Some(host) = host {
+            let counters = inner.domains.entry(host).or_default();
+            counters.calls += 1;
+            if !succeeded {
+                counters.errors += 1;
+            }
+        }
+    }
+
+    /// Record a fetch that received a response
+    pub fn record_fetch(&self, status: u16, bytes: usize, latency: Duration) {
+        let mut inner = self.inner.write().unwrap();
+        inner.fetches += 1;
+        inner.fetch_latency += latency;
+        inner.bytes_downloaded += bytes as u64;
+        *inner.statuses.entry(status).or_default() += 1;
+    }
+
+    /// Record a fetch that failed before any response was received
+    pub fn record_fetch_error(&self, latency: Duration) {
+        let mut inner = self.inner.write().unwrap();
+        inner.fetches += 1;
+        inner.fetch_errors += 1;
+        inner.fetch_latency += latency;
+    }
+
+    /// Every counter, including the calls made per domain; for the operator
+    pub fn to_json(&self) -> Value {
+        let inner = self.inner.read().unwrap();
+
+        let tools = inner
+            .tools
+            .iter()
+            .map(|(name, counters)| {
+                let mut latencies = counters.latencies_ms.iter().copied().collect::<Vec<_>>();
+                latencies.sort_unstable();
+                (
+                    name.clone(),
+                    json!({
+                        "calls": counters.calls,
+                        "errors": counters.errors,
+                        "error_rate": ratio(counters.errors, counters.calls),
+                        "latency_ms": {
+                            "p50": percentile(&latencies, 50),
+                            "p95": percentile(&latencies, 95),
+                        },
+                    }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>();
+
+        let domains = inner
+            .domains
+            .iter()
+            .map(|(host, counters)| {
+                (
+                    host.clone(),
+                    json!({
+                        "calls": counters.calls,
+                        "errors": counters.errors,
+                        "error_rate": ratio(counters.errors, counters.calls),
+                    }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>();
+
+        let statuses = inner
+            .statuses
+            .iter()
+            .map(|(status, count)| {
+                (
+                    status.to_string(),
+                    json!({
+                        "count": count,
+                        "rate": ratio(*count, inner.fetches),
+                    }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>();
+
+        let average_latency_ms = if inner.fetches > 0 {
+            inner.fetch_latency.as_secs_f64() * 1000.0 / inner.fetches as f64
+        } else {
+            0.0
+        };
+
+        json!({
+            "since": inner.since,
+            "uptime_seconds": self.started_at.elapsed().as_secs(),
+            "tools": tools,
+            "domains": domains,
+            "fetches": {
+                "total": inner.fetches,
+                "transport_errors": inner.fetch_errors,
+                "average_latency_ms": average_latency_ms,
+                "bytes_downloaded": inner.bytes_downloaded,
+                "statuses": statuses,
+            },
+        })
+    }
+
+    /// The counters without the calls made per domain, which would tell each client
+    /// of a shared server which sites the others read
+    pub fn to_shared_json(&self) -> Value {
+        let mut json = self.to_json();
+        if let Some(object) = json.as_object_mut() {
+            object.remove("domains");
+        }
+        json
+    }
+}
+
+fn ratio(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+/// Nearest-rank percentile of `sorted`; missing when there are no samples
+fn percentile(sorted: &[u64], percent: usize) -> Option<u64> {
+    let rank = (sorted.len() * percent).div_ceil(100);
+    sorted.get(rank.saturating_sub(1)).copied()
+}
@@ -0,0 +1,162 @@
+use std::{path::Path, sync::Mutex, time::Duration};
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde_json::Value;
+
+use crate::history::HistoryEntry;
+
+/// SQLite persistence for fetched pages, their metadata and extracted content
+pub struct SqliteStore(Mutex<Connection>);
+
+impl SqliteStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS fetches (
+                id INTEGER PRIMARY KEY,
+                url TEXT NOT NULL,
+                title TEXT,
+                status INTEGER NOT NULL,
+                fetched_at TEXT NOT NULL,
+                content TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS fetches_fetched_at ON fetches (fetched_at);
+            ",
+        )?;
+
+        // Databases created before fetches recorded their tool and metadata lack the columns
+        for column in ["tool", "metadata"] {
+            let exists = connection
+                .prepare("SELECT 1 FROM pragma_table_info('fetches') WHERE name = ?1")?
+                .exists(params![column])?;
+            if !exists {
+                connection.execute(
+                    &format!("ALTER TABLE fetches ADD COLUMN {} TEXT", column),
+                    [],
+                )?;
+            }
+        }
+
+        Ok(SqliteStore(Mutex::new(connection)))
+    }
+
+    pub fn insert(&self, entry: &HistoryEntry) -> Result<()> {
+        self.0.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO fetches (id, url, title, status, fetched_at, content, tool, metadata)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                entry.id as i64,
+                entry.url,
+                entry.title,
+                entry.status,
+                format_timestamp(entry.fetched_at),
+                entry.content,
+                entry.tool,
+                entry.metadata.as_ref().map(Value::to_string),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: u64) -> Result<Option<HistoryEntry>> {
+        let entry = self
+            .0
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT id, url, title, status, fetched_at, content, tool, metadata FROM fetches WHERE id = ?1",
+                params![id as i64],
+                entry_from_row,
+            )
+            .optional()?;
+        Ok(entry)
+    }
+
+    /// The `limit` most recent entries, most recent first
+    pub fn recent(&self, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let connection = self.0.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT id, url, title, status, fetched_at, content, tool, metadata FROM fetches
+             ORDER BY id DESC LIMIT ?1",
+        )?;
+        let entries = statement
+            .query_map(params![limit as i64], entry_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    /// The most recent entry for `url` recorded by `tool`
+    pub fn latest(&self, url: &str, tool: &str) -> Result<Option<HistoryEntry>> {
+        let entry = self
+            .0
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT id, url, title, status, fetched_at, content, tool, metadata FROM fetches
+                 WHERE url = ?1 AND tool = ?2 ORDER BY id DESC LIMIT 1",
+                params![url, tool],
+                entry_from_row,
+            )
+            .optional()?;
+        Ok(entry)
+    }
+
+    pub fn last_id(&self) -> Result<u64> {
+        let id: Option<i64> =
+            self.0
+                .lock()
+                .unwrap()
+                .query_row("SELECT MAX(id) FROM fetches", [], |row| row.get(0))?;
+        Ok(id.unwrap_or_default() as u64)
+    }
+
+    /// Delete entries older than `max_age` and all but the newest `max_entries`,
+    /// returning how many rows were removed
+    pub fn prune(&self, max_entries: usize, max_age: Option<Duration>) -> Result<usize> {
+        let connection = self.0.lock().unwrap();
+        let mut removed = 0;
+
+        if let Some(max_age) = max_age {
+            let cutoff = Utc::now() - chrono::Duration::from_std(max_age)?;
+            removed += connection.execute(
+                "DELETE FROM fetches WHERE fetched_at < ?1",
+                params![format_timestamp(cutoff)],
+            )?;
+        }
+
+        removed += connection.execute(
+            "DELETE FROM fetches WHERE id NOT IN (
+                SELECT id FROM fetches ORDER BY id DESC LIMIT ?1
+            )",
+            params![max_entries as i64],
+        )?;
+
+        Ok(removed)
+    }
+}
+
+// Fixed-width UTC timestamps so they order correctly as text
+fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<HistoryEntry> {
+    let fetched_at: String = row.get(4)?;
+    Ok(HistoryEntry {
+        id: row.get::<_, i64>(0)? as u64,
+        tool: row.get(6)?,
+        url: row.get(1)?,
+        title: row.get(2)?,
+        status: row.get(3)?,
+        fetched_at: DateTime::parse_from_rfc3339(&fetched_at)
+            .map(|d| d.with_timezone(&Utc))
+            .unwrap_or_default(),
+        content: row.get(5)?,
+        metadata: row
+            .get::<_, Option<String>>(7)?
+            .and_then(|metadata| serde_json::from_str(&metadata).ok()),
+    })
+}
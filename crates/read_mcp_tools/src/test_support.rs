@@ -0,0 +1,277 @@
+//! An `HttpClient` answering from canned responses instead of the network, and
+//! one recording real exchanges into a cassette it can later replay, so the
+//! tools can be tested deterministically.
+
+use std::{collections::VecDeque, fs, path::Path, sync::Mutex, time::Duration};
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use http_client::{
+    AsyncBody, HttpClient, Request, Response, ResponseAsyncBodyExt,
+    http::{HeaderMap, HeaderName, HeaderValue},
+};
+use serde::{Deserialize, Serialize};
+
+/// Exchanges recorded by a [`RecordingHttpClient`], stored as JSON
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cassette {}", path.display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse cassette {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write cassette {}", path.display()))
+    }
+}
+
+/// What a [`MockHttpClient`] does with one matching request
+#[derive(Debug, Clone)]
+pub enum Reply {
+    Respond(RecordedResponse),
+    /// Fail as a connection error would
+    Fail(String),
+}
+
+impl Reply {
+    pub fn ok(body: impl Into<String>) -> Self {
+        Reply::status(200, body)
+    }
+
+    pub fn status(status: u16, body: impl Into<String>) -> Self {
+        Reply::Respond(RecordedResponse {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        })
+    }
+
+    /// Add a response header; has no effect on failures
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        if let Reply::Respond(response) = &mut self {
+            response.headers.push((name.to_string(), value.to_string()));
+        }
+        self
+    }
+}
+
+struct Route {
+    method: String,
+    url: String,
+    /// Given out in order, the last one repeating once the others are used up
+    replies: VecDeque<Reply>,
+}
+
+/// Answers requests from the replies set up for their method and URL, in the
+/// order they were added, and records every request it is sent. Requests
+/// nothing was set up for fail.
+#[derive(Default)]
+pub struct MockHttpClient {
+    routes: Mutex<Vec<Route>>,
+    latency: Duration,
+    requests: Mutex<Vec<RecordedRequest>>,
+}
+
+impl MockHttpClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replay the responses of `cassette`, each request in the order recorded
+    pub fn from_cassette(cassette: Cassette) -> Self {
+        cassette
+            .interactions
+            .into_iter()
+            .fold(Self::new(), |client, interaction| {
+                client.with_reply(
+                    &interaction.request.method,
+                    &interaction.request.url,
+                    Reply::Respond(interaction.response),
+                )
+            })
+    }
+
+    /// Answer GET requests for `url`
+    pub fn with_get(self, url: &str, reply: Reply) -> Self {
+        self.with_reply("GET", url, reply)
+    }
+
+    pub fn with_reply(self, method: &str, url: &str, reply: Reply) -> Self {
+        {
+            let mut routes = self.routes.lock().unwrap();
+            match routes
+                .iter_mut()
+                .find(|route| route.method == method && route.url == url)
+            {
+                Some(route) => route.replies.push_back(reply),
+                None => routes.push(Route {
+                    method: method.to_string(),
+                    url: url.to_string(),
+                    replies: VecDeque::from([reply]),
+                }),
+            }
+        }
+        self
+    }
+
+    /// Wait this long before every reply, as a slow network would
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// The requests sent so far, oldest first
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// How many requests were sent for `url`
+    pub fn request_count(&self, url: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.url == url)
+            .count()
+    }
+
+    fn next_reply(&self, request: &RecordedRequest) -> Option<Reply> {
+        let mut routes = self.routes.lock().unwrap();
+        let route = routes
+            .iter_mut()
+            .find(|route| route.method == request.method && route.url == request.url)?;
+        if route.replies.len() > 1 {
+            route.replies.pop_front()
+        } else {
+            route.replies.front().cloned()
+        }
+    }
+}
+
+#[async_trait]
+impl HttpClient for MockHttpClient {
+    async fn send(&self, request: Request<AsyncBody>) -> Result<Response<AsyncBody>> {
+        let request = recorded_request(&request);
+        self.requests.lock().unwrap().push(request.clone());
+
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+        match self.next_reply(&request) {
+            Some(Reply::Respond(response)) => build_response(response),
+            Some(Reply::Fail(message)) => Err(anyhow!(message)),
+            None => Err(anyhow!(
+                "No response set up for {} {}",
+                request.method,
+                request.url
+            )),
+        }
+    }
+}
+
+/// Passes requests on to another client, recording each exchange in a
+/// cassette for a [`MockHttpClient`] to replay
+pub struct RecordingHttpClient<C> {
+    client: C,
+    cassette: Mutex<Cassette>,
+}
+
+impl<C: HttpClient> RecordingHttpClient<C> {
+    pub fn new(client: C) -> Self {
+        RecordingHttpClient {
+            client,
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.cassette().save(path)
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient> HttpClient for RecordingHttpClient<C> {
+    async fn send(&self, request: Request<AsyncBody>) -> Result<Response<AsyncBody>> {
+        let recorded = recorded_request(&request);
+        let response = self.client.send(request).await?;
+
+        let status = response.status().as_u16();
+        let headers = header_pairs(response.headers());
+        let body = response.text().await?;
+        let response = RecordedResponse {
+            status,
+            headers,
+            body,
+        };
+
+        self.cassette
+            .lock()
+            .unwrap()
+            .interactions
+            .push(Interaction {
+                request: recorded,
+                response: response.clone(),
+            });
+        build_response(response)
+    }
+}
+
+fn recorded_request(request: &Request<AsyncBody>) -> RecordedRequest {
+    RecordedRequest {
+        method: request.method().to_string(),
+        url: request.uri().to_string(),
+        headers: header_pairs(request.headers()),
+    }
+}
+
+/// Headers that aren't valid UTF-8 are left out
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn build_response(recorded: RecordedResponse) -> Result<Response<AsyncBody>> {
+    let mut response = Response::builder().status(recorded.status);
+    for (name, value) in &recorded.headers {
+        response = response.header(
+            HeaderName::try_from(name.as_str())?,
+            HeaderValue::try_from(value.as_str())?,
+        );
+    }
+    Ok(response.body(AsyncBody::from(recorded.body))?)
+}
@@ -0,0 +1,114 @@
+use url::Url;
+
+/// The origin URL behind a Google AMP viewer, AMP cache or Google cache URL
+pub(crate) fn canonical_url(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_lowercase();
+    let path = parsed.path();
+
+    // https://www.google.com/amp/s/example.com/article
+    let google = host
+        .strip_prefix("www.")
+        .unwrap_or(&host)
+        .starts_with("google.");
+    if google && let Some(rest) = path.strip_prefix("/amp/") {
+        return origin_url(rest, parsed.query());
+    }
+
+    // https://example-com.cdn.ampproject.org/c/s/example.com/article
+    if host.ends_with(".cdn.ampproject.org") {
+        let (kind, rest) = path.trim_start_matches('/').split_once('/')?;
+        return matches!(kind, "c" | "v" | "i" | "r" | "wp")
+            .then(|| origin_url(rest, parsed.query()))
+            .flatten();
+    }
+
+    // https://webcache.googleusercontent.com/search?q=cache:KEY:https://example.com/article+term
+    if host == "webcache.googleusercontent.com" {
+        let (_, query) = parsed.query_pairs().find(|(key, _)| key == "q")?;
+        let target = query.strip_prefix("cache:")?.split_whitespace().next()?;
+        let target = match target.split_once(':') {
+            Some((key, rest))
+                if !key.eq_ignore_ascii_case("http")
+                    && !key.eq_ignore_ascii_case("https")
+                    && !key.contains(['.', '/']) =>
+            {
+                rest
+            }
+            _ => target,
+        };
+        let target = if target.starts_with("http://") || target.starts_with("https://") {
+            target.to_string()
+        } else {
+            format!("https://{}", target)
+        };
+        return Url::parse(&target).ok().map(String::from);
+    }
+
+    None
+}
+
+/// Rebuild an origin URL from an AMP path such as `s/example.com/article`, where
+/// the `s/` prefix marks an HTTPS origin
+fn origin_url(path: &str, query: Option<&str>) -> Option<String> {
+    let (scheme, rest) = match path.strip_prefix("s/") {
+        Some(rest) => ("https", rest),
+        None => ("http", path),
+    };
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut url = format!("{}://{}", scheme, rest);
+    if let Some(query) = query {
+        url.push('?');
+        url.push_str(query);
+    }
+    Url::parse(&url).ok().map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_url() {
+        assert_eq!(
+            canonical_url("https://www.google.com/amp/s/example.com/article?id=3").as_deref(),
+            Some("https://example.com/article?id=3")
+        );
+        assert_eq!(
+            canonical_url("https://www.google.co.uk/amp/example.com/article").as_deref(),
+            Some("http://example.com/article")
+        );
+        assert_eq!(
+            canonical_url("https://example-com.cdn.ampproject.org/c/s/example.com/article")
+                .as_deref(),
+            Some("https://example.com/article")
+        );
+        assert_eq!(
+            canonical_url(
+                "https://webcache.googleusercontent.com/search?q=cache:AbC123:https://example.com/article+term"
+            )
+            .as_deref(),
+            Some("https://example.com/article")
+        );
+        assert_eq!(
+            canonical_url("https://webcache.googleusercontent.com/search?q=cache:example.com/page")
+                .as_deref(),
+            Some("https://example.com/page")
+        );
+    }
+
+    #[test]
+    fn test_canonical_url_leaves_other_urls() {
+        assert_eq!(canonical_url("https://example.com/amp/s/other.com/"), None);
+        assert_eq!(canonical_url("https://www.google.com/search?q=amp"), None);
+        assert_eq!(
+            canonical_url("https://example-com.cdn.ampproject.org/x/s/example.com/"),
+            None
+        );
+        assert_eq!(canonical_url("https://www.google.com/amp/s/"), None);
+        assert_eq!(canonical_url("not a url"), None);
+    }
+}
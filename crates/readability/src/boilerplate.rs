@@ -0,0 +1,123 @@
+use std::{collections::HashSet, sync::LazyLock};
+
+use regex::Regex;
+
+static LINK_TARGET_PATTERN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\]\([^)]*\)").unwrap());
+
+const ENGLISH: &[&str] = &[
+    "accept",
+    "accept all",
+    "accept all cookies",
+    "accept cookies",
+    "reject all",
+    "manage cookies",
+    "cookie settings",
+    "cookie preferences",
+    "we use cookies",
+    "this website uses cookies",
+    "advertisement",
+    "sponsored content",
+    "subscribe to our newsletter",
+    "sign up for our newsletter",
+    "skip to content",
+    "skip to main content",
+    "share this article",
+    "continue reading",
+    "read more",
+];
+
+const GERMAN: &[&str] = &[
+    "alle akzeptieren",
+    "cookies akzeptieren",
+    "alle ablehnen",
+    "cookie-einstellungen",
+    "werbung",
+    "anzeige",
+    "newsletter abonnieren",
+    "zum inhalt springen",
+    "weiterlesen",
+];
+
+const FRENCH: &[&str] = &[
+    "tout accepter",
+    "accepter les cookies",
+    "tout refuser",
+    "paramètres des cookies",
+    "publicité",
+    "abonnez-vous à notre newsletter",
+    "aller au contenu",
+    "lire la suite",
+];
+
+const SPANISH: &[&str] = &[
+    "aceptar todo",
+    "aceptar cookies",
+    "rechazar todo",
+    "configuración de cookies",
+    "publicidad",
+    "suscríbete a nuestro boletín",
+    "saltar al contenido",
+    "leer más",
+];
+
+const ITALIAN: &[&str] = &[
+    "accetta tutto",
+    "accetta i cookie",
+    "rifiuta tutto",
+    "impostazioni dei cookie",
+    "pubblicità",
+    "iscriviti alla newsletter",
+    "vai al contenuto",
+    "continua a leggere",
+];
+
+/// The built-in boilerplate phrases for a document language such as `de-AT`;
+/// English phrases are always included since they appear on sites in every language
+pub fn default_boilerplate_phrases(language: Option<&str>) -> Vec<String> {
+    let primary = language
+        .and_then(|lang| lang.split(['-', '_']).next())
+        .map(str::to_lowercase);
+    let localized: &[&str] = match primary.as_deref() {
+        Some("de") => GERMAN,
+        Some("fr") => FRENCH,
+        Some("es") => SPANISH,
+        Some("it") => ITALIAN,
+        _ => &[],
+    };
+
+    ENGLISH
+        .iter()
+        .chain(localized)
+        .map(|phrase| phrase.to_string())
+        .collect()
+}
+
+/// Remove every line of `markdown` that consists of nothing but one of `phrases`,
+/// ignoring case, punctuation, markdown syntax and link targets
+pub fn strip_boilerplate(markdown: &str, phrases: &[String]) -> String {
+    let phrases = phrases
+        .iter()
+        .map(|phrase| normalize(phrase))
+        .collect::<HashSet<_>>();
+
+    let mut stripped = String::new();
+    for line in markdown.lines() {
+        let text = normalize(&LINK_TARGET_PATTERN.replace_all(line, "]"));
+        if !text.is_empty() && phrases.contains(&text) {
+            continue;
+        }
+        stripped.push_str(line);
+        stripped.push('\n');
+    }
+    stripped
+}
+
+fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
@@ -0,0 +1,133 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// Month names and their common abbreviations in English, German, French,
+/// Spanish and Italian, lowercase and with the unaccented spellings people
+/// type as well. Abbreviations that are also ordinary words, such as the
+/// Italian `set` or Spanish `ago`, are left out.
+const MONTH_NAMES: &[(&str, u32)] = &[
+    // English
+    ("january", 1),
+    ("jan", 1),
+    ("february", 2),
+    ("feb", 2),
+    ("march", 3),
+    ("mar", 3),
+    ("april", 4),
+    ("apr", 4),
+    ("may", 5),
+    ("june", 6),
+    ("jun", 6),
+    ("july", 7),
+    ("jul", 7),
+    ("august", 8),
+    ("aug", 8),
+    ("september", 9),
+    ("sept", 9),
+    ("sep", 9),
+    ("october", 10),
+    ("oct", 10),
+    ("november", 11),
+    ("nov", 11),
+    ("december", 12),
+    ("dec", 12),
+    // German
+    ("januar", 1),
+    ("jänner", 1),
+    ("februar", 2),
+    ("märz", 3),
+    ("maerz", 3),
+    ("marz", 3),
+    ("mär", 3),
+    ("mai", 5),
+    ("juni", 6),
+    ("juli", 7),
+    ("oktober", 10),
+    ("okt", 10),
+    ("dezember", 12),
+    ("dez", 12),
+    // French
+    ("janvier", 1),
+    ("janv", 1),
+    ("février", 2),
+    ("fevrier", 2),
+    ("févr", 2),
+    ("fevr", 2),
+    ("mars", 3),
+    ("avril", 4),
+    ("avr", 4),
+    ("juin", 6),
+    ("juillet", 7),
+    ("juil", 7),
+    ("août", 8),
+    ("aout", 8),
+    ("septembre", 9),
+    ("octobre", 10),
+    ("novembre", 11),
+    ("décembre", 12),
+    ("decembre", 12),
+    ("déc", 12),
+    // Spanish
+    ("enero", 1),
+    ("ene", 1),
+    ("febrero", 2),
+    ("marzo", 3),
+    ("abril", 4),
+    ("abr", 4),
+    ("mayo", 5),
+    ("junio", 6),
+    ("julio", 7),
+    ("agosto", 8),
+    ("septiembre", 9),
+    ("setiembre", 9),
+    ("octubre", 10),
+    ("noviembre", 11),
+    ("diciembre", 12),
+    ("dic", 12),
+    // Italian
+    ("gennaio", 1),
+    ("gen", 1),
+    ("febbraio", 2),
+    ("aprile", 4),
+    ("maggio", 5),
+    ("mag", 5),
+    ("giugno", 6),
+    ("giu", 6),
+    ("luglio", 7),
+    ("lug", 7),
+    ("settembre", 9),
+    ("ottobre", 10),
+    ("ott", 10),
+    ("dicembre", 12),
+];
+
+/// `2024年3月5日`, as written in Japanese and Chinese; the day is optional
+static CJK_DATE_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\d{4})\s*年\s*(\d{1,2})\s*月(?:\s*(\d{1,2})\s*日)?").unwrap());
+
+/// The number of the first month named in `text`, in any of the languages
+/// above; only whole words count, so `mai` isn't found in `email`
+pub(crate) fn month_in(text: &str) -> Option<u32> {
+    text.split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .find_map(|word| {
+            let word = word.to_lowercase();
+            MONTH_NAMES
+                .iter()
+                .find(|(name, _)| *name == word)
+                .map(|&(_, month)| month)
+        })
+}
+
+/// Year, month and day of a `年月日` date in `text`, the day being 1 when
+/// only the month is given
+pub(crate) fn cjk_date(text: &str) -> Option<(i32, u32, u32)> {
+    let captures = CJK_DATE_PATTERN.captures(text)?;
+    let year = captures[1].parse().ok()?;
+    let month = captures[2].parse().ok()?;
+    let day = captures
+        .get(3)
+        .map_or(Some(1), |day| day.as_str().parse().ok())?;
+    Some((year, month, day))
+}
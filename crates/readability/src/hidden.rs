@@ -0,0 +1,138 @@
+use scraper::{Html, Node, node::Element};
+
+/// Remove comments and elements hidden from readers by the `hidden` attribute or
+/// inline styles. Pages use them to smuggle instructions past human readers, so
+/// they never reach the extracted text. Stylesheets are not evaluated.
+pub(crate) fn remove_hidden(document: &mut Html) {
+    let hidden = document
+        .tree
+        .nodes()
+        .filter(|node| match node.value() {
+            Node::Comment(_) => true,
+            Node::Element(element) => is_hidden(element),
+            _ => false,
+        })
+        .map(|node| node.id())
+        .collect::<Vec<_>>();
+
+    for id in hidden {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+}
+
+/// Serialize `html` again without comments and hidden elements, for converters
+/// that work on the raw page
+pub fn strip_hidden_content(html: &str) -> String {
+    let mut document = Html::parse_document(html);
+    remove_hidden(&mut document);
+    document.html()
+}
+
+pub(crate) fn is_hidden(element: &Element) -> bool {
+    // `until-found` content is revealed by find-in-page, so readers can get to it
+    if element
+        .attr("hidden")
+        .is_some_and(|value| !value.trim().eq_ignore_ascii_case("until-found"))
+    {
+        return true;
+    }
+
+    let Some(style) = element.attr("style") else {
+        return false;
+    };
+    let declarations = style
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .map(|(property, value)| {
+            let value = value.trim().to_lowercase();
+            let value = value.trim_end_matches("!important").trim().to_string();
+            (property.trim().to_lowercase(), value)
+        })
+        .collect::<Vec<_>>();
+    let get = |name: &str| {
+        declarations
+            .iter()
+            .rev()
+            .find(|(property, _)| property == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let zero = |value: &str| {
+        let number = value.trim_end_matches(|c: char| c.is_alphabetic() || c == '%');
+        number.parse::<f32>().is_ok_and(|number| number == 0.0)
+    };
+
+    if get("display") == Some("none")
+        || matches!(get("visibility"), Some("hidden" | "collapse"))
+        || get("opacity").is_some_and(zero)
+        || get("font-size").is_some_and(zero)
+    {
+        return true;
+    }
+
+    // Collapsed boxes that clip their content
+    let clipped = matches!(get("overflow"), Some("hidden" | "clip"));
+    if clipped && (get("height").is_some_and(zero) || get("width").is_some_and(zero)) {
+        return true;
+    }
+
+    // Text drawn in the color of its background, e.g. white on white
+    let color = get("color").and_then(normalize_color);
+    if color.as_deref() == Some("transparent") {
+        return true;
+    }
+    let background = get("background-color")
+        .or_else(|| get("background"))
+        .and_then(normalize_color);
+    color.is_some() && color == background
+}
+
+/// A color as `#rrggbb` or `transparent`, for the notations simple enough to compare
+fn normalize_color(value: &str) -> Option<String> {
+    let value = value.trim();
+    match value {
+        "transparent" => return Some("transparent".to_string()),
+        "white" => return Some("#ffffff".to_string()),
+        "black" => return Some("#000000".to_string()),
+        _ => {}
+    }
+
+    if let Some(hex) = value.strip_prefix('#') {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        return match hex.len() {
+            3 => Some(format!(
+                "#{}",
+                hex.chars().flat_map(|c| [c, c]).collect::<String>()
+            )),
+            6 => Some(format!("#{}", hex)),
+            _ => None,
+        };
+    }
+
+    let arguments = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))?
+        .strip_suffix(')')?;
+    let channels = arguments
+        .split([',', ' ', '/'])
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    if channels.get(3).is_some_and(|alpha| {
+        alpha
+            .trim_end_matches('%')
+            .parse::<f32>()
+            .is_ok_and(|alpha| alpha == 0.0)
+    }) {
+        return Some("transparent".to_string());
+    }
+    let rgb = channels
+        .iter()
+        .take(3)
+        .map(|channel| channel.parse::<u8>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    (rgb.len() == 3).then(|| format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2]))
+}
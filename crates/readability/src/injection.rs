@@ -0,0 +1,87 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// Text addressed to a model rather than to the page's readers
+static INSTRUCTION_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    [
+        r"(?i)\b(ignore|disregard|forget|override)\s+(all\s+)?(of\s+)?(the\s+|your\s+|any\s+)?(previous|prior|above|earlier|preceding|original)\s+(instructions|prompts?|directions|rules|messages)",
+        r"(?i)\b(reveal|print|output|repeat|show)\s+(me\s+)?(your|the)\s+(system\s+prompt|initial\s+instructions|hidden\s+instructions)",
+        r"(?i)\b(do\s+not|don't|never)\s+(tell|inform|alert|mention\s+this\s+to)\s+the\s+user",
+        r"(?i)\bnew\s+instructions\s*:",
+        r"(?i)\byou\s+are\s+now\s+(a|an|in)\s+",
+        r"(?i)<\|?(im_start|im_end|endoftext|system)\|?>",
+        r"(?im)^\s*\[?(system|assistant)\]?\s*:\s+\S",
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).unwrap())
+    .collect()
+});
+
+/// JSON shaped like a tool or function call
+static TOOL_CALL_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"\{\s*"(name|tool|tool_name|function|recipient_name)"\s*:\s*"[^"]+"\s*,\s*"(arguments|parameters|input|args)"\s*:"#,
+    )
+    .unwrap()
+});
+
+/// Long runs of base64, which can carry instructions past a reader
+static ENCODED_BLOB_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9+/]{200,}={0,2}").unwrap());
+
+/// Longest excerpt reported for a signal, in characters
+const MAX_EXCERPT_LENGTH: usize = 80;
+
+/// A passage of extracted text that looks like an attempt to instruct the model reading it
+#[derive(Debug, Clone, PartialEq)]
+pub struct InjectionSignal {
+    /// `instruction`, `tool_call` or `encoded_blob`
+    pub kind: &'static str,
+    pub excerpt: String,
+}
+
+/// Scan `text` for instruction-like phrases, tool-call JSON and base64 blobs.
+/// These are heuristics: a match means the text deserves suspicion, not that it is an attack.
+pub fn detect_injection(text: &str) -> Vec<InjectionSignal> {
+    let mut signals = Vec::new();
+
+    for pattern in INSTRUCTION_PATTERNS.iter() {
+        signals.extend(pattern.find_iter(text).map(|found| InjectionSignal {
+            kind: "instruction",
+            excerpt: excerpt(found.as_str()),
+        }));
+    }
+
+    signals.extend(
+        TOOL_CALL_PATTERN
+            .find_iter(text)
+            .map(|found| InjectionSignal {
+                kind: "tool_call",
+                excerpt: excerpt(found.as_str()),
+            }),
+    );
+
+    // Inline images are legitimately encoded
+    signals.extend(
+        ENCODED_BLOB_PATTERN
+            .find_iter(text)
+            .filter(|found| !text[..found.start()].ends_with("base64,"))
+            .map(|found| InjectionSignal {
+                kind: "encoded_blob",
+                excerpt: excerpt(found.as_str()),
+            }),
+    );
+
+    signals
+}
+
+fn excerpt(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= MAX_EXCERPT_LENGTH {
+        return text;
+    }
+    let mut truncated = text.chars().take(MAX_EXCERPT_LENGTH).collect::<String>();
+    truncated.push('…');
+    truncated
+}
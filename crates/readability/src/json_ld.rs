@@ -0,0 +1,125 @@
+use scraper::{Html, Selector};
+use serde_json::Value;
+
+/// schema.org types describing the article a page is about
+const ARTICLE_TYPES: &[&str] = &[
+    "Article",
+    "NewsArticle",
+    "AnalysisNewsArticle",
+    "OpinionNewsArticle",
+    "ReportageNewsArticle",
+    "BlogPosting",
+    "LiveBlogPosting",
+    "SocialMediaPosting",
+    "ScholarlyArticle",
+    "TechArticle",
+    "Report",
+];
+
+/// Article metadata declared in `<script type="application/ld+json">`, which
+/// is more reliable than anything guessed from the markup when present
+#[derive(Debug, Default)]
+pub(crate) struct JsonLdMetadata {
+    pub title: Option<String>,
+    pub byline: Option<String>,
+    /// As written, e.g. `2024-01-31T12:00:00+01:00`
+    pub date_published: Option<String>,
+    pub date_modified: Option<String>,
+    pub site_name: Option<String>,
+}
+
+/// The metadata of the first article described in the document's JSON-LD;
+/// scripts that aren't valid JSON are skipped
+pub(crate) fn parse_json_ld(document: &Html) -> JsonLdMetadata {
+    let selector = Selector::parse("script[type=\"application/ld+json\"]").unwrap();
+    let mut nodes = Vec::new();
+    for script in document.select(&selector) {
+        let text = script.text().collect::<String>();
+        if let Ok(value) = serde_json::from_str::<Value>(text.trim()) {
+            collect_nodes(value, &mut nodes);
+        }
+    }
+
+    let Some(article) = nodes.iter().find(|node| is_article(node)) else {
+        return JsonLdMetadata::default();
+    };
+    let resolve = |value: &Value| -> Value {
+        // Nodes in a `@graph` often refer to each other by `@id` alone
+        match value.get("@id").and_then(Value::as_str) {
+            Some(id) if value.as_object().is_some_and(|object| object.len() == 1) => nodes
+                .iter()
+                .find(|node| node.get("@id").and_then(Value::as_str) == Some(id))
+                .cloned()
+                .unwrap_or_else(|| value.clone()),
+            _ => value.clone(),
+        }
+    };
+
+    let authors = match article.get("author") {
+        Some(Value::Array(authors)) => authors.iter().filter_map(|a| name(&resolve(a))).collect(),
+        Some(author) => name(&resolve(author)).into_iter().collect(),
+        None => Vec::new(),
+    };
+
+    JsonLdMetadata {
+        title: text(article.get("headline")).or_else(|| text(article.get("name"))),
+        byline: join_names(authors),
+        date_published: text(article.get("datePublished")),
+        date_modified: text(article.get("dateModified")),
+        site_name: article
+            .get("publisher")
+            .and_then(|publisher| name(&resolve(publisher))),
+    }
+}
+
+/// Gather every object of a JSON-LD document, flattening arrays and `@graph`s
+fn collect_nodes(value: Value, nodes: &mut Vec<Value>) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                collect_nodes(value, nodes);
+            }
+        }
+        Value::Object(mut object) => {
+            if let Some(graph) = object.remove("@graph") {
+                collect_nodes(graph, nodes);
+            }
+            nodes.push(Value::Object(object));
+        }
+        _ => {}
+    }
+}
+
+fn is_article(node: &Value) -> bool {
+    match node.get("@type") {
+        Some(Value::String(kind)) => ARTICLE_TYPES.contains(&kind.as_str()),
+        Some(Value::Array(kinds)) => kinds
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|kind| ARTICLE_TYPES.contains(&kind)),
+        _ => false,
+    }
+}
+
+/// A person or organization's name, given either as a plain string or as a `name` property
+fn name(value: &Value) -> Option<String> {
+    match value {
+        Value::String(_) => text(Some(value)),
+        _ => text(value.get("name")),
+    }
+}
+
+fn text(value: Option<&Value>) -> Option<String> {
+    let text = value?.as_str()?.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// `A`, `A and B`, or `A, B and C`
+fn join_names(mut names: Vec<String>) -> Option<String> {
+    let last = names.pop()?;
+    if names.is_empty() {
+        Some(last)
+    } else {
+        Some(format!("{} and {}", names.join(", "), last))
+    }
+}
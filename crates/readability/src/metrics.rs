@@ -0,0 +1,173 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+static LINK_TARGET_PATTERN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\]\([^)]*\)").unwrap());
+
+static SENTENCE_END_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"[.!?]+["'”’)]*(\s|$)"#).unwrap());
+
+/// Basic statistics about the article text, to help callers calibrate the
+/// length and register of anything they derive from it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TextMetrics {
+    pub word_count: usize,
+    pub sentence_count: usize,
+    /// Mean number of words per sentence
+    pub average_sentence_length: f32,
+    /// Flesch reading ease, from 0 (very hard) to 100 (very easy); English text only
+    pub flesch_reading_ease: Option<f32>,
+    /// Flesch-Kincaid US school grade level; English text only
+    pub flesch_kincaid_grade: Option<f32>,
+}
+
+impl TextMetrics {
+    /// Measure the prose in `markdown`, skipping code blocks and link targets.
+    /// Syllable-based scores are only computed when `english` is set.
+    pub fn from_markdown(markdown: &str, english: bool) -> Self {
+        let mut word_count = 0;
+        let mut sentence_count = 0;
+        let mut syllable_count = 0;
+        let mut in_code_block = false;
+
+        for line in markdown.lines() {
+            let line = line.trim();
+            if line.starts_with("```") {
+                in_code_block = !in_code_block;
+                continue;
+            }
+            if in_code_block || line.is_empty() || line.starts_with("![") {
+                continue;
+            }
+
+            let line = LINK_TARGET_PATTERN.replace_all(line, "]");
+            let words = line
+                .split_whitespace()
+                .filter(|word| word.chars().any(char::is_alphanumeric))
+                .collect::<Vec<_>>();
+            if words.is_empty() {
+                continue;
+            }
+
+            word_count += words.len();
+            syllable_count += words
+                .iter()
+                .map(|word| count_syllables(word))
+                .sum::<usize>();
+
+            // Headings, list items and captions often end without punctuation
+            // but still read as a sentence of their own
+            let ends = SENTENCE_END_PATTERN.find_iter(&line).count();
+            let terminated = SENTENCE_END_PATTERN
+                .find_iter(&line)
+                .last()
+                .is_some_and(|m| m.end() == line.len());
+            sentence_count += if terminated { ends } else { ends + 1 };
+        }
+
+        if word_count == 0 {
+            return TextMetrics::default();
+        }
+
+        let words_per_sentence = word_count as f32 / sentence_count as f32;
+        let syllables_per_word = syllable_count as f32 / word_count as f32;
+
+        TextMetrics {
+            word_count,
+            sentence_count,
+            average_sentence_length: words_per_sentence,
+            flesch_reading_ease: english
+                .then_some(206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word),
+            flesch_kincaid_grade: english
+                .then_some(0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59),
+        }
+    }
+}
+
+/// Words read per minute in scripts that separate words with spaces
+const WORDS_PER_MINUTE: f32 = 238.0;
+
+/// Characters read per minute in Chinese and Japanese
+const CJK_CHARACTERS_PER_MINUTE: f32 = 500.0;
+
+/// The words in `markdown`, skipping code blocks, images and link targets, and
+/// the minutes it takes to read them. Chinese and Japanese don't separate words
+/// with spaces, so each of their characters counts as a word of its own.
+pub(crate) fn reading_length(markdown: &str) -> (usize, u32) {
+    let mut words = 0;
+    let mut cjk_characters = 0;
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let line = line.trim();
+        if line.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block || line.starts_with("![") {
+            continue;
+        }
+
+        let line = LINK_TARGET_PATTERN.replace_all(line, "]");
+        for word in line.split_whitespace() {
+            let cjk = word.chars().filter(|&c| is_cjk(c)).count();
+            cjk_characters += cjk;
+            // Latin text mixed in with CJK characters still counts as a word
+            if word.chars().any(|c| c.is_alphanumeric() && !is_cjk(c)) {
+                words += 1;
+            }
+        }
+    }
+
+    let minutes =
+        words as f32 / WORDS_PER_MINUTE + cjk_characters as f32 / CJK_CHARACTERS_PER_MINUTE;
+    let minutes = if words + cjk_characters == 0 {
+        0
+    } else {
+        (minutes.ceil() as u32).max(1)
+    };
+    (words + cjk_characters, minutes)
+}
+
+/// Han ideographs and Japanese kana; Hangul is left out since Korean separates
+/// words with spaces
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' // Hiragana and Katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+        | '\u{FF66}'..='\u{FF9F}' // Halfwidth Katakana
+        | '\u{20000}'..='\u{2FA1F}' // Extensions B and beyond
+    )
+}
+
+/// Estimate the syllables in an English word by counting vowel groups
+fn count_syllables(word: &str) -> usize {
+    let word = word
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_lowercase())
+        .collect::<String>();
+    if word.is_empty() {
+        return 1;
+    }
+
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for c in word.chars() {
+        let vowel = matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+
+    // A trailing silent "e", as in "make", doesn't add a syllable
+    if word.ends_with('e') && !word.ends_with("le") && count > 1 {
+        count -= 1;
+    }
+
+    count.max(1)
+}
@@ -0,0 +1,91 @@
+/// What `Article::content` is written as
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ContentFormat {
+    #[default]
+    Markdown,
+    /// Plain text without markup, whitespace collapsed and paragraphs
+    /// separated by blank lines
+    Text,
+}
+
+/// Thresholds and weights used to find the article in a page; the defaults
+/// follow readability.js
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadabilityOptions {
+    pub(crate) min_text_length: usize,
+    pub(crate) top_candidates: usize,
+    pub(crate) weight_classes: bool,
+    pub(crate) class_weight: f32,
+    pub(crate) char_threshold: usize,
+    pub(crate) max_elements: Option<usize>,
+    pub(crate) max_parent_levels: usize,
+    pub(crate) content_format: ContentFormat,
+}
+
+impl Default for ReadabilityOptions {
+    fn default() -> Self {
+        Self {
+            min_text_length: 25,
+            top_candidates: 5,
+            weight_classes: true,
+            class_weight: 25.0,
+            char_threshold: 500,
+            max_elements: None,
+            max_parent_levels: 5,
+            content_format: ContentFormat::Markdown,
+        }
+    }
+}
+
+impl ReadabilityOptions {
+    /// Characters of text an element needs to be scored at all; 25 by default
+    pub fn with_min_text_length(mut self, length: usize) -> Self {
+        self.min_text_length = length;
+        self
+    }
+
+    /// How many of the best candidates are compared when looking for a common
+    /// ancestor holding the whole article; 5 by default
+    pub fn with_top_candidates(mut self, count: usize) -> Self {
+        self.top_candidates = count;
+        self
+    }
+
+    /// Whether class names and IDs such as `comment` or `article` raise or lower
+    /// scores; enabled by default
+    pub fn with_class_weights(mut self, enabled: bool) -> Self {
+        self.weight_classes = enabled;
+        self
+    }
+
+    /// Points a matching class name or ID adds or takes away; 25 by default
+    pub fn with_class_weight(mut self, weight: f32) -> Self {
+        self.class_weight = weight;
+        self
+    }
+
+    /// Characters of text below which extraction is retried without dropping
+    /// unlikely elements or weighing classes; 500 by default, 0 never retries
+    pub fn with_char_threshold(mut self, threshold: usize) -> Self {
+        self.char_threshold = threshold;
+        self
+    }
+
+    /// Refuse documents with more elements than this; unlimited by default
+    pub fn with_max_elements(mut self, max: usize) -> Self {
+        self.max_elements = Some(max);
+        self
+    }
+
+    /// How many levels of ancestors a paragraph's score is passed up to; 5 by default
+    pub fn with_max_parent_levels(mut self, levels: usize) -> Self {
+        self.max_parent_levels = levels;
+        self
+    }
+
+    /// Write the article's content as markdown, the default, or plain text
+    pub fn with_content_format(mut self, format: ContentFormat) -> Self {
+        self.content_format = format;
+        self
+    }
+}
@@ -0,0 +1,64 @@
+use scraper::{ElementRef, Html, Selector};
+
+use crate::{POSITIVE_PATTERNS, UNLIKELY_PATTERNS, hidden::is_hidden};
+
+/// Characters of text a paragraph needs before it counts towards the score
+const MIN_CONTENT_LENGTH: usize = 140;
+
+/// Score above which a page is taken to hold an article
+const MIN_SCORE: f64 = 20.0;
+
+/// Guess, without scoring the whole document, whether extracting an article
+/// from `html` will work, as readability.js's `isProbablyReaderable` does.
+/// Visible paragraphs outside lists and unlikely containers add the square
+/// root of how far their text runs past 140 characters; the page passes once
+/// the total goes over 20. Index and landing pages rarely do.
+pub fn is_probably_readerable(html: &str) -> bool {
+    let document = Html::parse_document(html);
+    let paragraphs = Selector::parse("p, pre, article").unwrap();
+    let line_breaks = Selector::parse("div > br").unwrap();
+
+    // Divs broken up with <br>s stand in for paragraphs on older pages
+    let mut nodes = document.select(&paragraphs).collect::<Vec<_>>();
+    for br in document.select(&line_breaks) {
+        if let Some(div) = br.parent().and_then(ElementRef::wrap)
+            && !nodes.iter().any(|node| node.id() == div.id())
+        {
+            nodes.push(div);
+        }
+    }
+
+    let mut score = 0.0;
+    for node in nodes {
+        if !counts_towards_score(&node) {
+            continue;
+        }
+        let length = node.text().collect::<String>().trim().chars().count();
+        if length < MIN_CONTENT_LENGTH {
+            continue;
+        }
+        score += ((length - MIN_CONTENT_LENGTH) as f64).sqrt();
+        if score > MIN_SCORE {
+            return true;
+        }
+    }
+    false
+}
+
+fn counts_towards_score(node: &ElementRef) -> bool {
+    let class = node.value().attr("class").unwrap_or("");
+    let id = node.value().attr("id").unwrap_or("");
+    let combined = format!("{} {}", class, id);
+    if UNLIKELY_PATTERNS.is_match(&combined) && !POSITIVE_PATTERNS.is_match(&combined) {
+        return false;
+    }
+
+    // Hidden text and list items don't make an article
+    !std::iter::once(**node)
+        .chain(node.ancestors())
+        .filter_map(ElementRef::wrap)
+        .any(|element| {
+            is_hidden(element.value())
+                || (element.id() != node.id() && element.value().name() == "li")
+        })
+}
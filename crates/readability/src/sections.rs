@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+/// A heading of the converted markdown together with everything under it,
+/// including its subsections
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// Heading level, from 1 to 6
+    pub level: usize,
+    pub heading: String,
+    /// GitHub-style anchor for the heading, unique within the document
+    pub anchor: String,
+    /// The heading line and the markdown up to the next heading of the same or a higher level
+    pub content: String,
+    /// The markdown between the heading line and the next heading of any level
+    pub own_content: String,
+}
+
+/// Split markdown into one section per ATX heading, ignoring `#` lines inside code blocks.
+/// Sections nest, so a subsection's content is also part of its parent's.
+pub fn split_sections(markdown: &str) -> Vec<Section> {
+    let lines = markdown.lines().collect::<Vec<_>>();
+
+    let mut headings = Vec::new();
+    let mut fence: Option<&str> = None;
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+        if let Some((level, heading)) = parse_heading(trimmed) {
+            headings.push((index, level, heading));
+        }
+    }
+
+    let mut anchors = HashMap::new();
+    headings
+        .iter()
+        .enumerate()
+        .map(|(position, (start, level, heading))| {
+            let end = headings[position + 1..]
+                .iter()
+                .find(|(_, next_level, _)| next_level <= level)
+                .map_or(lines.len(), |(next_start, _, _)| *next_start);
+
+            let own_end = headings
+                .get(position + 1)
+                .map_or(lines.len(), |(next_start, _, _)| *next_start);
+
+            Section {
+                level: *level,
+                heading: heading.clone(),
+                anchor: unique_anchor(heading, &mut anchors),
+                content: join_lines(&lines[*start..end]),
+                own_content: join_lines(&lines[start + 1..own_end])
+                    .trim_start()
+                    .to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Find the section whose heading best matches `query`: an exact heading or anchor
+/// first, then a heading containing the query, then the most overlapping words
+pub fn find_section<'a>(sections: &'a [Section], query: &str) -> Option<&'a Section> {
+    let query = query.trim();
+    let anchor = query.trim_start_matches('#').to_lowercase();
+    let normalized = normalize(query);
+    if normalized.is_empty() {
+        return None;
+    }
+
+    if let Some(section) = sections
+        .iter()
+        .find(|section| normalize(&section.heading) == normalized || section.anchor == anchor)
+    {
+        return Some(section);
+    }
+
+    if let Some(section) = sections
+        .iter()
+        .find(|section| normalize(&section.heading).contains(&normalized))
+    {
+        return Some(section);
+    }
+
+    let query_words = normalized.split(' ').collect::<Vec<_>>();
+    sections
+        .iter()
+        .map(|section| {
+            let heading = normalize(&section.heading);
+            let heading_words = heading.split(' ').collect::<Vec<_>>();
+            let common = query_words
+                .iter()
+                .filter(|word| heading_words.contains(word))
+                .count();
+            (section, common as f32 / query_words.len() as f32)
+        })
+        .filter(|(_, overlap)| *overlap >= 0.5)
+        .fold(
+            None,
+            |best: Option<(&Section, f32)>, (section, overlap)| match best {
+                Some((_, best_overlap)) if best_overlap >= overlap => best,
+                _ => Some((section, overlap)),
+            },
+        )
+        .map(|(section, _)| section)
+}
+
+fn join_lines(lines: &[&str]) -> String {
+    let mut joined = lines
+        .iter()
+        .map(|line| line.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n");
+    joined.truncate(joined.trim_end().len());
+    joined.push('\n');
+    joined
+}
+
+/// The level and text of an ATX heading line such as `## Installation ##`
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    let heading = rest.trim().trim_end_matches('#').trim();
+    (!heading.is_empty()).then(|| (level, heading.to_string()))
+}
+
+fn unique_anchor(heading: &str, seen: &mut HashMap<String, usize>) -> String {
+    let slug = heading
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect::<String>();
+
+    let count = seen.entry(slug.clone()).or_insert(0);
+    let anchor = if *count == 0 {
+        slug
+    } else {
+        format!("{}-{}", slug, count)
+    };
+    *count += 1;
+    anchor
+}
+
+fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
@@ -0,0 +1,98 @@
+/// One image candidate from a `srcset` attribute
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Candidate {
+    pub url: String,
+    pub width: Option<u32>,
+    pub density: Option<f32>,
+}
+
+/// Parse a `srcset` attribute following the HTML candidate string grammar, so
+/// that commas inside URLs don't split a candidate
+pub(crate) fn parse_srcset(srcset: &str) -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+    let mut rest = srcset;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+
+        let url_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (url, remainder) = rest.split_at(url_end);
+        rest = remainder;
+
+        // A URL ending in a comma has no descriptors
+        let descriptors = if url.ends_with(',') {
+            ""
+        } else {
+            let descriptors_end = rest.find(',').unwrap_or(rest.len());
+            let (descriptors, remainder) = rest.split_at(descriptors_end);
+            rest = remainder;
+            descriptors
+        };
+
+        let mut candidate = Candidate {
+            url: url.trim_end_matches(',').to_string(),
+            width: None,
+            density: None,
+        };
+        for descriptor in descriptors.split_whitespace() {
+            if let Some(width) = descriptor.strip_suffix('w') {
+                candidate.width = width.parse().ok();
+            } else if let Some(density) = descriptor.strip_suffix('x') {
+                candidate.density = density.parse().ok();
+            }
+        }
+
+        if !candidate.url.is_empty() {
+            candidates.push(candidate);
+        }
+    }
+
+    candidates
+}
+
+/// The rendered width in pixels given by the default (last) entry of a `sizes`
+/// attribute, when it is an absolute length
+pub(crate) fn parse_sizes(sizes: &str) -> Option<f32> {
+    let default = sizes.rsplit(',').next()?.trim();
+    default.strip_suffix("px")?.trim().parse().ok()
+}
+
+/// Pick the smallest candidate at least `target_width` wide, or the widest one
+/// when none is. `slot_width` converts pixel densities into widths; without it
+/// the densest candidate wins.
+pub(crate) fn select_candidate(
+    candidates: &[Candidate],
+    target_width: u32,
+    slot_width: Option<f32>,
+) -> Option<&Candidate> {
+    let target_width = target_width as f32;
+    let effective_width = |candidate: &Candidate| {
+        candidate
+            .width
+            .map(|width| width as f32)
+            .or_else(|| slot_width.map(|slot| slot * candidate.density.unwrap_or(1.0)))
+    };
+
+    let sized = candidates
+        .iter()
+        .filter_map(|candidate| effective_width(candidate).map(|width| (candidate, width)))
+        .collect::<Vec<_>>();
+
+    if sized.is_empty() {
+        return candidates.iter().max_by(|a, b| {
+            a.density
+                .unwrap_or(1.0)
+                .total_cmp(&b.density.unwrap_or(1.0))
+        });
+    }
+
+    sized
+        .iter()
+        .filter(|(_, width)| *width >= target_width)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .or_else(|| sized.iter().max_by(|(_, a), (_, b)| a.total_cmp(b)))
+        .map(|(candidate, _)| *candidate)
+}
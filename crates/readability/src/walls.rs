@@ -0,0 +1,130 @@
+use scraper::{ElementRef, Html, Selector};
+
+/// An interstitial standing between the reader and the content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wall {
+    /// The content is only shown after signing in
+    Login,
+    /// A cookie or privacy consent dialog hides the content
+    Consent,
+    /// A CAPTCHA or bot check must be solved first
+    Captcha,
+}
+
+impl Wall {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Wall::Login => "login",
+            Wall::Consent => "consent",
+            Wall::Captcha => "captcha",
+        }
+    }
+}
+
+/// Pages with more words than this outside the wall are treated as content
+const MAX_WORDS_OUTSIDE_WALL: usize = 150;
+
+const CAPTCHA_SELECTORS: &str = ".g-recaptcha, .h-captcha, .cf-turnstile, #captcha, .captcha, \
+    iframe[src*='recaptcha'], iframe[src*='hcaptcha'], iframe[src*='challenges.cloudflare.com']";
+
+const CAPTCHA_PHRASES: &[&str] = &[
+    "verify you are human",
+    "verify that you are human",
+    "are you a robot",
+    "i'm not a robot",
+    "not a robot",
+    "complete the security check",
+];
+
+/// Containers of common consent management platforms and cookie banners
+const CONSENT_SELECTORS: &str = "#onetrust-consent-sdk, #onetrust-banner-sdk, #didomi-host, \
+    #qc-cmp2-container, #CybotCookiebotDialog, #usercentrics-root, [id^='sp_message_container'], \
+    [class*='cookie-consent'], [class*='cookie-banner'], [id*='cookie-consent'], \
+    [id*='cookie-banner'], [class*='consent-wall'], [id*='consent-wall']";
+
+const CONSENT_PHRASES: &[&str] = &[
+    "we value your privacy",
+    "accept all cookies",
+    "before you continue",
+    "manage your privacy settings",
+];
+
+const LOGIN_PHRASES: &[&str] = &[
+    "sign in to continue",
+    "log in to continue",
+    "login to continue",
+    "sign in to read",
+    "log in to read",
+    "create a free account to continue",
+];
+
+/// Detect a login gate, consent interstitial or CAPTCHA challenge, judged by known
+/// widgets and phrases and by how little text the page has besides its forms and dialogs
+pub fn detect_wall(html: &str) -> Option<Wall> {
+    let document = Html::parse_document(html);
+    let text = visible_text(document.root_element()).to_lowercase();
+    let words = text.split_whitespace().count();
+    let has_phrase = |phrases: &[&str]| phrases.iter().any(|phrase| text.contains(phrase));
+
+    let captcha = Selector::parse(CAPTCHA_SELECTORS).unwrap();
+    if words <= MAX_WORDS_OUTSIDE_WALL
+        && (document.select(&captcha).next().is_some() || has_phrase(CAPTCHA_PHRASES))
+    {
+        return Some(Wall::Captcha);
+    }
+
+    // Words inside forms don't count as content, so a sign-in page with a long
+    // form still reads as a login wall
+    let forms = Selector::parse("form").unwrap();
+    let form_words = words_in(&document, &forms);
+    let password = Selector::parse("input[type='password' i]").unwrap();
+    if words.saturating_sub(form_words) <= MAX_WORDS_OUTSIDE_WALL
+        && (document.select(&password).next().is_some() || has_phrase(LOGIN_PHRASES))
+    {
+        return Some(Wall::Login);
+    }
+
+    let consent = Selector::parse(CONSENT_SELECTORS).unwrap();
+    let consent_words = words_in(&document, &consent);
+    if words.saturating_sub(consent_words) <= MAX_WORDS_OUTSIDE_WALL
+        && (consent_words > 0 || has_phrase(CONSENT_PHRASES))
+    {
+        return Some(Wall::Consent);
+    }
+
+    None
+}
+
+/// Words in the outermost elements matching `selector`, so nested matches count once
+fn words_in(document: &Html, selector: &Selector) -> usize {
+    document
+        .select(selector)
+        .filter(|element| {
+            !element
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|ancestor| selector.matches(&ancestor))
+        })
+        .map(|element| visible_text(element).split_whitespace().count())
+        .sum()
+}
+
+/// Text of `element`, leaving out scripts, styles and other non-rendered content
+fn visible_text(element: ElementRef) -> String {
+    element
+        .descendants()
+        .filter(|node| {
+            !node
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|ancestor| {
+                    matches!(
+                        ancestor.value().name(),
+                        "script" | "style" | "noscript" | "template"
+                    )
+                })
+        })
+        .filter_map(|node| node.value().as_text().map(|text| text.to_string()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,414 @@
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate};
+use serde_json::{Map, Value};
+use url::Url;
+
+/// Returned when the arguments of a tool call don't match its input schema
+#[derive(Debug)]
+pub struct InvalidArguments {
+    pub tool: String,
+    /// One message per problem, each naming the argument at fault
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for InvalidArguments {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid arguments for {}: {}",
+            self.tool,
+            self.errors.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for InvalidArguments {}
+
+/// Check `arguments` against the input schema of `tool`. Covers the parts of
+/// JSON Schema tool schemas use: `type`, `properties`, `required`,
+/// `additionalProperties`, `items`, `enum`, the numeric and length bounds, and
+/// the `uri`, `date` and `date-time` formats.
+pub fn validate_arguments(
+    tool: &str,
+    schema: &Value,
+    arguments: Option<&Value>,
+) -> Result<(), InvalidArguments> {
+    let empty = Value::Object(Map::new());
+    let mut errors = Vec::new();
+    validate(schema, arguments.unwrap_or(&empty), "", &mut errors);
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(InvalidArguments {
+            tool: tool.to_string(),
+            errors,
+        })
+    }
+}
+
+/// The schema of an elicitation asking for the arguments the call lacks, or
+/// whose URL is not absolute, or `None` if there are none the user could be asked
+/// for. Elicitations can only ask for flat, primitive values, so arguments of other
+/// types are left for validation to report.
+pub fn missing_arguments_schema(schema: &Value, arguments: Option<&Value>) -> Option<Value> {
+    let properties = schema.get("properties")?.as_object()?;
+    let argument = |field: &str| {
+        arguments
+            .and_then(|arguments| arguments.get(field))
+            .filter(|value| !value.is_null())
+    };
+
+    let missing = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .filter(|field| argument(field).is_none());
+    let bad_urls = properties
+        .iter()
+        .filter(|(_, property)| {
+            matches!(
+                property.get("format").and_then(Value::as_str),
+                Some("uri" | "url")
+            )
+        })
+        .filter(|(field, _)| {
+            argument(field)
+                .and_then(Value::as_str)
+                .is_some_and(|value| format_problem("uri", value).is_some())
+        })
+        .map(|(field, _)| field.as_str());
+
+    let mut requested = Map::new();
+    for field in missing.chain(bad_urls) {
+        if let Some(property) = properties.get(field).and_then(primitive_schema) {
+            requested.insert(field.to_string(), property);
+        }
+    }
+    if requested.is_empty() {
+        return None;
+    }
+
+    let required = requested.keys().cloned().collect::<Vec<_>>();
+    Some(serde_json::json!({
+        "type": "object",
+        "properties": requested,
+        "required": required,
+    }))
+}
+
+/// `property` reduced to what an elicitation schema may contain, if it describes
+/// a single primitive value
+fn primitive_schema(property: &Value) -> Option<Value> {
+    let kind = property.get("type")?.as_str()?;
+    if !matches!(kind, "string" | "number" | "integer" | "boolean") {
+        return None;
+    }
+
+    const KEYWORDS: &[&str] = &[
+        "type",
+        "title",
+        "description",
+        "enum",
+        "format",
+        "minimum",
+        "maximum",
+        "minLength",
+        "maxLength",
+        "default",
+    ];
+    let property = property.as_object()?;
+    Some(Value::Object(
+        KEYWORDS
+            .iter()
+            .filter_map(|keyword| Some((keyword.to_string(), property.get(*keyword)?.clone())))
+            .collect(),
+    ))
+}
+
+fn validate(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+    let name = if path.is_empty() { "arguments" } else { path };
+
+    if let Some(expected) = schema.get("type") {
+        let types = match expected {
+            Value::String(expected) => vec![expected.as_str()],
+            Value::Array(expected) => expected.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|expected| has_type(value, expected)) {
+            errors.push(format!(
+                "{} must be {}, not {}",
+                name,
+                types
+                    .iter()
+                    .map(|expected| article(expected))
+                    .collect::<Vec<_>>()
+                    .join(" or "),
+                article(type_name(value))
+            ));
+            // Further checks would only repeat the mismatch
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+        && !allowed.contains(value)
+    {
+        errors.push(format!(
+            "{} must be one of {}",
+            name,
+            allowed
+                .iter()
+                .map(Value::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    match value {
+        Value::Object(object) => validate_object(schema, object, path, errors),
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64)
+                && (items.len() as u64) < min
+            {
+                errors.push(format!("{} must have at least {} items", name, min));
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64)
+                && (items.len() as u64) > max
+            {
+                errors.push(format!("{} must have at most {} items", name, max));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate(item_schema, item, &format!("{}[{}]", name, i), errors);
+                }
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64)
+                && number < min
+            {
+                errors.push(format!("{} must be at least {}", name, min));
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64)
+                && number > max
+            {
+                errors.push(format!("{} must be at most {}", name, max));
+            }
+        }
+        Value::String(string) => {
+            let length = string.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64)
+                && length < min
+            {
+                errors.push(format!("{} must be at least {} characters long", name, min));
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64)
+                && length > max
+            {
+                errors.push(format!("{} must be at most {} characters long", name, max));
+            }
+            if let Some(format) = schema.get("format").and_then(Value::as_str)
+                && let Some(problem) = format_problem(format, string)
+            {
+                errors.push(format!("{} {}", name, problem));
+            }
+        }
+        Value::Bool(_) | Value::Null => {}
+    }
+}
+
+fn validate_object(
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    let field_path = |field: &str| {
+        if path.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", path, field)
+        }
+    };
+
+    for required in schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        if object.get(required).is_none_or(Value::is_null) {
+            errors.push(format!("{} is required", field_path(required)));
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (field, value) in object {
+        // Clients often send null for arguments they leave out
+        if value.is_null() {
+            continue;
+        }
+        match properties.and_then(|properties| properties.get(field)) {
+            Some(property) => validate(property, value, &field_path(field), errors),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    errors.push(format!("{} is not a known argument", field_path(field)))
+                }
+                Some(additional) => validate(additional, value, &field_path(field), errors),
+                None => {}
+            },
+        }
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "integer" => match value {
+            Value::Number(number) => {
+                number.is_i64()
+                    || number.is_u64()
+                    || number.as_f64().is_some_and(|n| n.fract() == 0.0)
+            }
+            _ => false,
+        },
+        "number" => value.is_number(),
+        expected => type_name(value) == expected,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn article(type_name: &str) -> String {
+    match type_name {
+        "null" => "null".to_string(),
+        "array" | "integer" | "object" => format!("an {}", type_name),
+        _ => format!("a {}", type_name),
+    }
+}
+
+/// What is wrong with `value` for `format`; unknown formats are not checked
+fn format_problem(format: &str, value: &str) -> Option<&'static str> {
+    let valid = match format {
+        "uri" | "url" => Url::parse(value).is_ok_and(|url| url.has_host()),
+        "date" => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+        "date-time" => DateTime::parse_from_rfc3339(value).is_ok(),
+        _ => true,
+    };
+    if valid {
+        return None;
+    }
+
+    Some(match format {
+        "date" => "must be a date such as 2024-01-31",
+        "date-time" => "must be an RFC 3339 date and time such as 2024-01-31T12:00:00Z",
+        _ => "must be an absolute URL such as https://example.com/page",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string", "format": "uri" },
+                "max_chars": { "type": "integer", "minimum": 100 },
+                "format": { "type": "string", "enum": ["markdown", "text"] },
+                "since": { "type": "string", "format": "date" },
+                "urls": { "type": "array", "items": { "type": "string" }, "maxItems": 2 }
+            },
+            "required": ["url"],
+            "additionalProperties": false
+        })
+    }
+
+    fn errors(arguments: Value) -> Vec<String> {
+        match validate_arguments("read_url", &schema(), Some(&arguments)) {
+            Ok(()) => Vec::new(),
+            Err(e) => e.errors,
+        }
+    }
+
+    #[test]
+    fn test_valid_arguments() {
+        assert!(errors(json!({ "url": "https://example.com/a", "max_chars": 500 })).is_empty());
+        // Nulls stand for arguments left out
+        assert!(errors(json!({ "url": "https://example.com/a", "format": null })).is_empty());
+        assert!(errors(json!({ "url": "https://example.com/a", "max_chars": 500.0 })).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert_eq!(errors(json!({})), vec!["url is required"]);
+        assert_eq!(
+            errors(json!({ "url": "example.com" })),
+            vec!["url must be an absolute URL such as https://example.com/page"]
+        );
+        assert_eq!(
+            errors(json!({ "url": "https://example.com", "max_chars": "many" })),
+            vec!["max_chars must be an integer, not a string"]
+        );
+        assert_eq!(
+            errors(json!({ "url": "https://example.com", "max_chars": 10 })),
+            vec!["max_chars must be at least 100"]
+        );
+        assert_eq!(
+            errors(json!({ "url": "https://example.com", "format": "pdf" })),
+            vec![r#"format must be one of "markdown", "text""#]
+        );
+        assert_eq!(
+            errors(json!({ "url": "https://example.com", "since": "31/01/2024" })),
+            vec!["since must be a date such as 2024-01-31"]
+        );
+        assert_eq!(
+            errors(json!({ "url": "https://example.com", "urls": ["a", 1, "c"] })),
+            vec![
+                "urls must have at most 2 items",
+                "urls[1] must be a string, not a number"
+            ]
+        );
+        assert_eq!(
+            errors(json!({ "url": "https://example.com", "depth": 2 })),
+            vec!["depth is not a known argument"]
+        );
+    }
+
+    #[test]
+    fn test_missing_arguments_schema() {
+        let requested = missing_arguments_schema(&schema(), Some(&json!({ "url": "example" })));
+        assert_eq!(
+            requested,
+            Some(json!({
+                "type": "object",
+                "properties": { "url": { "type": "string", "format": "uri" } },
+                "required": ["url"],
+            }))
+        );
+        assert_eq!(
+            missing_arguments_schema(&schema(), Some(&json!({ "url": "https://example.com" }))),
+            None
+        );
+    }
+}
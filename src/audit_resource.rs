@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use context_server::Resource;
+use read_mcp_tools::AuditLog;
+use serde_json::Value;
+
+use crate::resource_registry::ResourceProvider;
+
+const AUDIT_URI: &str = "read://audit";
+
+/// Exposes the session's most recent fetches from the audit log as `read://audit`
+pub struct AuditResource {
+    log: Arc<AuditLog>,
+    session: String,
+}
+
+impl AuditResource {
+    pub fn new(log: Arc<AuditLog>, session: String) -> Self {
+        AuditResource { log, session }
+    }
+}
+
+impl ResourceProvider for AuditResource {
+    fn list(&self) -> Vec<Resource> {
+        vec![Resource {
+            uri: AUDIT_URI.to_string(),
+            name: "Fetch audit log".to_string(),
+            description: Some(
+                "Recent outbound fetches made by this session, with tool, URL, status, size and cache hits"
+                    .to_string(),
+            ),
+            mime_type: Some("application/json".to_string()),
+        }]
+    }
+
+    fn read(&self, uri: &str) -> Option<String> {
+        if uri != AUDIT_URI {
+            return None;
+        }
+
+        // Other sessions' fetches stay in the log file, out of this client's sight
+        let entries = self
+            .log
+            .recent()
+            .iter()
+            .filter(|entry| {
+                entry
+                    .caller
+                    .as_ref()
+                    .is_some_and(|caller| caller.session == self.session)
+            })
+            .map(|entry| entry.to_json())
+            .collect();
+        serde_json::to_string_pretty(&Value::Array(entries)).ok()
+    }
+}
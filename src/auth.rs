@@ -0,0 +1,420 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow};
+use axum::http::{HeaderMap, header};
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use http_client::{
+    AsyncBody, HttpClient, Request, RequestBuilderExt, ResponseAsyncBodyExt, http::Method,
+};
+use parking_lot::Mutex;
+use serde_json::Value;
+use tokio::sync::OnceCell;
+use url::form_urlencoded;
+
+use crate::config::{AuthConfig, OidcConfig};
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Why a request was not let through
+#[derive(Debug)]
+pub enum AuthError {
+    MissingCredentials,
+    InvalidCredentials,
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::MissingCredentials => write!(
+                f,
+                "Authentication required: send an Authorization: Bearer header or an X-API-Key header"
+            ),
+            AuthError::InvalidCredentials => write!(f, "Invalid API key or token"),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+/// Checks the credentials of requests to a network transport against static API
+/// keys and, when configured, an OpenID Connect provider
+pub struct Authenticator {
+    api_keys: Vec<String>,
+    oidc: Option<OidcValidator>,
+}
+
+/// Validates access tokens by introspecting them or, failing that, by reading
+/// the audience from their JWT claims and presenting them to the provider's
+/// userinfo endpoint, remembering accepted tokens and their subjects for a while
+struct OidcValidator {
+    http_client: Arc<dyn HttpClient>,
+    issuer: String,
+    audiences: Vec<String>,
+    subjects: Vec<String>,
+    introspection: Option<Introspection>,
+    cache_ttl: Duration,
+    userinfo_endpoint: OnceCell<String>,
+    /// Accepted tokens, when they must be checked again and whose they are
+    accepted: Mutex<HashMap<String, (Instant, String)>>,
+}
+
+struct Introspection {
+    endpoint: String,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+}
+
+impl Authenticator {
+    pub fn new(config: &AuthConfig, http_client: Arc<dyn HttpClient>) -> Self {
+        Authenticator {
+            api_keys: config.api_keys.clone(),
+            oidc: config
+                .oidc
+                .as_ref()
+                .map(|oidc| OidcValidator::new(oidc, http_client)),
+        }
+    }
+
+    /// Who the request comes from: `api-key:<n>` for the nth configured key, or
+    /// `oidc:<sub>` for a token of the provider's user `sub`
+    pub async fn authenticate(&self, headers: &HeaderMap) -> Result<String, AuthError> {
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim);
+        let api_key = headers
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim);
+        let Some(credential) = bearer.or(api_key).filter(|value| !value.is_empty()) else {
+            return Err(AuthError::MissingCredentials);
+        };
+
+        if let Some(index) = self
+            .api_keys
+            .iter()
+            .position(|key| constant_time_eq(key.as_bytes(), credential.as_bytes()))
+        {
+            return Ok(format!("api-key:{}", index + 1));
+        }
+
+        // Tokens only ever come as bearer credentials
+        match (&self.oidc, bearer) {
+            (Some(oidc), Some(token)) => match oidc.validate(token).await {
+                Ok(Some(subject)) => Ok(format!("oidc:{}", subject)),
+                Ok(None) => Err(AuthError::InvalidCredentials),
+                Err(e) => {
+                    eprintln!("Error validating token with {}: {}", oidc.issuer, e);
+                    Err(AuthError::InvalidCredentials)
+                }
+            },
+            _ => Err(AuthError::InvalidCredentials),
+        }
+    }
+}
+
+impl OidcValidator {
+    fn new(config: &OidcConfig, http_client: Arc<dyn HttpClient>) -> Self {
+        OidcValidator {
+            http_client,
+            issuer: config.issuer.trim_end_matches('/').to_string(),
+            audiences: config.audiences.clone(),
+            subjects: config.subjects.clone(),
+            introspection: config
+                .introspection_endpoint
+                .as_ref()
+                .map(|endpoint| Introspection {
+                    endpoint: endpoint.clone(),
+                    client_id: config.client_id.clone(),
+                    client_secret: config.client_secret.clone(),
+                }),
+            cache_ttl: Duration::from_secs(config.cache_seconds),
+            userinfo_endpoint: OnceCell::new(),
+            accepted: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The subject of `token`, if the provider accepts it and it was issued to
+    /// one of the configured audiences and subjects
+    async fn validate(&self, token: &str) -> Result<Option<String>> {
+        let now = Instant::now();
+        if let Some((_, subject)) = self
+            .accepted
+            .lock()
+            .get(token)
+            .filter(|(expires, _)| *expires > now)
+        {
+            return Ok(Some(subject.clone()));
+        }
+
+        let subject = match &self.introspection {
+            Some(introspection) => self.introspect(introspection, token).await?,
+            None => self.ask_userinfo(token).await?,
+        };
+        let Some(subject) = subject.filter(|subject| {
+            self.subjects.is_empty() || self.subjects.iter().any(|allowed| allowed == subject)
+        }) else {
+            return Ok(None);
+        };
+
+        let mut accepted = self.accepted.lock();
+        accepted.retain(|_, (expires, _)| *expires > now);
+        accepted.insert(token.to_string(), (now + self.cache_ttl, subject.clone()));
+        Ok(Some(subject))
+    }
+
+    /// Ask the introspection endpoint whether `token` is active and for whom
+    async fn introspect(
+        &self,
+        introspection: &Introspection,
+        token: &str,
+    ) -> Result<Option<String>> {
+        let body = form_urlencoded::Serializer::new(String::new())
+            .append_pair("token", token)
+            .append_pair("token_type_hint", "access_token")
+            .finish();
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(introspection.endpoint.as_str())
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(header::ACCEPT, "application/json");
+        if let Some(client_id) = &introspection.client_id {
+            let credentials = format!(
+                "{}:{}",
+                client_id,
+                introspection.client_secret.as_deref().unwrap_or_default()
+            );
+            request = request.header(
+                header::AUTHORIZATION,
+                format!("Basic {}", STANDARD.encode(credentials)),
+            );
+        }
+        let response = self
+            .http_client
+            .send(request.body(AsyncBody::from(body))?)
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "{} answered with HTTP {}",
+                introspection.endpoint,
+                response.status()
+            ));
+        }
+
+        let claims: Value = serde_json::from_str(&response.text().await?)
+            .context("introspection response is not valid JSON")?;
+        if claims.get("active") != Some(&Value::Bool(true))
+            || !self.is_for_audience(&claims, "client_id")
+        {
+            return Ok(None);
+        }
+        Ok(claims
+            .get("sub")
+            .or_else(|| claims.get("client_id"))
+            .and_then(Value::as_str)
+            .map(str::to_string))
+    }
+
+    /// Check the audience of a JWT `token`, then have the userinfo endpoint
+    /// vouch for it. The claims are read without checking the signature, which
+    /// is safe only because the provider then confirms the token is its own.
+    async fn ask_userinfo(&self, token: &str) -> Result<Option<String>> {
+        // An opaque token's audience can only be learned by introspection
+        let Some(claims) = jwt_claims(token) else {
+            return Ok(None);
+        };
+        if !self.is_for_audience(&claims, "azp") {
+            return Ok(None);
+        }
+
+        let endpoint = self
+            .userinfo_endpoint
+            .get_or_try_init(|| self.discover_userinfo_endpoint())
+            .await?;
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(endpoint.as_str())
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .end()?;
+        let response = self.http_client.send(request).await?;
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+
+        let userinfo: Value = serde_json::from_str(&response.text().await?)
+            .context("userinfo response is not valid JSON")?;
+        let subject = userinfo
+            .get("sub")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("userinfo response has no sub"))?;
+        // The claims must describe the user the provider vouched for
+        if claims
+            .get("sub")
+            .and_then(Value::as_str)
+            .is_some_and(|claimed| claimed != subject)
+        {
+            return Ok(None);
+        }
+        Ok(Some(subject.to_string()))
+    }
+
+    /// Whether the token's `aud`, or its `party` claim naming the client it was
+    /// issued to, is one of the configured audiences
+    fn is_for_audience(&self, claims: &Value, party: &str) -> bool {
+        let audiences = match claims.get("aud") {
+            Some(Value::String(audience)) => vec![audience.as_str()],
+            Some(Value::Array(audiences)) => audiences.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        audiences
+            .into_iter()
+            .chain(claims.get(party).and_then(Value::as_str))
+            .any(|audience| self.audiences.iter().any(|allowed| allowed == audience))
+    }
+
+    async fn discover_userinfo_endpoint(&self) -> Result<String> {
+        let url = format!("{}/.well-known/openid-configuration", self.issuer);
+        let request = Request::builder().method(Method::GET).uri(&url).end()?;
+        let response = self.http_client.send(request).await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("{} answered with HTTP {}", url, response.status()));
+        }
+
+        let configuration: Value = serde_json::from_str(&response.text().await?)
+            .with_context(|| format!("{} is not valid JSON", url))?;
+        configuration
+            .get("userinfo_endpoint")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("{} has no userinfo_endpoint", url))
+    }
+}
+
+/// The claims of a JWT, or `None` for an opaque token
+fn jwt_claims(token: &str) -> Option<Value> {
+    let mut parts = token.split('.');
+    let (Some(_header), Some(payload), Some(_signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return None;
+    };
+    let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    serde_json::from_slice::<Value>(&payload)
+        .ok()
+        .filter(Value::is_object)
+}
+
+/// Compare secrets without revealing through timing how much of them matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use read_mcp_tools::test_support::{MockHttpClient, Reply};
+
+    use super::*;
+
+    const ISSUER: &str = "https://id.example.com";
+    const USERINFO: &str = "https://id.example.com/userinfo";
+    const INTROSPECTION: &str = "https://id.example.com/introspect";
+
+    fn oidc_config(introspection: bool) -> OidcConfig {
+        OidcConfig {
+            issuer: ISSUER.to_string(),
+            audiences: vec!["read-mcp".to_string()],
+            subjects: Vec::new(),
+            introspection_endpoint: introspection.then(|| INTROSPECTION.to_string()),
+            client_id: introspection.then(|| "read-mcp".to_string()),
+            client_secret: introspection.then(|| "secret".to_string()),
+            cache_seconds: 300,
+        }
+    }
+
+    fn provider() -> MockHttpClient {
+        MockHttpClient::new()
+            .with_get(
+                &format!("{}/.well-known/openid-configuration", ISSUER),
+                Reply::ok(format!(r#"{{"userinfo_endpoint": "{}"}}"#, USERINFO)),
+            )
+            .with_get(USERINFO, Reply::ok(r#"{"sub": "alice"}"#))
+    }
+
+    fn jwt(claims: Value) -> String {
+        format!(
+            "{}.{}.signature",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256"}"#),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        )
+    }
+
+    async fn validate(config: &OidcConfig, client: MockHttpClient, token: &str) -> Option<String> {
+        OidcValidator::new(config, Arc::new(client))
+            .validate(token)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_token_audience() {
+        let config = oidc_config(false);
+        let ours = jwt(serde_json::json!({ "sub": "alice", "aud": ["read-mcp", "api"] }));
+        assert_eq!(
+            validate(&config, provider(), &ours).await.as_deref(),
+            Some("alice")
+        );
+
+        let authorized = jwt(serde_json::json!({ "aud": "api", "azp": "read-mcp" }));
+        assert!(validate(&config, provider(), &authorized).await.is_some());
+
+        // Valid at the provider, but issued for another application
+        let theirs = jwt(serde_json::json!({ "sub": "alice", "aud": "mail" }));
+        assert_eq!(validate(&config, provider(), &theirs).await, None);
+
+        // An opaque token's audience can't be known without introspection
+        assert_eq!(validate(&config, provider(), "opaque").await, None);
+
+        let config = OidcConfig {
+            subjects: vec!["bob".to_string()],
+            ..oidc_config(false)
+        };
+        assert_eq!(validate(&config, provider(), &ours).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_token_introspection() {
+        let config = oidc_config(true);
+        let active = MockHttpClient::new().with_reply(
+            "POST",
+            INTROSPECTION,
+            Reply::ok(r#"{"active": true, "sub": "alice", "aud": "read-mcp"}"#),
+        );
+        assert_eq!(
+            validate(&config, active, "opaque").await.as_deref(),
+            Some("alice")
+        );
+
+        let other_client = MockHttpClient::new().with_reply(
+            "POST",
+            INTROSPECTION,
+            Reply::ok(r#"{"active": true, "sub": "alice", "client_id": "mail"}"#),
+        );
+        assert_eq!(validate(&config, other_client, "opaque").await, None);
+
+        let inactive = MockHttpClient::new().with_reply(
+            "POST",
+            INTROSPECTION,
+            Reply::ok(r#"{"active": false}"#),
+        );
+        assert_eq!(validate(&config, inactive, "opaque").await, None);
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+
+use crate::config::QuotaConfig;
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Returned when a client has used up one of its quotas
+#[derive(Debug)]
+pub enum QuotaExceeded {
+    RequestRate { limit: u32, retry_after: Duration },
+    SessionRate { limit: u32, retry_after: Duration },
+    Requests { limit: u64 },
+    ResponseBytes { limit: u64 },
+}
+
+impl QuotaExceeded {
+    /// When the request can be retried; never for the quotas on total usage
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            QuotaExceeded::RequestRate { retry_after, .. }
+            | QuotaExceeded::SessionRate { retry_after, .. } => Some(*retry_after),
+            QuotaExceeded::Requests { .. } | QuotaExceeded::ResponseBytes { .. } => None,
+        }
+    }
+
+    /// Details for the `data` of a JSON-RPC error
+    pub fn to_json(&self) -> Value {
+        let (quota, limit) = match self {
+            QuotaExceeded::RequestRate { limit, .. } => ("requests_per_minute", *limit as u64),
+            QuotaExceeded::SessionRate { limit, .. } => ("sessions_per_minute", *limit as u64),
+            QuotaExceeded::Requests { limit } => ("max_requests", *limit),
+            QuotaExceeded::ResponseBytes { limit } => ("max_response_bytes", *limit),
+        };
+        json!({
+            "quota": quota,
+            "limit": limit,
+            "retry_after_seconds": self
+                .retry_after()
+                .map(|retry_after| retry_after.as_secs().max(1)),
+        })
+    }
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaExceeded::RequestRate { limit, retry_after } => write!(
+                f,
+                "Quota exceeded: this client may make {} requests a minute; retry in {}s",
+                limit,
+                retry_after.as_secs().max(1)
+            ),
+            QuotaExceeded::SessionRate { limit, retry_after } => write!(
+                f,
+                "Quota exceeded: this client may open {} sessions a minute; retry in {}s",
+                limit,
+                retry_after.as_secs().max(1)
+            ),
+            QuotaExceeded::Requests { limit } => write!(
+                f,
+                "Quota exceeded: this client has made the {} requests it is allowed",
+                limit
+            ),
+            QuotaExceeded::ResponseBytes { limit } => write!(
+                f,
+                "Quota exceeded: this client has received the {} bytes of responses it is allowed",
+                limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// Request, session and bandwidth usage of one client, across all its sessions.
+/// Clients are told apart by the credential they authenticated with, or by their
+/// address when the server has no authentication, so opening another session
+/// doesn't start the count over.
+#[derive(Default)]
+pub struct ClientQuota(Mutex<Usage>);
+
+#[derive(Default)]
+struct Usage {
+    /// Requests made within the last rate window
+    recent_requests: VecDeque<Instant>,
+    /// Sessions opened within the last rate window
+    recent_sessions: VecDeque<Instant>,
+    requests: u64,
+    response_bytes: u64,
+    last_seen: Option<Instant>,
+}
+
+impl ClientQuota {
+    /// Count a request, unless the client is over one of the quotas in `config`
+    pub fn admit(&self, config: &QuotaConfig) -> Result<(), QuotaExceeded> {
+        let mut usage = self.0.lock();
+        let now = Instant::now();
+        usage.last_seen = Some(now);
+
+        if let Some(limit) = config.max_requests
+            && usage.requests >= limit
+        {
+            return Err(QuotaExceeded::Requests { limit });
+        }
+        if let Some(limit) = config.max_response_bytes
+            && usage.response_bytes >= limit
+        {
+            return Err(QuotaExceeded::ResponseBytes { limit });
+        }
+        if let Some(limit) = config.requests_per_minute {
+            rate_limit(&mut usage.recent_requests, limit, now)
+                .map_err(|retry_after| QuotaExceeded::RequestRate { limit, retry_after })?;
+        }
+
+        usage.recent_requests.push_back(now);
+        usage.requests += 1;
+        Ok(())
+    }
+
+    /// Count a new session, unless the client opens them faster than `config` allows
+    pub fn admit_session(&self, config: &QuotaConfig) -> Result<(), QuotaExceeded> {
+        let mut usage = self.0.lock();
+        let now = Instant::now();
+        usage.last_seen = Some(now);
+
+        if let Some(limit) = config.sessions_per_minute {
+            rate_limit(&mut usage.recent_sessions, limit, now)
+                .map_err(|retry_after| QuotaExceeded::SessionRate { limit, retry_after })?;
+        }
+        usage.recent_sessions.push_back(now);
+        Ok(())
+    }
+
+    /// Count `bytes` of response body against the bandwidth quota
+    pub fn record_response(&self, bytes: usize) {
+        self.0.lock().response_bytes += bytes as u64;
+    }
+
+    /// How long since the client was last admitted or refused
+    pub fn idle_for(&self) -> Duration {
+        self.0
+            .lock()
+            .last_seen
+            .map_or(Duration::MAX, |at| at.elapsed())
+    }
+}
+
+/// Drop the events of `recent` older than the rate window, failing with how long
+/// until one expires when `limit` of them remain
+fn rate_limit(recent: &mut VecDeque<Instant>, limit: u32, now: Instant) -> Result<(), Duration> {
+    while recent
+        .front()
+        .is_some_and(|at| now.duration_since(*at) >= RATE_WINDOW)
+    {
+        recent.pop_front();
+    }
+    if recent.len() >= limit as usize {
+        let oldest = recent.front().copied().unwrap_or(now);
+        return Err(RATE_WINDOW.saturating_sub(now.duration_since(oldest)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_quotas() {
+        let config = QuotaConfig {
+            requests_per_minute: Some(2),
+            max_requests: Some(3),
+            ..QuotaConfig::default()
+        };
+        let quota = ClientQuota::default();
+        assert!(quota.admit(&config).is_ok());
+        assert!(quota.admit(&config).is_ok());
+        let exceeded = quota.admit(&config).unwrap_err();
+        assert!(matches!(
+            exceeded,
+            QuotaExceeded::RequestRate { limit: 2, .. }
+        ));
+        assert!(exceeded.retry_after().is_some());
+        assert!(!exceeded.to_string().contains("session"));
+
+        let config = QuotaConfig {
+            max_requests: Some(2),
+            ..QuotaConfig::default()
+        };
+        assert!(matches!(
+            quota.admit(&config),
+            Err(QuotaExceeded::Requests { limit: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_session_rate() {
+        let config = QuotaConfig {
+            sessions_per_minute: Some(1),
+            ..QuotaConfig::default()
+        };
+        let quota = ClientQuota::default();
+        assert!(quota.admit_session(&config).is_ok());
+        let exceeded = quota.admit_session(&config).unwrap_err();
+        assert_eq!(exceeded.to_json()["quota"], "sessions_per_minute");
+        // Requests within the open session are not affected
+        assert!(quota.admit(&config).is_ok());
+    }
+
+    #[test]
+    fn test_response_bytes() {
+        let config = QuotaConfig {
+            max_response_bytes: Some(100),
+            ..QuotaConfig::default()
+        };
+        let quota = ClientQuota::default();
+        assert!(quota.admit(&config).is_ok());
+        quota.record_response(150);
+        assert!(matches!(
+            quota.admit(&config),
+            Err(QuotaExceeded::ResponseBytes { limit: 100 })
+        ));
+    }
+}
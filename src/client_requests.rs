@@ -0,0 +1,259 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use read_mcp_tools::ClientRoots;
+use serde_json::{Map, Value, json};
+use tokio::sync::{broadcast, oneshot};
+use url::Url;
+
+/// How long a user has to answer an elicitation before the call goes ahead without it
+const ELICITATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long the client has to list its roots
+const ROOTS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Messages held for the transport before the oldest are dropped
+const OUTGOING_BUFFER: usize = 16;
+
+/// Messages the server sends to a client of its own accord, notifications and
+/// requests, along with the responses awaited for those requests
+pub struct ClientChannel {
+    outgoing: broadcast::Sender<Value>,
+    pending: Mutex<HashMap<String, oneshot::Sender<Value>>>,
+    next_id: AtomicU64,
+    /// Whether the client declared the `elicitation` capability
+    elicitation: AtomicBool,
+    /// Whether the client declared the `roots` capability
+    has_roots: AtomicBool,
+    /// The client's roots as last listed, unset until they have been
+    roots: Mutex<Option<Vec<PathBuf>>>,
+}
+
+impl Default for ClientChannel {
+    fn default() -> Self {
+        ClientChannel {
+            outgoing: broadcast::channel(OUTGOING_BUFFER).0,
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            elicitation: AtomicBool::new(false),
+            has_roots: AtomicBool::new(false),
+            roots: Mutex::new(None),
+        }
+    }
+}
+
+impl ClientChannel {
+    /// Messages for the transport to deliver to the client
+    pub fn subscribe(&self) -> broadcast::Receiver<Value> {
+        self.outgoing.subscribe()
+    }
+
+    /// Whether the transport is holding a way to reach the client open, such as an
+    /// event stream
+    pub fn is_connected(&self) -> bool {
+        self.outgoing.receiver_count() > 0
+    }
+
+    /// Deliver `notification` if the transport has a way to; nobody is told otherwise
+    pub fn notify(&self, notification: Value) {
+        let _ = self.outgoing.send(notification);
+    }
+
+    /// Remember what the client said it supports in its `initialize` request
+    pub fn set_capabilities(&self, capabilities: Option<&Value>) {
+        let elicitation = capabilities.is_some_and(|c| c.get("elicitation").is_some());
+        self.elicitation.store(elicitation, Ordering::Relaxed);
+        let roots = capabilities.is_some_and(|c| c.get("roots").is_some());
+        self.has_roots.store(roots, Ordering::Relaxed);
+    }
+
+    pub fn supports_elicitation(&self) -> bool {
+        self.elicitation.load(Ordering::Relaxed)
+    }
+
+    /// Hand a response from the client to the request awaiting it, returning
+    /// whether `message` was such a response
+    pub fn resolve(&self, message: &Value) -> bool {
+        if message.get("method").is_some()
+            || (message.get("result").is_none() && message.get("error").is_none())
+        {
+            return false;
+        }
+        let Some(id) = message.get("id").map(Value::to_string) else {
+            return false;
+        };
+
+        if let Some(waiting) = self.pending.lock().remove(&id) {
+            let _ = waiting.send(message.clone());
+        }
+        true
+    }
+
+    /// Send a request to the client and wait up to `timeout` for its result
+    async fn request(&self, method: &str, params: Value, timeout: Duration) -> Result<Value> {
+        let id = json!(format!(
+            "server-{}",
+            self.next_id.fetch_add(1, Ordering::Relaxed)
+        ));
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().insert(id.to_string(), sender);
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        if self.outgoing.send(request).is_err() {
+            self.pending.lock().remove(&id.to_string());
+            return Err(anyhow!("No open stream to send {} on", method));
+        }
+
+        let response = match tokio::time::timeout(timeout, receiver).await {
+            Ok(response) => response?,
+            Err(_) => {
+                self.pending.lock().remove(&id.to_string());
+                return Err(anyhow!("The client did not answer {} in time", method));
+            }
+        };
+        match response.get("error") {
+            Some(error) => Err(anyhow!("The client rejected {}: {}", method, error)),
+            None => Ok(response["result"].clone()),
+        }
+    }
+
+    /// Ask the client for its roots again, as after `notifications/roots/list_changed`.
+    /// Until it answers, none are known and no local file is allowed. Clients
+    /// that didn't declare roots aren't asked.
+    pub async fn refresh_roots(&self) -> Vec<PathBuf> {
+        if !self.has_roots.load(Ordering::Relaxed) {
+            return Vec::new();
+        }
+        *self.roots.lock() = None;
+        match self.request("roots/list", json!({}), ROOTS_TIMEOUT).await {
+            Ok(result) => {
+                let roots = parse_roots(&result);
+                *self.roots.lock() = Some(roots.clone());
+                roots
+            }
+            Err(e) => {
+                eprintln!("Error listing the client's roots: {:#}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Whether `path` is inside one of the client's roots as last listed: always
+    /// when it declares no roots, never before it has listed them
+    pub fn allows(&self, path: &Path) -> bool {
+        if !self.has_roots.load(Ordering::Relaxed) {
+            return true;
+        }
+        self.roots
+            .lock()
+            .as_ref()
+            .is_some_and(|roots| roots.iter().any(|root| path.starts_with(root)))
+    }
+
+    /// Ask the user for the fields described by `schema`, returning what they
+    /// entered, or `None` if they declined or cancelled
+    pub async fn elicit(&self, message: &str, schema: Value) -> Result<Option<Map<String, Value>>> {
+        let result = self
+            .request(
+                "elicitation/create",
+                json!({ "message": message, "requestedSchema": schema }),
+                ELICITATION_TIMEOUT,
+            )
+            .await?;
+
+        match (result["action"].as_str(), result.get("content")) {
+            (Some("accept"), Some(Value::Object(content))) => Ok(Some(content.clone())),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl ClientRoots for ClientChannel {
+    /// Lists the roots first if the client hasn't been asked yet
+    async fn roots(&self) -> Option<Vec<PathBuf>> {
+        if !self.has_roots.load(Ordering::Relaxed) {
+            return None;
+        }
+        let listed = self.roots.lock().clone();
+        match listed {
+            Some(roots) => Some(roots),
+            None => Some(self.refresh_roots().await),
+        }
+    }
+}
+
+/// The local directories of a `roots/list` result, with symlinks resolved;
+/// roots that aren't `file://` URIs bound nothing on this machine
+fn parse_roots(result: &Value) -> Vec<PathBuf> {
+    result
+        .get("roots")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|root| root.get("uri").and_then(Value::as_str))
+        .filter_map(|uri| Url::parse(uri).ok())
+        .filter(|uri| uri.scheme() == "file")
+        .filter_map(|uri| uri.to_file_path().ok())
+        .map(|path| path.canonicalize().unwrap_or(path))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_roots() {
+        let result = json!({
+            "roots": [
+                { "uri": "file:///srv/research", "name": "Research" },
+                { "uri": "https://example.com/repo" },
+                { "uri": "not a uri" },
+            ]
+        });
+        assert_eq!(parse_roots(&result), vec![PathBuf::from("/srv/research")]);
+        assert!(parse_roots(&json!({})).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_roots_bound_local_files() {
+        let client = ClientChannel::default();
+        // A client without roots sets no bounds
+        assert!(client.allows(Path::new("/srv/research/notes.md")));
+        assert_eq!(client.roots().await, None);
+        let mut outgoing = client.subscribe();
+        assert!(client.refresh_roots().await.is_empty());
+        assert!(outgoing.try_recv().is_err());
+
+        client.set_capabilities(Some(&json!({ "roots": { "listChanged": true } })));
+        assert!(!client.allows(Path::new("/srv/research/notes.md")));
+
+        let listing = client.refresh_roots();
+        let answer = async {
+            let request = outgoing.recv().await.unwrap();
+            assert_eq!(request["method"], "roots/list");
+            client.resolve(&json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": { "roots": [{ "uri": "file:///srv/research" }] },
+            }));
+        };
+        let (roots, ()) = tokio::join!(listing, answer);
+        assert_eq!(roots, vec![PathBuf::from("/srv/research")]);
+        assert!(client.allows(Path::new("/srv/research/notes.md")));
+        assert!(!client.allows(Path::new("/srv/other/notes.md")));
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use read_mcp_tools::FetchHistory;
+use serde_json::{Value, json};
+
+/// The MCP spec caps a single completion response at 100 values
+const MAX_COMPLETION_VALUES: usize = 100;
+
+pub struct CompletionProvider {
+    history: Arc<FetchHistory>,
+}
+
+impl CompletionProvider {
+    pub fn new(history: Arc<FetchHistory>) -> Self {
+        Self { history }
+    }
+
+    /// Compute the `completion/complete` result for the given request params
+    pub fn complete(&self, params: Option<&Value>) -> Value {
+        let argument = params.and_then(|p| p.get("argument"));
+        let name = argument
+            .and_then(|a| a.get("name"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let value = argument
+            .and_then(|a| a.get("value"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+
+        let candidates = match name {
+            "url" => self.history.urls_matching(value),
+            _ => Vec::new(),
+        };
+
+        let total = candidates.len();
+        let values = candidates
+            .into_iter()
+            .take(MAX_COMPLETION_VALUES)
+            .collect::<Vec<_>>();
+
+        json!({
+            "completion": {
+                "values": values,
+                "total": total,
+                "hasMore": total > MAX_COMPLETION_VALUES,
+            }
+        })
+    }
+}
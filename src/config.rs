@@ -0,0 +1,612 @@
+use std::{collections::HashMap, env, fs, path::PathBuf, time::Duration};
+
+use anyhow::{Context, Result, anyhow};
+use read_mcp_tools::{LinkHandling, ToolAnnotations};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::tool_registry::ExecutionLimits;
+
+/// Operator configuration, read from the TOML file given by `--config <path>`
+/// or the `READ_MCP_CONFIG` environment variable.
+///
+/// On SIGHUP the file is read again, and `policy`, `headers`, `sessions`, `tools`,
+/// `limits`, `http.quotas` and `http.auth` take effect; the rest waits for a restart.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Persist the fetch history to SQLite, with the text and extracted metadata of
+    /// each read; history only lives in memory when unset. Only for the stdio
+    /// transport, whose single client owns the history.
+    pub store: Option<StoreConfig>,
+    /// External commands and WASM modules registered as additional tools
+    pub plugins: Vec<PluginConfig>,
+    /// Prompts filled in from their arguments and the output of tool calls
+    pub prompts: Vec<PromptConfig>,
+    pub server: ServerConfig,
+    pub tools: ToolsConfig,
+    pub limits: LimitsConfig,
+    pub extraction: ExtractionConfig,
+    pub fetch: FetchConfig,
+    /// Extra headers, such as API keys, sent with every fetch of a domain
+    pub headers: Vec<DomainHeadersConfig>,
+    /// Cookies of logged-in sessions, sent with every fetch of their domain by every
+    /// client session
+    pub sessions: Vec<SessionConfig>,
+    /// What fetches are refused, whatever tool makes them
+    pub policy: PolicyConfig,
+    /// Register the save_article tool, writing only inside these directories
+    pub save: Option<SaveConfig>,
+    /// Local directories of markdown, HTML and text files served as resources
+    pub directories: Vec<DirectoryConfig>,
+    /// Record every outbound fetch; nothing is recorded when unset
+    pub audit: Option<AuditConfig>,
+    /// Keep the `read://stats` counters across restarts; they start over when unset
+    pub stats: Option<StatsConfig>,
+    /// Export traces over OTLP; needs a build with the `otel` feature
+    pub telemetry: Option<TelemetryConfig>,
+    /// Serve MCP over HTTP instead of stdio
+    pub http: Option<HttpConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Socket address to bind, e.g. `127.0.0.1:8931`
+    pub listen: String,
+    /// Interval between keep-alive comments on open event streams
+    #[serde(default = "default_keep_alive_seconds")]
+    pub keep_alive_seconds: u64,
+    /// Sessions without a request or an open event stream for this long are closed
+    #[serde(default = "default_session_idle_seconds")]
+    pub session_idle_seconds: u64,
+    /// Sessions open at once; further `initialize` requests are refused with HTTP 503
+    #[serde(default = "default_max_sessions")]
+    pub max_sessions: usize,
+    /// Limits applied to every session separately
+    #[serde(default)]
+    pub quotas: QuotaConfig,
+    /// Who may connect; anyone who can reach `listen` when unset
+    pub auth: Option<AuthConfig>,
+    /// Serve HTTPS instead of plain HTTP
+    pub tls: Option<TlsConfig>,
+}
+
+/// Either a PEM certificate chain and private key, or `self_signed` for development
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: Option<PathBuf>,
+    pub key_path: Option<PathBuf>,
+    /// Generate a throwaway certificate for `localhost` at startup
+    #[serde(default)]
+    pub self_signed: bool,
+}
+
+/// Credentials accepted by the HTTP transport, as `Authorization: Bearer` or `X-API-Key`
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    /// Also accept access tokens issued by this OpenID Connect provider
+    pub oidc: Option<OidcConfig>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OidcConfig {
+    /// The provider's issuer URL, where `/.well-known/openid-configuration` is served
+    pub issuer: String,
+    /// Accept only tokens issued for one of these audiences, matched against the
+    /// token's `aud` or `azp`, or the introspected `aud` or `client_id`
+    pub audiences: Vec<String>,
+    /// Accept only tokens of these users (`sub`); any user when empty
+    #[serde(default)]
+    pub subjects: Vec<String>,
+    /// Validate tokens with this RFC 7662 introspection endpoint instead of
+    /// reading their JWT claims and asking the userinfo endpoint; opaque tokens
+    /// are only accepted this way
+    pub introspection_endpoint: Option<String>,
+    /// Credentials read-mcp authenticates to the introspection endpoint with
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    /// How long an accepted token is trusted before the provider is asked again
+    #[serde(default = "default_oidc_cache_seconds")]
+    pub cache_seconds: u64,
+}
+
+/// Per-client usage limits for the HTTP transport; unset ones don't apply. A
+/// client is an API key, an OpenID Connect subject or, without `auth`, an address.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuotaConfig {
+    /// Requests a client may make in any minute, across its sessions
+    pub requests_per_minute: Option<u32>,
+    /// Sessions a client may open in any minute
+    pub sessions_per_minute: Option<u32>,
+    /// Requests a client may make in total
+    pub max_requests: Option<u64>,
+    /// Bytes of responses a client may receive in total
+    pub max_response_bytes: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DomainHeadersConfig {
+    /// Also covers subdomains
+    pub domain: String,
+    pub values: HashMap<String, String>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionConfig {
+    /// Also covers subdomains
+    pub domain: String,
+    pub cookies: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// Content types never returned, e.g. `application/x-msdownload` or `video/*`
+    pub blocked_mime_types: Vec<String>,
+    /// URLs whose path ends in one of these extensions are not fetched, e.g. `exe`
+    pub blocked_extensions: Vec<String>,
+    pub blocked_domain_lists: Vec<DomainListConfig>,
+}
+
+/// A category of domains that are never fetched, along with their subdomains
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DomainListConfig {
+    /// Reported when a fetch is refused, e.g. `malware`
+    pub category: String,
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// File with one domain per line; hosts-file lines such as `0.0.0.0 example.com` also work
+    pub path: Option<PathBuf>,
+}
+
+impl DomainListConfig {
+    /// The listed domains together with those read from `path`
+    pub fn load(&self) -> Result<Vec<String>> {
+        let mut domains = self.domains.clone();
+        if let Some(path) = &self.path {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read domain list {}", path.display()))?;
+            domains.extend(
+                text.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .filter_map(|line| line.split_whitespace().last())
+                    .map(str::to_string),
+            );
+        }
+        Ok(domains)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SaveConfig {
+    /// Directories save_article may write to; relative paths go in the first one.
+    /// A client that declares roots narrows these to the parts inside them.
+    pub roots: Vec<PathBuf>,
+}
+
+/// How pages are requested
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FetchConfig {
+    /// Send a desktop browser's headers, for sites that block unfamiliar clients
+    pub impersonate_browser: bool,
+    /// Longest Retry-After a 429 or 503 may ask for and still be retried automatically
+    pub max_retry_wait_seconds: Option<u64>,
+    /// Register the set_cookies tool, letting clients hand over cookies of their own;
+    /// each session keeps its own on top of those in `[[sessions]]`
+    pub client_cookies: bool,
+}
+
+/// How read_url turns pages into markdown
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExtractionConfig {
+    /// Removed when they make up a whole block, on top of the built-in phrases
+    pub boilerplate_phrases: Vec<String>,
+    /// Keep the page's heading levels instead of demoting them below the title
+    pub preserve_heading_levels: bool,
+    /// Characters of page content read_url returns per call before asking callers to page
+    pub max_length: Option<usize>,
+    /// Split read_url content longer than this many characters into several text blocks
+    pub chunk_size: Option<usize>,
+    pub fallback: FallbackConfig,
+    /// Engine pages are extracted with: `readability` (the default), `semantic`, `page`, or
+    /// `auto` to run them all and keep the best scoring result
+    pub engine: Option<String>,
+    /// Engines used instead for particular sites
+    pub domain_engines: Vec<DomainEngineConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DomainEngineConfig {
+    /// Also covers subdomains
+    pub domain: String,
+    pub engine: String,
+}
+
+/// How whole pages are converted when no article is found in them
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FallbackConfig {
+    /// Elements left out along with their content; replaces the built-in list when set
+    pub skip_tags: Option<Vec<String>>,
+    /// `inline`, `referenced` or `text`
+    pub links: LinkHandling,
+    pub tables: bool,
+}
+
+impl Default for FallbackConfig {
+    fn default() -> Self {
+        Self {
+            skip_tags: None,
+            links: LinkHandling::default(),
+            tables: true,
+        }
+    }
+}
+
+/// How the server introduces itself to clients in its `initialize` response
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Guidance on using the tools, passed on to the client's model; the
+    /// built-in guidance, which uses the tools' own names, is sent when unset,
+    /// and nothing when empty
+    pub instructions: Option<String>,
+    /// Human-readable name shown by clients, e.g. `Company docs reader`
+    pub title: Option<String>,
+    pub website_url: Option<String>,
+}
+
+/// Which of the built-in and plugin tools are exposed to clients, and under what
+/// names; tools are always referred to by their own name here
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolsConfig {
+    /// Only these tools are registered when set
+    pub enabled: Option<Vec<String>>,
+    /// Tools never registered, even if listed in `enabled`
+    pub disabled: Vec<String>,
+    /// Expose every tool as `{namespace}.{name}`, e.g. `read.read_url`
+    pub namespace: Option<String>,
+    /// Names tools are exposed under instead, e.g. `read_url = "read.url"`;
+    /// these take precedence over `namespace`
+    pub rename: HashMap<String, String>,
+    /// Further names a tool also answers to, e.g. `read_url = ["read_url"]` to
+    /// keep the original name working after a rename
+    pub aliases: HashMap<String, Vec<String>>,
+}
+
+impl ToolsConfig {
+    /// The names the tool called `name` is exposed under; none when it is disabled
+    pub fn exposed_names(&self, name: &str) -> Vec<String> {
+        if !self.is_enabled(name) {
+            return Vec::new();
+        }
+
+        let primary = match (self.rename.get(name), &self.namespace) {
+            (Some(renamed), _) => renamed.clone(),
+            (None, Some(namespace)) => format!("{}.{}", namespace, name),
+            (None, None) => name.to_string(),
+        };
+        let mut names = vec![primary];
+        for alias in self.aliases.get(name).into_iter().flatten() {
+            if !names.contains(alias) {
+                names.push(alias.clone());
+            }
+        }
+        names
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        let allowed = self
+            .enabled
+            .as_ref()
+            .is_none_or(|enabled| enabled.iter().any(|n| n == name));
+        allowed && !self.disabled.iter().any(|n| n == name)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuditConfig {
+    /// JSON lines file every fetch is appended to; entries are only kept in memory when unset
+    pub path: Option<PathBuf>,
+    /// Number of entries served by the `read://audit` resource
+    #[serde(default = "default_recent_audit_entries")]
+    pub recent_entries: usize,
+}
+
+/// A directory served as resources; a client that declares roots only sees the
+/// files inside them
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DirectoryConfig {
+    pub path: PathBuf,
+    /// Names the directory in resource URIs; the directory's own name when unset
+    pub name: Option<String>,
+    /// How long a listing is reused before the directory is scanned again
+    #[serde(default = "default_rescan_seconds")]
+    pub rescan_seconds: u64,
+    #[serde(default = "default_max_directory_files")]
+    pub max_files: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatsConfig {
+    /// JSON file the counters are loaded from at startup and saved to. It holds the
+    /// calls made per domain, which `read://stats` leaves out when serving HTTP.
+    pub path: PathBuf,
+    #[serde(default = "default_stats_save_interval_seconds")]
+    pub save_interval_seconds: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "otel"), allow(dead_code))]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP traces endpoint, e.g. `http://localhost:4318/v1/traces`; the
+    /// `OTEL_EXPORTER_OTLP_*` environment variables apply when unset
+    pub endpoint: Option<String>,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StoreConfig {
+    pub path: PathBuf,
+    /// Number of fetches kept in the database
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+    /// Fetches older than this are pruned; kept until evicted by `max_entries` when unset
+    pub ttl_days: Option<u64>,
+    #[serde(default = "default_prune_interval_minutes")]
+    pub prune_interval_minutes: u64,
+}
+
+/// Execution limits applied to all tool calls, with per-tool overrides; concurrency
+/// limits count the calls of every session together
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub timeout_seconds: Option<u64>,
+    /// Tool calls allowed to run at once across all tools
+    pub max_concurrent: Option<usize>,
+    /// Results with more characters of text than this are stored as a
+    /// `read://results/{id}` resource, and only a preview is returned
+    pub max_result_chars: Option<usize>,
+    /// Characters of an offloaded result shown in its preview; 2000 when unset
+    pub preview_chars: Option<usize>,
+    pub tools: HashMap<String, ToolLimitsConfig>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolLimitsConfig {
+    pub timeout_seconds: Option<u64>,
+    pub max_concurrent: Option<usize>,
+}
+
+impl LimitsConfig {
+    pub fn global(&self) -> ExecutionLimits {
+        ExecutionLimits {
+            timeout: self.timeout_seconds.map(Duration::from_secs),
+            max_concurrent: self.max_concurrent,
+        }
+    }
+
+    pub fn per_tool(&self) -> HashMap<String, ExecutionLimits> {
+        self.tools
+            .iter()
+            .map(|(tool, limits)| {
+                (
+                    tool.clone(),
+                    ExecutionLimits {
+                        timeout: limits.timeout_seconds.map(Duration::from_secs),
+                        max_concurrent: limits.max_concurrent,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    pub name: String,
+    pub description: Option<String>,
+    /// Program to run for each call
+    pub command: Option<PathBuf>,
+    /// WASI module to run for each call in place of a command, without access to
+    /// files or the network; needs a build with the `wasm` feature
+    pub wasm: Option<PathBuf>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default = "default_input_schema")]
+    pub input_schema: Value,
+    /// Schema of the structured content the plugin returns, if any
+    pub output_schema: Option<Value>,
+    /// Behaviour hints advertised to clients, e.g. `read_only = true`
+    pub annotations: Option<ToolAnnotations>,
+}
+
+/// A prompt whose `template` may contain `{{argument}}` placeholders and tool calls
+/// such as `{{read_url url}}` or `{{read_url url=link format="text"}}`, where a
+/// bare parameter takes the prompt argument of the same name
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptConfig {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<PromptArgumentConfig>,
+    pub template: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptArgumentConfig {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+fn default_max_entries() -> usize {
+    10_000
+}
+
+fn default_prune_interval_minutes() -> u64 {
+    60
+}
+
+fn default_service_name() -> String {
+    env!("CARGO_PKG_NAME").to_string()
+}
+
+fn default_recent_audit_entries() -> usize {
+    100
+}
+
+fn default_rescan_seconds() -> u64 {
+    5
+}
+
+fn default_max_directory_files() -> usize {
+    1_000
+}
+
+fn default_stats_save_interval_seconds() -> u64 {
+    60
+}
+
+fn default_oidc_cache_seconds() -> u64 {
+    300
+}
+
+fn default_keep_alive_seconds() -> u64 {
+    15
+}
+
+fn default_session_idle_seconds() -> u64 {
+    30 * 60
+}
+
+fn default_max_sessions() -> usize {
+    1000
+}
+
+fn default_input_schema() -> Value {
+    json!({ "type": "object" })
+}
+
+impl Config {
+    /// Sections that differ from `other` among those applied without a restart
+    pub fn reloadable_changes(&self, other: &Config) -> Vec<&'static str> {
+        fn http_auth(config: &Config) -> Option<&AuthConfig> {
+            config.http.as_ref()?.auth.as_ref()
+        }
+        let http_quotas = |config: &Config| config.http.as_ref().map(|http| http.quotas);
+
+        [
+            ("policy", self.policy != other.policy),
+            ("headers", self.headers != other.headers),
+            ("sessions", self.sessions != other.sessions),
+            ("tools", self.tools != other.tools),
+            ("limits", self.limits != other.limits),
+            ("http.quotas", http_quotas(self) != http_quotas(other)),
+            ("http.auth", http_auth(self) != http_auth(other)),
+        ]
+        .into_iter()
+        .filter_map(|(section, changed)| changed.then_some(section))
+        .collect()
+    }
+
+    pub fn load() -> Result<Self> {
+        let Some(path) = config_path() else {
+            return Ok(Config::default());
+        };
+
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let config: Config = toml::from_str(&text)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+        config
+            .validate()
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Reject combinations of sections the server can't honour
+    fn validate(&self) -> Result<()> {
+        // HTTP sessions each keep their own history, which one database can't
+        // hold apart, and the store would hand one client's reads to another
+        if self.store.is_some() && self.http.is_some() {
+            return Err(anyhow!(
+                "[store] only works with the stdio transport; remove it or [http]"
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+
+    env::var_os("READ_MCP_CONFIG").map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_needs_stdio() {
+        let config: Config = toml::from_str(
+            r#"
+            [store]
+            path = "read-mcp.db"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(
+            r#"
+            [store]
+            path = "read-mcp.db"
+
+            [http]
+            listen = "127.0.0.1:8080"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use context_server::Resource;
+use read_mcp_tools::{FetchHistory, HISTORY_URI};
+
+use crate::resource_registry::ResourceProvider;
+
+/// Exposes the fetch history as `read://history` plus one `read://history/{id}` per fetch
+pub struct HistoryResources(Arc<FetchHistory>);
+
+impl HistoryResources {
+    pub fn new(history: Arc<FetchHistory>) -> Self {
+        HistoryResources(history)
+    }
+}
+
+impl ResourceProvider for HistoryResources {
+    fn list(&self) -> Vec<Resource> {
+        let mut resources = vec![Resource {
+            uri: HISTORY_URI.to_string(),
+            name: "Fetch history".to_string(),
+            description: Some("URLs fetched in this session, most recent first".to_string()),
+            mime_type: Some("text/markdown".to_string()),
+        }];
+
+        resources.extend(self.0.entries().into_iter().map(|entry| Resource {
+            uri: format!("{}/{}", HISTORY_URI, entry.id),
+            name: entry.title.clone().unwrap_or_else(|| entry.url.clone()),
+            description: Some(format!(
+                "{} fetched at {} (status {})",
+                entry.url,
+                entry.fetched_at.to_rfc3339(),
+                entry.status
+            )),
+            mime_type: Some("text/plain".to_string()),
+        }));
+
+        resources
+    }
+
+    fn read(&self, uri: &str) -> Option<String> {
+        if uri == HISTORY_URI {
+            let mut index = String::from("# Fetch history\n\n");
+            for entry in self.0.entries() {
+                index.push_str(&format!(
+                    "- {} [{}] {} <{}> ({}/{})\n",
+                    entry.fetched_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                    entry.status,
+                    entry.title.as_deref().unwrap_or("Untitled"),
+                    entry.url,
+                    HISTORY_URI,
+                    entry.id
+                ));
+            }
+            return Some(index);
+        }
+
+        let id = uri
+            .strip_prefix(HISTORY_URI)?
+            .strip_prefix('/')?
+            .parse()
+            .ok()?;
+        self.0.get(id).map(|entry| entry.content)
+    }
+}
@@ -0,0 +1,502 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow};
+use axum::{
+    Extension, Json, Router,
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::{self, Next},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{get, post},
+};
+use axum_server::tls_rustls::RustlsConfig;
+use parking_lot::{Mutex, RwLock};
+use read_mcp_tools::FetchHistory;
+use serde_json::{Value, json};
+use tokio_stream::{StreamExt, wrappers::BroadcastStream};
+use uuid::Uuid;
+
+use crate::{
+    ContextServerState, PARSE_ERROR, Services,
+    auth::Authenticator,
+    client_quota::{ClientQuota, QuotaExceeded},
+    config::{Config, HttpConfig, QuotaConfig, TlsConfig},
+    rpc_error,
+};
+
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// How long `/healthz` waits on the session table before reporting the server as wedged
+const HEALTH_LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Longest wait between looks for idle sessions to close
+const MAX_REAP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long a client without open sessions keeps its quota usage
+const CLIENT_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// JSON-RPC error code for requests refused because a client is over its quota
+const QUOTA_EXCEEDED_CODE: i64 = -32029;
+
+/// Who sent a request: the identity its credentials authenticated, or
+/// `ip:<address>` when the server has no authentication
+#[derive(Clone)]
+struct ClientIdentity(String);
+
+struct HttpState {
+    services: Arc<Services>,
+    sessions: RwLock<HashMap<String, Arc<Session>>>,
+    /// Quota usage by client identity
+    clients: RwLock<HashMap<String, Arc<ClientQuota>>>,
+    keep_alive: Duration,
+    session_idle: Duration,
+    max_sessions: usize,
+    quotas: RwLock<QuotaConfig>,
+    auth: RwLock<Option<Arc<Authenticator>>>,
+}
+
+struct Session {
+    server: ContextServerState,
+    /// Identity of the client that opened the session, the only one it answers
+    owner: String,
+    quota: Arc<ClientQuota>,
+    last_used: Mutex<Instant>,
+}
+
+impl HttpState {
+    fn client_quota(&self, identity: &str) -> Arc<ClientQuota> {
+        if let Some(quota) = self.clients.read().get(identity) {
+            return quota.clone();
+        }
+        self.clients
+            .write()
+            .entry(identity.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// The session `session_id`, unless it is unknown or belongs to another client
+    fn session(&self, session_id: &str, client: &ClientIdentity) -> Option<Arc<Session>> {
+        self.sessions
+            .read()
+            .get(session_id)
+            .filter(|session| session.owner == client.0)
+            .cloned()
+    }
+}
+
+impl Session {
+    fn touch(&self) {
+        *self.last_used.lock() = Instant::now();
+    }
+
+    /// Whether the client has neither sent a request nor held an event stream open
+    /// for `idle`
+    fn is_idle(&self, idle: Duration) -> bool {
+        !self.server.client.is_connected() && self.last_used.lock().elapsed() >= idle
+    }
+}
+
+/// Serve MCP over HTTP at `/mcp`.
+///
+/// Every `initialize` request opens a new session, identified by the
+/// `Mcp-Session-Id` response header that clients send back on later requests.
+/// Sessions have their own history, resources and cookies. The fetcher with its
+/// domain headers and content policy, the server statistics, the audit log and
+/// the tool concurrency limits are shared between them. Sessions are closed by
+/// `DELETE /mcp` or after `session_idle_seconds` without use, and no more than
+/// `max_sessions` are open at once.
+///
+/// `GET /mcp` opens an event stream carrying the session's notifications, such as
+/// `tools/list_changed` after a configuration reload, and the server's own requests,
+/// such as elicitations, whose responses are POSTed back. `GET /healthz` reports
+/// whether the server is still answering.
+///
+/// Request, session and bandwidth quotas apply to each client across all its
+/// sessions, so one client can't monopolize a shared server; requests over them
+/// are refused with HTTP 429. Clients are told apart by their API key or token
+/// subject, or by address without `auth`, and a session only answers the client
+/// that opened it. A client's usage is forgotten a day after its last request
+/// once it has no open sessions.
+///
+/// With `auth` configured, `/mcp` requires an API key or an access token;
+/// `/healthz` stays open for load balancers. With `tls` configured, everything
+/// is served over HTTPS.
+pub async fn serve(services: Arc<Services>, config: &HttpConfig) -> Result<()> {
+    let state = Arc::new(HttpState {
+        sessions: RwLock::new(HashMap::new()),
+        clients: RwLock::new(HashMap::new()),
+        keep_alive: Duration::from_secs(config.keep_alive_seconds.max(1)),
+        session_idle: Duration::from_secs(config.session_idle_seconds.max(1)),
+        max_sessions: config.max_sessions,
+        quotas: RwLock::new(config.quotas),
+        auth: RwLock::new(
+            config
+                .auth
+                .as_ref()
+                .map(|auth| Arc::new(Authenticator::new(auth, services.http_client.clone()))),
+        ),
+        services,
+    });
+    spawn_config_reloads(state.clone());
+    spawn_session_reaping(state.clone());
+
+    let app = Router::new()
+        .route(
+            "/mcp",
+            post(handle_message)
+                .get(open_event_stream)
+                .delete(close_session),
+        )
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .route("/healthz", get(health))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(&config.listen).await?;
+    let address = listener.local_addr()?;
+    let tls = match &config.tls {
+        Some(tls) => Some(rustls_config(tls, address).await?),
+        None => None,
+    };
+    eprintln!(
+        "Listening on {}://{}/mcp",
+        if tls.is_some() { "https" } else { "http" },
+        address
+    );
+    if config.auth.is_none() && !address.ip().is_loopback() {
+        eprintln!(
+            "Warning: {} is reachable beyond this machine and no [http.auth] is configured",
+            address
+        );
+    }
+
+    match tls {
+        Some(tls) => {
+            axum_server::from_tcp_rustls(listener.into_std()?, tls)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await?
+        }
+        None => {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await?
+        }
+    }
+
+    Ok(())
+}
+
+async fn rustls_config(config: &TlsConfig, address: SocketAddr) -> Result<RustlsConfig> {
+    match (&config.cert_path, &config.key_path, config.self_signed) {
+        (Some(cert_path), Some(key_path), false) => {
+            RustlsConfig::from_pem_file(cert_path, key_path)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to load TLS certificate {} and key {}",
+                        cert_path.display(),
+                        key_path.display()
+                    )
+                })
+        }
+        (None, None, true) => {
+            let mut names = vec!["localhost".to_string()];
+            if !address.ip().is_unspecified() {
+                names.push(address.ip().to_string());
+            }
+            let certified = rcgen::generate_simple_self_signed(names.clone())?;
+            eprintln!(
+                "Warning: serving a self-signed certificate for {}; clients will have to be told to trust it",
+                names.join(", ")
+            );
+            Ok(RustlsConfig::from_pem(
+                certified.cert.pem().into_bytes(),
+                certified.key_pair.serialize_pem().into_bytes(),
+            )
+            .await?)
+        }
+        _ => Err(anyhow!(
+            "[http.tls] needs either both cert_path and key_path, or self_signed = true"
+        )),
+    }
+}
+
+/// Close sessions that have gone idle, which clients that never send DELETE leave
+/// behind, and forget clients that have been gone long enough
+fn spawn_session_reaping(state: Arc<HttpState>) {
+    let period = state.session_idle.min(MAX_REAP_INTERVAL);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            state
+                .sessions
+                .write()
+                .retain(|_, session| !session.is_idle(state.session_idle));
+            // Open sessions hold the only other references to a client's quota
+            state.clients.write().retain(|_, quota| {
+                Arc::strong_count(quota) > 1 || quota.idle_for() < CLIENT_RETENTION
+            });
+        }
+    });
+}
+
+/// Apply reloaded `http` settings, and tell every session about the reload
+fn spawn_config_reloads(state: Arc<HttpState>) {
+    let mut config_changes = state.services.config.subscribe();
+    tokio::spawn(async move {
+        let mut previous = config_changes.borrow_and_update().clone();
+        while config_changes.changed().await.is_ok() {
+            let config = config_changes.borrow_and_update().clone();
+            apply_http_config(&state, &previous, &config);
+
+            let sessions = state.sessions.read().values().cloned().collect::<Vec<_>>();
+            for session in sessions {
+                for notification in session.server.apply_config(config.clone()) {
+                    session.server.client.notify(notification);
+                }
+            }
+            previous = config;
+        }
+    });
+}
+
+fn apply_http_config(state: &HttpState, previous: &Config, config: &Config) {
+    let Some(http) = &config.http else {
+        eprintln!("[http] was removed; its settings stay as they are until a restart");
+        return;
+    };
+    let changes = previous.reloadable_changes(config);
+
+    if changes.contains(&"http.quotas") {
+        *state.quotas.write() = http.quotas;
+    }
+    if changes.contains(&"http.auth") {
+        *state.auth.write() = http
+            .auth
+            .as_ref()
+            .map(|auth| Arc::new(Authenticator::new(auth, state.services.http_client.clone())));
+    }
+}
+
+/// Check the request's credentials when `auth` is configured, and attach the
+/// identity of its client for quotas and session ownership
+async fn authenticate(
+    State(state): State<Arc<HttpState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let auth = state.auth.read().clone();
+    let identity = match auth {
+        Some(auth) => match auth.authenticate(request.headers()).await {
+            Ok(identity) => identity,
+            Err(e) => {
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    [(header::WWW_AUTHENTICATE, "Bearer realm=\"read-mcp\"")],
+                    e.to_string(),
+                )
+                    .into_response();
+            }
+        },
+        None => match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(peer)) => format!("ip:{}", peer.ip()),
+            None => "ip:unknown".to_string(),
+        },
+    };
+    request.extensions_mut().insert(ClientIdentity(identity));
+    next.run(request).await
+}
+
+async fn handle_message(
+    State(state): State<Arc<HttpState>>,
+    Extension(client): Extension<ClientIdentity>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let message: Value = match serde_json::from_str(&body) {
+        Ok(message) => message,
+        Err(e) => {
+            let error = rpc_error(Value::Null, PARSE_ERROR, format!("Parse error: {}", e));
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    };
+    let is_initialize = message.get("method").and_then(Value::as_str) == Some("initialize");
+
+    let quotas = *state.quotas.read();
+    let (session_id, session) = if is_initialize {
+        let quota = state.client_quota(&client.0);
+        if let Err(exceeded) = quota.admit_session(&quotas) {
+            return quota_exceeded(&message, None, exceeded);
+        }
+        let session_id = Uuid::new_v4().to_string();
+        let server = match ContextServerState::new(
+            &state.services,
+            &session_id,
+            Arc::new(FetchHistory::default()),
+        ) {
+            Ok(server) => server,
+            Err(e) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+        };
+        let session = Arc::new(Session {
+            server,
+            owner: client.0,
+            quota,
+            last_used: Mutex::new(Instant::now()),
+        });
+        let mut sessions = state.sessions.write();
+        if sessions.len() >= state.max_sessions {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Too many open sessions; retry later",
+            )
+                .into_response();
+        }
+        sessions.insert(session_id.clone(), session.clone());
+        drop(sessions);
+        (session_id, session)
+    } else {
+        let Some(session_id) = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) else {
+            return (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header").into_response();
+        };
+        let Some(session) = state.session(session_id, &client) else {
+            return (StatusCode::NOT_FOUND, "Unknown or expired session").into_response();
+        };
+        session.touch();
+        (session_id.to_string(), session)
+    };
+
+    if let Err(exceeded) = session.quota.admit(&quotas) {
+        return quota_exceeded(&message, Some(&session_id), exceeded);
+    }
+
+    // A long tool call counts as use until it ends
+    let response = session.server.process_payload(message).await;
+    session.touch();
+    match response {
+        Some(response) => {
+            // Measured as serialized, which is what the quota is about
+            let body = response.to_string();
+            session.quota.record_response(body.len());
+            (
+                [
+                    (header::CONTENT_TYPE.as_str(), "application/json"),
+                    (SESSION_HEADER, session_id.as_str()),
+                ],
+                body,
+            )
+                .into_response()
+        }
+        None => (StatusCode::ACCEPTED, [(SESSION_HEADER, session_id)]).into_response(),
+    }
+}
+
+/// A JSON-RPC error explaining which quota the client is over, answering every
+/// request of a batch; `session_id` is unset when opening the session was refused
+fn quota_exceeded(message: &Value, session_id: Option<&str>, exceeded: QuotaExceeded) -> Response {
+    let error = |request: &Value| {
+        json!({
+            "jsonrpc": "2.0",
+            "id": request.get("id").cloned().unwrap_or(Value::Null),
+            "error": {
+                "code": QUOTA_EXCEEDED_CODE,
+                "message": exceeded.to_string(),
+                "data": exceeded.to_json(),
+            },
+        })
+    };
+    let body = match message {
+        Value::Array(requests) => Value::Array(requests.iter().map(error).collect()),
+        request => error(request),
+    };
+
+    let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response();
+    if let Some(session_id) = session_id {
+        response
+            .headers_mut()
+            .insert(SESSION_HEADER, session_id.parse().unwrap());
+    }
+    if let Some(retry_after) = exceeded.retry_after() {
+        response.headers_mut().insert(
+            header::RETRY_AFTER,
+            retry_after.as_secs().max(1).to_string().parse().unwrap(),
+        );
+    }
+    response
+}
+
+/// The session's notifications, with keep-alives in between that stop proxies
+/// from dropping the stream and let clients spot a dead server
+async fn open_event_stream(
+    State(state): State<Arc<HttpState>>,
+    Extension(client): Extension<ClientIdentity>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(session_id) = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) else {
+        return (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header").into_response();
+    };
+    let Some(session) = state.session(session_id, &client) else {
+        return (StatusCode::NOT_FOUND, "Unknown or expired session").into_response();
+    };
+    session.touch();
+
+    // Messages a slow client missed are skipped rather than ending the stream
+    let events =
+        BroadcastStream::new(session.server.client.subscribe()).filter_map(|notification| {
+            notification.ok().map(|notification| {
+                Ok::<_, Infallible>(Event::default().data(notification.to_string()))
+            })
+        });
+    Sse::new(events)
+        .keep_alive(KeepAlive::new().interval(state.keep_alive))
+        .into_response()
+}
+
+async fn health(State(state): State<Arc<HttpState>>) -> Response {
+    let Some(sessions) = state.sessions.try_read_for(HEALTH_LOCK_TIMEOUT) else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "status": "unavailable" })),
+        )
+            .into_response();
+    };
+
+    Json(json!({
+        "status": "ok",
+        "sessions": sessions.len(),
+        "uptime_seconds": state.services.stats.uptime().as_secs(),
+    }))
+    .into_response()
+}
+
+async fn close_session(
+    State(state): State<Arc<HttpState>>,
+    Extension(client): Extension<ClientIdentity>,
+    headers: HeaderMap,
+) -> StatusCode {
+    let Some(session_id) = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) else {
+        return StatusCode::BAD_REQUEST;
+    };
+
+    let mut sessions = state.sessions.write();
+    match sessions.get(session_id) {
+        Some(session) if session.owner == client.0 => {
+            sessions.remove(session_id);
+            StatusCode::NO_CONTENT
+        }
+        _ => StatusCode::NOT_FOUND,
+    }
+}
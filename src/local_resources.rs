@@ -0,0 +1,165 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use context_server::Resource;
+use parking_lot::Mutex;
+
+use crate::{
+    client_requests::ClientChannel, config::DirectoryConfig, resource_registry::ResourceProvider,
+};
+
+const LOCAL_URI: &str = "read://local";
+
+/// Serves the markdown, HTML and text files under a local directory as
+/// `read://local/{name}/{path}`, scanning it again once the listing is older
+/// than the rescan interval
+pub struct LocalDirectory {
+    name: String,
+    root: PathBuf,
+    max_files: usize,
+    rescan_interval: Duration,
+    /// Relative paths of the files found by the last scan, and when it ran
+    scan: Mutex<Option<(Instant, Vec<PathBuf>)>>,
+}
+
+impl LocalDirectory {
+    pub fn new(config: &DirectoryConfig) -> Result<Self> {
+        let root = config
+            .path
+            .canonicalize()
+            .with_context(|| format!("Failed to open directory {}", config.path.display()))?;
+        let name = match &config.name {
+            Some(name) => name.clone(),
+            None => root
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "root".to_string()),
+        };
+
+        Ok(LocalDirectory {
+            name,
+            root,
+            max_files: config.max_files,
+            rescan_interval: Duration::from_secs(config.rescan_seconds),
+            scan: Mutex::new(None),
+        })
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        let mut scan = self.scan.lock();
+        if let Some((scanned_at, files)) = &*scan
+            && scanned_at.elapsed() < self.rescan_interval
+        {
+            return files.clone();
+        }
+
+        let mut files = Vec::new();
+        if let Err(e) = self.walk(&self.root, &mut files) {
+            eprintln!("Error scanning {}: {}", self.root.display(), e);
+        }
+        files.sort();
+        *scan = Some((Instant::now(), files.clone()));
+        files
+    }
+
+    fn walk(&self, directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(directory)? {
+            if files.len() >= self.max_files {
+                break;
+            }
+
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            // Not followed through symlinks, which are skipped
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                self.walk(&path, files)?;
+            } else if file_type.is_file()
+                && mime_type(&path).is_some()
+                && let Ok(relative) = path.strip_prefix(&self.root)
+            {
+                files.push(relative.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+
+    fn uri(&self, relative: &Path) -> String {
+        let segments = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>();
+        format!("{}/{}/{}", LOCAL_URI, self.name, segments.join("/"))
+    }
+
+    /// The file `uri` points to; only listed files are served, so `..` and
+    /// symlinks can't lead outside the directory
+    fn resolve(&self, uri: &str) -> Option<PathBuf> {
+        self.files()
+            .into_iter()
+            .find(|relative| self.uri(relative) == uri)
+            .map(|relative| self.root.join(relative))
+    }
+}
+
+/// A directory as one session sees it: only the files inside the client's roots
+pub struct ClientDirectory {
+    directory: Arc<LocalDirectory>,
+    client: Arc<ClientChannel>,
+}
+
+impl ClientDirectory {
+    pub fn new(directory: Arc<LocalDirectory>, client: Arc<ClientChannel>) -> Self {
+        ClientDirectory { directory, client }
+    }
+}
+
+impl ResourceProvider for ClientDirectory {
+    fn list(&self) -> Vec<Resource> {
+        let directory = &self.directory;
+        directory
+            .files()
+            .iter()
+            .filter(|relative| self.client.allows(&directory.root.join(relative)))
+            .map(|relative| Resource {
+                uri: directory.uri(relative),
+                name: relative.to_string_lossy().into_owned(),
+                description: Some(format!("{} in {}", relative.display(), directory.name)),
+                mime_type: mime_type(relative).map(str::to_string),
+            })
+            .collect()
+    }
+
+    fn read(&self, uri: &str) -> Option<String> {
+        let path = self
+            .directory
+            .resolve(uri)
+            .filter(|path| self.client.allows(path))?;
+        match fs::read_to_string(&path) {
+            Ok(content) => Some(content),
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+/// The MIME type of the files served, going by their extension
+fn mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "md" | "markdown" => Some("text/markdown"),
+        "html" | "htm" => Some("text/html"),
+        "txt" => Some("text/plain"),
+        _ => None,
+    }
+}
@@ -1,77 +1,958 @@
+mod argument_validation;
+mod audit_resource;
+mod auth;
+mod client_quota;
+mod client_requests;
+mod completion_provider;
+mod config;
+mod history_resources;
+mod http_transport;
+mod local_resources;
+mod no_redirect_client;
+mod offloaded_results;
+mod plugin_tool;
 mod prompt_registry;
 mod resource_registry;
+mod run_command;
+mod self_test;
+mod stats_resource;
+mod stdio_transport;
+mod telemetry;
+mod template_prompt;
+mod tool_middleware;
 mod tool_registry;
+#[cfg(feature = "wasm")]
+mod wasm_plugin;
 
-use std::{env, sync::Arc};
+use std::{collections::HashSet, env, process::ExitCode, sync::Arc, time::Duration};
 
 use anyhow::Result;
-use context_server::{ContextServer, ContextServerRpcRequest, ContextServerRpcResponse};
+use context_server::{ContextServer, ContextServerRpcRequest};
 use http_client::HttpClient;
 use http_client_reqwest::HttpClientReqwest;
-use read_mcp_tools::{FetchRawTool, ReadUrlTool};
-use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use parking_lot::Mutex;
+use read_mcp_tools::{
+    AuditCaller, AuditLog, ChangedSinceLastReadTool, CiteUrlTool, ClientRoots, ContentPolicy,
+    CookieJar, FallbackOptions, FetchHistory, FetchRawTool, Fetcher, HeadingLevels, ListFeedsTool,
+    ReadCsvTool, ReadJsonApiTool, ReadSitemapTool, ReadUrlTool, SaveArticleTool, ServerStats,
+    SetCookiesTool, SqliteStore,
+};
+use serde_json::{Map, Value, json};
+use tokio::sync::watch;
 
 use crate::{
-    prompt_registry::PromptRegistry, resource_registry::ResourceRegistry,
-    tool_registry::ToolRegistry,
+    argument_validation::missing_arguments_schema,
+    audit_resource::AuditResource,
+    client_requests::ClientChannel,
+    completion_provider::CompletionProvider,
+    config::{Config, PolicyConfig, StatsConfig, StoreConfig, ToolsConfig},
+    history_resources::HistoryResources,
+    local_resources::{ClientDirectory, LocalDirectory},
+    no_redirect_client::NoRedirectClient,
+    offloaded_results::OffloadedResults,
+    plugin_tool::PluginTool,
+    prompt_registry::PromptRegistry,
+    resource_registry::ResourceRegistry,
+    run_command::RunCommand,
+    stats_resource::StatsResource,
+    template_prompt::TemplatePrompt,
+    tool_middleware::StatsMiddleware,
+    tool_registry::{RegisteredTool, ToolLimits, ToolRegistry},
 };
 
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Sent as the `instructions` of the `initialize` result unless configured otherwise
+const DEFAULT_INSTRUCTIONS: &str = "\
+This server reads the web for you. Use read_url to read a page as clean markdown; \
+it is the right choice for articles, documentation and most other pages. Use \
+fetch_raw only when you need the response exactly as served, such as HTML markup, \
+plain text or data files. To find pages on a site, read its sitemap with \
+read_sitemap or its feeds with list_feeds rather than guessing URLs. Prefer \
+read_json_api and read_csv for JSON and CSV data. Use changed_since_last_read to \
+check whether a page you read before has changed instead of reading it again. \
+Long results may be cut short with a read://results resource holding the rest.";
+
+/// Severities of MCP logging notifications, least severe first
+const LOG_LEVELS: &[&str] = &[
+    "debug",
+    "info",
+    "notice",
+    "warning",
+    "error",
+    "critical",
+    "alert",
+    "emergency",
+];
+
+/// Process-wide services shared by every client session
+struct Services {
+    /// The configuration in effect; sessions subscribe to it to apply reloads
+    config: watch::Sender<Arc<Config>>,
+    http_client: Arc<dyn HttpClient>,
+    fetcher: Arc<Fetcher>,
+    stats: Arc<ServerStats>,
+    /// Cookies configured in `[[sessions]]`, under those of every client session
+    cookies: Arc<CookieJar>,
+    audit: Option<Arc<AuditLog>>,
+    directories: Vec<Arc<LocalDirectory>>,
+    /// Plugins are set up once, as WASM modules are compiled when loaded
+    plugins: Vec<Arc<PluginTool>>,
+    /// Bounds tool calls across every session together
+    tool_limits: Arc<ToolLimits>,
+}
+
+impl Services {
+    /// `fetch_client` serves the fetcher and must leave redirects to it
+    fn new(
+        http_client: Arc<dyn HttpClient>,
+        fetch_client: Arc<dyn HttpClient>,
+        config: Config,
+    ) -> Result<Self> {
+        let stats = match &config.stats {
+            Some(stats_config) => {
+                let stats = Arc::new(ServerStats::default().with_file(&stats_config.path)?);
+                spawn_stats_saving(stats.clone(), stats_config);
+                stats
+            }
+            None => Arc::new(ServerStats::default()),
+        };
+
+        let cookies = Arc::new(CookieJar::default());
+        for session in &config.sessions {
+            cookies.set(&session.domain, session.cookies.clone());
+        }
+
+        let policy = content_policy(&config.policy)?;
+
+        let audit = match &config.audit {
+            Some(audit_config) => {
+                let mut audit = AuditLog::with_capacity(audit_config.recent_entries);
+                if let Some(path) = &audit_config.path {
+                    audit = audit.with_file(path)?;
+                }
+                Some(Arc::new(audit))
+            }
+            None => None,
+        };
+
+        let directories = config
+            .directories
+            .iter()
+            .map(|directory| LocalDirectory::new(directory).map(Arc::new))
+            .collect::<Result<Vec<_>>>()?;
+
+        let tool_limits = Arc::new(ToolLimits::new(
+            config.limits.global(),
+            config.limits.per_tool(),
+        ));
+
+        let plugins = config
+            .plugins
+            .iter()
+            .map(|plugin| PluginTool::new(plugin.clone()).map(Arc::new))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut fetcher = Fetcher::new(fetch_client)
+            .with_stats(stats.clone())
+            .with_cookies(cookies.clone())
+            .with_browser_headers(config.fetch.impersonate_browser)
+            .with_content_policy(policy);
+        if let Some(seconds) = config.fetch.max_retry_wait_seconds {
+            fetcher = fetcher.with_max_retry_wait(Duration::from_secs(seconds));
+        }
+        for headers in &config.headers {
+            fetcher = fetcher.with_domain_headers(&headers.domain, headers.values.clone())?;
+        }
+        if let Some(audit) = &audit {
+            fetcher = fetcher.with_audit_log(audit.clone());
+        }
+        let fetcher = Arc::new(fetcher);
+
+        Ok(Self {
+            config: watch::Sender::new(Arc::new(config)),
+            http_client,
+            fetcher,
+            stats,
+            cookies,
+            audit,
+            directories,
+            plugins,
+            tool_limits,
+        })
+    }
+
+    /// Save the statistics before exiting, when they are persisted
+    fn save_stats(&self) {
+        if let Err(e) = self.stats.save() {
+            eprintln!("Error saving stats: {:#}", e);
+        }
+    }
+
+    fn config(&self) -> Arc<Config> {
+        self.config.borrow().clone()
+    }
+
+    /// Read the config file again and apply the settings that can change while
+    /// running, returning the sections that changed. An invalid file changes nothing.
+    fn reload(&self) -> Result<Vec<&'static str>> {
+        let config = Config::load()?;
+        let previous = self.config();
+
+        let policy = content_policy(&config.policy)?;
+        self.fetcher.set_domain_headers(
+            config
+                .headers
+                .iter()
+                .map(|headers| (headers.domain.as_str(), headers.values.clone())),
+        )?;
+        // Domain lists are read again even when the config file is unchanged
+        self.fetcher.set_content_policy(policy);
+
+        // Cookies sites have rotated since are kept by the client sessions
+        for session in &previous.sessions {
+            if !config.sessions.contains(session) {
+                self.cookies.clear(&session.domain);
+            }
+        }
+        for session in &config.sessions {
+            if !previous.sessions.contains(session) {
+                self.cookies.set(&session.domain, session.cookies.clone());
+            }
+        }
+
+        let changes = previous.reloadable_changes(&config);
+        if changes.contains(&"limits") {
+            self.tool_limits
+                .set(config.limits.global(), config.limits.per_tool());
+        }
+        self.config.send_if_modified(|current| {
+            *current = Arc::new(config);
+            !changes.is_empty()
+        });
+        Ok(changes)
+    }
+}
+
+fn content_policy(config: &PolicyConfig) -> Result<ContentPolicy> {
+    let mut policy = ContentPolicy::default()
+        .with_blocked_mime_types(&config.blocked_mime_types)
+        .with_blocked_extensions(&config.blocked_extensions);
+    for list in &config.blocked_domain_lists {
+        policy = policy.with_blocked_domains(&list.category, list.load()?);
+    }
+    Ok(policy)
+}
+
+/// The state of a single client session
 struct ContextServerState {
+    /// Identifies the session's fetches in the audit log
+    session_id: String,
     rpc: ContextServer,
+    tools: Arc<ToolRegistry>,
+    /// Every tool the session can offer, enabled or not, so reloads can change which are
+    available_tools: Vec<RegisteredTool>,
+    completions: CompletionProvider,
+    /// The configuration last applied to the session
+    config: Mutex<Arc<Config>>,
+    /// Least severe logging notification the client wants, as an index into `LOG_LEVELS`
+    log_level: Mutex<usize>,
+    client: Arc<ClientChannel>,
+    offloaded_results: Arc<OffloadedResults>,
+    /// Cookies set through set_cookies or rotated by sites, over the configured ones
+    cookies: Arc<CookieJar>,
 }
 
 impl ContextServerState {
-    fn new(http_client: Arc<dyn HttpClient>) -> Result<Self> {
+    fn new(services: &Services, session_id: &str, history: Arc<FetchHistory>) -> Result<Self> {
+        let config = services.config();
+        let fetcher = &services.fetcher;
+        let stats = &services.stats;
+
+        let client = Arc::new(ClientChannel::default());
         let resource_registry = Arc::new(ResourceRegistry::default());
+        resource_registry.register_provider(Arc::new(HistoryResources::new(history.clone())));
+        // Over HTTP the counters are shared by every client; only the operator's
+        // stats file tells which sites were read
+        let mut stats_resource = StatsResource::new(stats.clone());
+        if config.http.is_some() {
+            stats_resource = stats_resource.without_domains();
+        }
+        resource_registry.register_provider(Arc::new(stats_resource));
+        for directory in &services.directories {
+            resource_registry.register_provider(Arc::new(ClientDirectory::new(
+                directory.clone(),
+                client.clone(),
+            )));
+        }
+        if let Some(audit) = &services.audit {
+            resource_registry.register_provider(Arc::new(AuditResource::new(
+                audit.clone(),
+                session_id.to_string(),
+            )));
+        }
+
+        let mut read_url = ReadUrlTool::new(fetcher.clone())
+            .with_history(history.clone())
+            .with_boilerplate_phrases(config.extraction.boilerplate_phrases.clone())
+            .with_heading_levels(if config.extraction.preserve_heading_levels {
+                HeadingLevels::Preserve
+            } else {
+                HeadingLevels::Demote
+            });
+        if let Some(max_length) = config.extraction.max_length {
+            read_url = read_url.with_max_length(max_length);
+        }
+        if let Some(chunk_size) = config.extraction.chunk_size {
+            read_url = read_url.with_chunk_size(chunk_size);
+        }
+        let fallback = &config.extraction.fallback;
+        let mut fallback_options = FallbackOptions::default()
+            .with_links(fallback.links)
+            .with_tables(fallback.tables);
+        if let Some(skip_tags) = &fallback.skip_tags {
+            fallback_options = fallback_options.with_skip_tags(skip_tags);
+        }
+        read_url = read_url.with_fallback_options(fallback_options);
+        if let Some(engine) = &config.extraction.engine {
+            read_url = read_url.with_engine(engine)?;
+        }
+        for domain_engine in &config.extraction.domain_engines {
+            read_url = read_url.with_domain_engine(&domain_engine.domain, &domain_engine.engine)?;
+        }
+
+        let read_url = Arc::new(read_url);
 
-        let tool_registry = Arc::new(ToolRegistry::default());
-        tool_registry.register(Arc::new(ReadUrlTool::new(http_client.clone())));
-        tool_registry.register(Arc::new(FetchRawTool::new(http_client.clone())));
+        let mut tools = vec![
+            RegisteredTool::structured(read_url.clone()).with_annotations(ReadUrlTool::ANNOTATIONS),
+            RegisteredTool::structured(Arc::new(
+                FetchRawTool::new(fetcher.clone()).with_history(history.clone()),
+            ))
+            .with_annotations(FetchRawTool::ANNOTATIONS),
+            RegisteredTool::structured(Arc::new(ChangedSinceLastReadTool::new(
+                read_url.clone(),
+                history.clone(),
+            )))
+            .with_annotations(ChangedSinceLastReadTool::ANNOTATIONS),
+            RegisteredTool::structured(Arc::new(CiteUrlTool::new(read_url.clone())))
+                .with_annotations(CiteUrlTool::ANNOTATIONS),
+            RegisteredTool::structured(Arc::new(ListFeedsTool::new(fetcher.clone())))
+                .with_annotations(ListFeedsTool::ANNOTATIONS),
+            RegisteredTool::structured(Arc::new(ReadJsonApiTool::new(fetcher.clone())))
+                .with_annotations(ReadJsonApiTool::ANNOTATIONS),
+            RegisteredTool::structured(Arc::new(ReadCsvTool::new(fetcher.clone())))
+                .with_annotations(ReadCsvTool::ANNOTATIONS),
+            RegisteredTool::structured(Arc::new(ReadSitemapTool::new(fetcher.clone())))
+                .with_annotations(ReadSitemapTool::ANNOTATIONS),
+        ];
+        let cookies = Arc::new(CookieJar::default().with_base(services.cookies.clone()));
+        if config.fetch.client_cookies {
+            tools.push(
+                RegisteredTool::new(Arc::new(SetCookiesTool::new(cookies.clone())))
+                    .with_annotations(SetCookiesTool::ANNOTATIONS),
+            );
+        }
+        if let Some(save) = &config.save {
+            tools.push(
+                RegisteredTool::structured(Arc::new(
+                    SaveArticleTool::new(read_url, save.roots.clone())
+                        .with_client_roots(client.clone()),
+                ))
+                .with_annotations(SaveArticleTool::ANNOTATIONS),
+            );
+        }
+        for plugin_tool in &services.plugins {
+            let plugin = plugin_tool.config();
+            let mut tool = if plugin.output_schema.is_some() {
+                RegisteredTool::structured(plugin_tool.clone())
+            } else {
+                RegisteredTool::new(plugin_tool.clone())
+            };
+            if let Some(annotations) = plugin.annotations {
+                tool = tool.with_annotations(annotations);
+            }
+            tools.push(tool);
+        }
+
+        let tool_registry =
+            Arc::new(ToolRegistry::default().with_limits(services.tool_limits.clone()));
+        tool_registry.add_middleware(Arc::new(StatsMiddleware::new(stats.clone())));
+        let offloaded_results = Arc::new(OffloadedResults::new(&config.limits));
+        tool_registry.add_middleware(offloaded_results.clone());
+        resource_registry.register_provider(offloaded_results.clone());
+        tool_registry.set_tools(exposed_tools(&tools, &config.tools));
 
         let prompt_registry = Arc::new(PromptRegistry::default());
+        for prompt in &config.prompts {
+            prompt_registry.register(Arc::new(TemplatePrompt::new(
+                prompt.clone(),
+                tool_registry.clone(),
+            )?));
+        }
         Ok(Self {
+            session_id: session_id.to_string(),
             rpc: ContextServer::builder()
                 .with_server_info((env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")))
                 .with_resources(resource_registry)
-                .with_tools(tool_registry)
+                .with_tools(tool_registry.clone())
                 .with_prompts(prompt_registry)
                 .build()?,
+            tools: tool_registry,
+            available_tools: tools,
+            completions: CompletionProvider::new(history),
+            config: Mutex::new(config),
+            log_level: Mutex::new(0),
+            client,
+            offloaded_results,
+            cookies,
         })
     }
 
-    async fn process_request(
+    /// Apply a reloaded configuration to the session, returning the notifications
+    /// that tell the client about it
+    fn apply_config(&self, config: Arc<Config>) -> Vec<Value> {
+        let previous = std::mem::replace(&mut *self.config.lock(), config.clone());
+        let changes = previous.reloadable_changes(&config);
+        if changes.is_empty() {
+            return Vec::new();
+        }
+
+        let mut notifications = Vec::new();
+        if changes.contains(&"limits") {
+            self.offloaded_results.set_limits(&config.limits);
+        }
+        if changes.contains(&"tools")
+            && self
+                .tools
+                .set_tools(exposed_tools(&self.available_tools, &config.tools))
+        {
+            notifications.push(json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/tools/list_changed",
+            }));
+        }
+        if LOG_LEVELS[*self.log_level.lock()..].contains(&"info") {
+            notifications.push(json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/message",
+                "params": {
+                    "level": "info",
+                    "logger": env!("CARGO_PKG_NAME"),
+                    "data": format!("Configuration reloaded; changed: {}", changes.join(", ")),
+                },
+            }));
+        }
+        notifications
+    }
+
+    /// Handle a single message or a JSON-RPC batch, which is answered with the
+    /// responses to its requests in order
+    async fn process_payload(&self, payload: Value) -> Option<Value> {
+        let Value::Array(messages) = payload else {
+            return self.process_message(payload).await;
+        };
+
+        if messages.is_empty() {
+            return Some(rpc_error(
+                Value::Null,
+                INVALID_REQUEST,
+                "Invalid Request: empty batch",
+            ));
+        }
+
+        let mut responses = Vec::new();
+        for message in messages {
+            if let Some(response) = self.process_message(message).await {
+                responses.push(response);
+            }
+        }
+
+        // A batch of notifications gets no response at all
+        (!responses.is_empty()).then_some(Value::Array(responses))
+    }
+
+    #[tracing::instrument(
+        name = "mcp.request",
+        skip_all,
+        fields(rpc.method = message.get("method").and_then(|method| method.as_str()).unwrap_or_default())
+    )]
+    async fn process_message(&self, message: Value) -> Option<Value> {
+        if self.client.resolve(&message) {
+            return None;
+        }
+        let id = match check_request(&message) {
+            Ok(id) => id,
+            Err(error) => return Some(error),
+        };
+
+        // Tool calls, including those prompts make, fetch with the session's cookies
+        let response = self
+            .cookies
+            .clone()
+            .scope(self.handle_request(message))
+            .await;
+        match response {
+            Ok(response) => response,
+            Err(e) => match id {
+                Some(id) => Some(rpc_error(id, INTERNAL_ERROR, format!("{:#}", e))),
+                None => {
+                    eprintln!("Error handling notification: {:#}", e);
+                    None
+                }
+            },
+        }
+    }
+
+    async fn handle_request(&self, message: Value) -> Result<Option<Value>> {
+        let method = message
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        if method == "initialize" {
+            self.client
+                .set_capabilities(message.get("params").and_then(|p| p.get("capabilities")));
+        }
+        // Local files are offered within the client's roots, listed before they're needed
+        match method.as_str() {
+            "notifications/roots/list_changed" => {
+                let client = self.client.clone();
+                // Answered over the same transport, so not waited for here
+                tokio::spawn(async move { client.refresh_roots().await });
+                return Ok(None);
+            }
+            "resources/list" | "resources/read" => {
+                self.client.roots().await;
+            }
+            _ => {}
+        }
+
+        // Methods the underlying context server doesn't implement are answered here
+        let result = match method.as_str() {
+            "completion/complete" => Some(self.completions.complete(message.get("params"))),
+            "logging/setLevel" => Some(self.set_log_level(message.get("params"))),
+            "ping" => Some(json!({})),
+            "tools/call" => Some(self.call_tool(message.get("params")).await),
+            _ => None,
+        };
+        if let Some(result) = result {
+            return Ok(Some(json!({
+                "jsonrpc": "2.0",
+                "id": message.get("id").cloned().unwrap_or(Value::Null),
+                "result": result,
+            })));
+        }
+
+        let request: ContextServerRpcRequest = serde_json::from_value(message)?;
+        let Some(response) = self.rpc.handle_incoming_message(request).await? else {
+            return Ok(None);
+        };
+
+        let mut response = serde_json::to_value(response)?;
+        if method == "initialize"
+            && let Some(result) = response.get_mut("result").and_then(Value::as_object_mut)
+        {
+            self.introduce(result);
+        }
+        if method == "initialize"
+            && let Some(capabilities) = response
+                .get_mut("result")
+                .and_then(|r| r.get_mut("capabilities"))
+                .and_then(Value::as_object_mut)
+        {
+            capabilities.insert("completions".into(), json!({}));
+            capabilities.insert("logging".into(), json!({}));
+            if let Some(tools) = capabilities.get_mut("tools").and_then(Value::as_object_mut) {
+                tools.insert("listChanged".into(), json!(true));
+            }
+        }
+        if method == "tools/list"
+            && let Some(tools) = response
+                .get_mut("result")
+                .and_then(|r| r.get_mut("tools"))
+                .and_then(Value::as_array_mut)
+        {
+            for tool in tools {
+                let name = tool.get("name").and_then(Value::as_str).unwrap_or_default();
+                let output_schema = self.tools.output_schema(name);
+                let annotations = self.tools.annotations(name);
+                if let Some(schema) = output_schema {
+                    tool["outputSchema"] = schema;
+                }
+                if let Some(annotations) = annotations {
+                    tool["annotations"] = annotations.to_json();
+                }
+            }
+        }
+
+        Ok(Some(response))
+    }
+
+    /// Add the configured instructions and server details to an `initialize` result
+    fn introduce(&self, result: &mut Map<String, Value>) {
+        let config = self.config.lock().clone();
+        let server = &config.server;
+
+        let instructions = server
+            .instructions
+            .as_deref()
+            .unwrap_or(DEFAULT_INSTRUCTIONS);
+        if !instructions.trim().is_empty() {
+            result.insert("instructions".into(), json!(instructions));
+        }
+        if let Some(info) = result.get_mut("serverInfo").and_then(Value::as_object_mut) {
+            if let Some(title) = &server.title {
+                info.insert("title".into(), json!(title));
+            }
+            if let Some(website_url) = &server.website_url {
+                info.insert("websiteUrl".into(), json!(website_url));
+            }
+        }
+    }
+
+    /// Unknown levels leave the current one in place
+    fn set_log_level(&self, params: Option<&Value>) -> Value {
+        let level = params
+            .and_then(|p| p.get("level"))
+            .and_then(Value::as_str)
+            .and_then(|level| LOG_LEVELS.iter().position(|known| *known == level));
+        if let Some(level) = level {
+            *self.log_level.lock() = level;
+        }
+        json!({})
+    }
+
+    /// Tool calls bypass the context server, which can neither return
+    /// `structuredContent` nor report a failed call as a result with `isError`
+    async fn call_tool(&self, params: Option<&Value>) -> Value {
+        let name = params
+            .and_then(|p| p.get("name"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let mut arguments = params.and_then(|p| p.get("arguments")).cloned();
+        if self.client.supports_elicitation() {
+            arguments = self.elicit_missing_arguments(name, arguments).await;
+        }
+
+        let caller = AuditCaller {
+            session: self.session_id.clone(),
+            tool: name.to_string(),
+        };
+        match caller.scope(self.tools.execute(name, arguments)).await {
+            Ok(output) => {
+                let mut result = json!({ "content": output.content });
+                if let Some(structured_content) = output.structured_content {
+                    result["structuredContent"] = structured_content;
+                }
+                result
+            }
+            Err(e) => json!({
+                "content": [{ "type": "text", "text": e.to_string() }],
+                "isError": true,
+            }),
+        }
+    }
+
+    /// Ask the user for the arguments the call lacks, or whose URL is not
+    /// absolute; the arguments are left as they are if they won't say
+    async fn elicit_missing_arguments(
         &self,
-        request: ContextServerRpcRequest,
-    ) -> Result<Option<ContextServerRpcResponse>> {
-        let response = self.rpc.handle_incoming_message(request).await;
-        response
+        tool: &str,
+        arguments: Option<Value>,
+    ) -> Option<Value> {
+        let requested = self
+            .tools
+            .input_schema(tool)
+            .and_then(|schema| missing_arguments_schema(&schema, arguments.as_ref()));
+        let Some(requested) = requested else {
+            return arguments;
+        };
+
+        let fields = requested["required"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = format!("{} needs a value for {}", tool, fields);
+        match self.client.elicit(&message, requested).await {
+            Ok(Some(answers)) => {
+                let mut arguments = match arguments {
+                    Some(Value::Object(arguments)) => arguments,
+                    _ => Map::new(),
+                };
+                arguments.extend(answers);
+                Some(Value::Object(arguments))
+            }
+            Ok(None) => arguments,
+            Err(e) => {
+                eprintln!("Error asking for {} arguments: {:#}", tool, e);
+                arguments
+            }
+        }
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let http_client = Arc::new(HttpClientReqwest::default());
-    let state = ContextServerState::new(http_client)?;
+/// The id of a well-formed request, `None` for a notification, or the error
+/// response to a message that is not a valid JSON-RPC request
+fn check_request(message: &Value) -> Result<Option<Value>, Value> {
+    let invalid = |id: Value, problem: &str| {
+        rpc_error(id, INVALID_REQUEST, format!("Invalid Request: {}", problem))
+    };
 
-    let mut stdin = BufReader::new(io::stdin()).lines();
-    let mut stdout = io::stdout();
+    let Some(request) = message.as_object() else {
+        return Err(invalid(Value::Null, "not an object"));
+    };
+    let id = match request.get("id") {
+        None => None,
+        Some(id @ (Value::String(_) | Value::Number(_) | Value::Null)) => Some(id.clone()),
+        Some(_) => return Err(invalid(Value::Null, "id must be a string or a number")),
+    };
+    let reply_to = id.clone().unwrap_or(Value::Null);
+    if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err(invalid(reply_to, "jsonrpc must be \"2.0\""));
+    }
+    if !request.get("method").is_some_and(Value::is_string) {
+        return Err(invalid(reply_to, "method must be a string"));
+    }
+    Ok(id)
+}
 
-    while let Some(line) = stdin.next_line().await? {
-        let request: ContextServerRpcRequest = match serde_json::from_str(&line) {
-            Ok(req) => req,
-            Err(e) => {
-                eprintln!("Error parsing request: {}", e);
-                continue;
+/// A JSON-RPC error response to the request with `id`
+fn rpc_error(id: Value, code: i64, message: impl Into<String>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message.into() },
+    })
+}
+
+/// The enabled tools under each of the names `config` exposes them as; a name
+/// already taken by another tool is skipped
+fn exposed_tools(tools: &[RegisteredTool], config: &ToolsConfig) -> Vec<RegisteredTool> {
+    let mut taken = HashSet::new();
+    let mut exposed = Vec::new();
+    for tool in tools {
+        for name in config.exposed_names(&tool.own_name()) {
+            if taken.insert(name.clone()) {
+                exposed.push(tool.clone().with_name(name));
+            } else {
+                eprintln!(
+                    "Not exposing {} as {}; another tool has that name",
+                    tool.own_name(),
+                    name
+                );
             }
-        };
-        if let Some(response) = state.process_request(request).await? {
-            let response_json = serde_json::to_string(&response)?;
-            stdout.write_all(response_json.as_bytes()).await?;
-            stdout.write_all(b"\n").await?;
-            stdout.flush().await?;
-        } else {
         }
     }
+    exposed
+}
+
+fn spawn_store_pruning(store: Arc<SqliteStore>, config: &StoreConfig) {
+    let max_entries = config.max_entries;
+    let max_age = config
+        .ttl_days
+        .map(|days| Duration::from_secs(days * 24 * 60 * 60));
+    let period = Duration::from_secs(config.prune_interval_minutes.max(1) * 60);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            match store.prune(max_entries, max_age) {
+                Ok(0) => {}
+                Ok(removed) => eprintln!("Pruned {} stored fetches", removed),
+                Err(e) => eprintln!("Error pruning store: {}", e),
+            }
+        }
+    });
+}
+
+fn spawn_stats_saving(stats: Arc<ServerStats>, config: &StatsConfig) {
+    let period = Duration::from_secs(config.save_interval_seconds.max(1));
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        // The first tick completes at once, with nothing new to save
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = stats.save() {
+                eprintln!("Error saving stats: {:#}", e);
+            }
+        }
+    });
+}
+
+/// The session id of the only client of the stdio transport
+const STDIO_SESSION_ID: &str = "stdio";
+
+/// Reload the configuration whenever the process receives SIGHUP
+#[cfg(unix)]
+fn spawn_reload_on_hangup(services: Arc<Services>) -> Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
 
+    let mut hangups = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            match services.reload() {
+                Ok(changes) if changes.is_empty() => {
+                    eprintln!("Reloaded configuration; nothing changed")
+                }
+                Ok(changes) => eprintln!("Reloaded configuration; changed: {}", changes.join(", ")),
+                Err(e) => eprintln!(
+                    "Error reloading configuration, keeping the current one: {:#}",
+                    e
+                ),
+            }
+        }
+    });
     Ok(())
 }
+
+#[tokio::main]
+async fn main() -> Result<ExitCode> {
+    let run_command = RunCommand::parse(env::args().skip(1))?;
+    let config = Config::load()?;
+    let _telemetry = telemetry::init(config.telemetry.as_ref())?;
+    let http_client = Arc::new(HttpClientReqwest::default());
+    let fetch_client = Arc::new(NoRedirectClient::new()?);
+    let services = Arc::new(Services::new(http_client, fetch_client, config)?);
+    #[cfg(unix)]
+    spawn_reload_on_hangup(services.clone())?;
+
+    // `--self-test`, or `--self-test=<url>` to also read a live page
+    let self_test = env::args().skip(1).find_map(|arg| match arg.as_str() {
+        "--self-test" => Some(None),
+        _ => arg
+            .strip_prefix("--self-test=")
+            .map(|url| Some(url.to_string())),
+    });
+    if let Some(live_url) = self_test {
+        self_test::run(services, live_url.as_deref()).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if let Some(command) = run_command {
+        let result = run_command::run(services.clone(), command).await;
+        services.save_stats();
+        result?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let config = services.config();
+    if let Some(http_config) = &config.http {
+        http_transport::serve(services.clone(), http_config).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let history = match &config.store {
+        Some(store_config) => {
+            let store = Arc::new(SqliteStore::open(&store_config.path)?);
+            spawn_store_pruning(store.clone(), store_config);
+            FetchHistory::default().with_store(store)?
+        }
+        None => FetchHistory::default(),
+    };
+    let state = Arc::new(ContextServerState::new(
+        &services,
+        STDIO_SESSION_ID,
+        Arc::new(history),
+    )?);
+
+    let shutdown = stdio_transport::serve(&services, state).await;
+
+    services.save_stats();
+    Ok(shutdown.exit_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use read_mcp_tools::test_support::MockHttpClient;
+
+    use super::*;
+
+    fn session() -> ContextServerState {
+        let client = Arc::new(MockHttpClient::new());
+        let services = Services::new(client.clone(), client, Config::default()).unwrap();
+        ContextServerState::new(&services, "test", Arc::new(FetchHistory::default())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_batch_responses_keep_request_order() {
+        let session = session();
+        let response = session
+            .process_payload(json!([
+                { "jsonrpc": "2.0", "id": 1, "method": "ping" },
+                { "id": 2, "method": "ping" },
+                { "jsonrpc": "2.0", "method": "notifications/roots/list_changed" },
+                { "jsonrpc": "2.0", "id": "a", "method": "ping" },
+            ]))
+            .await
+            .unwrap();
+
+        let responses = response.as_array().unwrap();
+        let ids = responses
+            .iter()
+            .map(|r| r["id"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![json!(1), json!(2), json!("a")]);
+        assert_eq!(responses[0]["result"], json!({}));
+        assert_eq!(responses[1]["error"]["code"], INVALID_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_empty_and_notification_only_batches() {
+        let session = session();
+        let response = session.process_payload(json!([])).await.unwrap();
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+
+        let notifications = json!([
+            { "jsonrpc": "2.0", "method": "notifications/roots/list_changed" },
+        ]);
+        assert_eq!(session.process_payload(notifications).await, None);
+
+        // A single message is answered with an object, not an array
+        let response = session
+            .process_payload(json!({ "jsonrpc": "2.0", "id": 7, "method": "ping" }))
+            .await
+            .unwrap();
+        assert_eq!(response["id"], 7);
+    }
+
+    #[test]
+    fn test_check_request() {
+        assert_eq!(
+            check_request(&json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" })),
+            Ok(Some(json!(1)))
+        );
+        assert_eq!(
+            check_request(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })),
+            Ok(None)
+        );
+
+        let error = check_request(&json!("ping")).unwrap_err();
+        assert_eq!(error["id"], Value::Null);
+        assert_eq!(error["error"]["code"], INVALID_REQUEST);
+        // An id that can't be echoed back is answered with null
+        let error =
+            check_request(&json!({ "jsonrpc": "2.0", "id": [1], "method": "ping" })).unwrap_err();
+        assert_eq!(error["id"], Value::Null);
+        let error =
+            check_request(&json!({ "jsonrpc": "1.0", "id": 3, "method": "ping" })).unwrap_err();
+        assert_eq!(error["id"], 3);
+        let error = check_request(&json!({ "jsonrpc": "2.0", "id": 4, "method": 5 })).unwrap_err();
+        assert_eq!(
+            error,
+            rpc_error(
+                json!(4),
+                INVALID_REQUEST,
+                "Invalid Request: method must be a string"
+            )
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use http_client::{AsyncBody, HttpClient, Request, Response};
+use reqwest::redirect::Policy;
+
+/// An `HttpClient` that hands 3xx responses back instead of following them, so
+/// the fetcher can check every hop of a redirect against the content policy.
+/// Only sends the body-less GET and HEAD requests the fetcher makes; request
+/// bodies are not forwarded.
+pub struct NoRedirectClient(reqwest::Client);
+
+impl NoRedirectClient {
+    pub fn new() -> Result<Self> {
+        Ok(NoRedirectClient(
+            reqwest::Client::builder()
+                .redirect(Policy::none())
+                .build()?,
+        ))
+    }
+}
+
+#[async_trait]
+impl HttpClient for NoRedirectClient {
+    async fn send(&self, request: Request<AsyncBody>) -> Result<Response<AsyncBody>> {
+        let response = self
+            .0
+            .request(request.method().clone(), request.uri().to_string())
+            .headers(request.headers().clone())
+            .send()
+            .await?;
+
+        let mut builder = Response::builder()
+            .status(response.status())
+            .version(response.version());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+        }
+        let body = response.bytes().await?;
+        Ok(builder.body(AsyncBody::from(body.to_vec()))?)
+    }
+}
@@ -0,0 +1,185 @@
+use std::{collections::VecDeque, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use context_server::{Resource, ToolContent};
+use parking_lot::{Mutex, RwLock};
+use read_mcp_tools::ToolOutput;
+use serde_json::Value;
+
+use crate::{
+    config::LimitsConfig, resource_registry::ResourceProvider, tool_middleware::ToolMiddleware,
+};
+
+const RESULTS_URI: &str = "read://results";
+
+const DEFAULT_PREVIEW_CHARS: usize = 2_000;
+
+/// Offloaded results a session keeps before the oldest are dropped
+const MAX_STORED_RESULTS: usize = 50;
+
+/// Stores the text of tool results over the configured size as
+/// `read://results/{id}` resources, handing the client a preview and the
+/// resource's URI instead
+pub struct OffloadedResults {
+    limits: RwLock<OffloadLimits>,
+    stored: Mutex<Stored>,
+}
+
+#[derive(Clone, Copy)]
+struct OffloadLimits {
+    /// Characters of text above which a result is offloaded; never when unset
+    max_chars: Option<usize>,
+    preview_chars: usize,
+}
+
+#[derive(Default)]
+struct Stored {
+    next_id: u64,
+    /// Most recent first
+    results: VecDeque<StoredResult>,
+}
+
+struct StoredResult {
+    id: u64,
+    tool: String,
+    text: String,
+}
+
+impl OffloadedResults {
+    pub fn new(config: &LimitsConfig) -> Self {
+        let offloaded = OffloadedResults {
+            limits: RwLock::new(OffloadLimits {
+                max_chars: None,
+                preview_chars: DEFAULT_PREVIEW_CHARS,
+            }),
+            stored: Mutex::new(Stored::default()),
+        };
+        offloaded.set_limits(config);
+        offloaded
+    }
+
+    /// Apply the result size limits of `config` to calls made from now on
+    pub fn set_limits(&self, config: &LimitsConfig) {
+        *self.limits.write() = OffloadLimits {
+            max_chars: config.max_result_chars,
+            preview_chars: config.preview_chars.unwrap_or(DEFAULT_PREVIEW_CHARS),
+        };
+    }
+
+    fn store(&self, tool: &str, text: String) -> u64 {
+        let mut stored = self.stored.lock();
+        stored.next_id += 1;
+        let id = stored.next_id;
+        stored.results.push_front(StoredResult {
+            id,
+            tool: tool.to_string(),
+            text,
+        });
+        stored.results.truncate(MAX_STORED_RESULTS);
+        id
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for OffloadedResults {
+    async fn after(
+        &self,
+        tool: &str,
+        _arguments: Option<&Value>,
+        _elapsed: Duration,
+        result: Result<ToolOutput>,
+    ) -> Result<ToolOutput> {
+        let limits = *self.limits.read();
+        let Some(max_chars) = limits.max_chars else {
+            return result;
+        };
+        let mut output = result?;
+
+        let text = output
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ToolContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let length = text.chars().count();
+        if length <= max_chars {
+            return Ok(output);
+        }
+
+        let preview = preview(&text, limits.preview_chars).to_string();
+        let id = self.store(tool, text);
+
+        // Images and embedded resources are passed on as they are
+        output
+            .content
+            .retain(|block| !matches!(block, ToolContent::Text { .. }));
+        output.content.insert(
+            0,
+            ToolContent::Text {
+                text: format!(
+                    "{}\n\n[…]\n\nThis result is {} characters long, so only the start is shown. \
+                     Read the resource {}/{} for all of it.",
+                    preview, length, RESULTS_URI, id
+                ),
+            },
+        );
+        Ok(output)
+    }
+}
+
+impl ResourceProvider for OffloadedResults {
+    fn list(&self) -> Vec<Resource> {
+        self.stored
+            .lock()
+            .results
+            .iter()
+            .map(|result| Resource {
+                uri: format!("{}/{}", RESULTS_URI, result.id),
+                name: format!("{} result {}", result.tool, result.id),
+                description: Some(format!(
+                    "Full text of a {} result too long to return, {} characters",
+                    result.tool,
+                    result.text.chars().count()
+                )),
+                mime_type: Some("text/plain".to_string()),
+            })
+            .collect()
+    }
+
+    fn read(&self, uri: &str) -> Option<String> {
+        let id = uri
+            .strip_prefix(RESULTS_URI)?
+            .strip_prefix('/')?
+            .parse::<u64>()
+            .ok()?;
+        self.stored
+            .lock()
+            .results
+            .iter()
+            .find(|result| result.id == id)
+            .map(|result| result.text.clone())
+    }
+}
+
+/// The first `max_chars` characters of `text`, cut back to the last line or
+/// word break when there is one
+fn preview(text: &str, max_chars: usize) -> &str {
+    let end = text
+        .char_indices()
+        .nth(max_chars)
+        .map_or(text.len(), |(index, _)| index);
+    let cut = &text[..end];
+    let trimmed = cut
+        .rfind('\n')
+        .filter(|&index| index > end / 2)
+        .or_else(|| {
+            cut.rfind(char::is_whitespace)
+                .filter(|&index| index > end / 2)
+        })
+        .map_or(cut, |index| &cut[..index]);
+    trimmed.trim_end()
+}
@@ -0,0 +1,331 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use context_server::{Tool, ToolContent, ToolExecutor};
+use read_mcp_tools::{StructuredToolExecutor, ToolOutput};
+use serde_json::{Value, json};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::config::PluginConfig;
+#[cfg(feature = "wasm")]
+use crate::wasm_plugin::WasmModule;
+
+/// A tool backed by an external command or WASI module: the call arguments are
+/// written to its stdin as JSON and its stdout is parsed as the JSON result
+pub struct PluginTool {
+    config: PluginConfig,
+    runner: Runner,
+}
+
+enum Runner {
+    Command(PathBuf),
+    #[cfg(feature = "wasm")]
+    Wasm(WasmModule),
+}
+
+/// How a run ended: `failure` describes a non-zero exit
+struct RunOutput {
+    failure: Option<String>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl PluginTool {
+    /// Set up the plugin, compiling it first when it is a WASM module
+    pub fn new(config: PluginConfig) -> Result<Self> {
+        let runner = match (&config.command, &config.wasm) {
+            (Some(command), None) => Runner::Command(command.clone()),
+            (None, Some(path)) => wasm_runner(&config.name, path)?,
+            _ => {
+                return Err(anyhow!(
+                    "Plugin {} needs exactly one of command and wasm",
+                    config.name
+                ));
+            }
+        };
+        Ok(PluginTool { config, runner })
+    }
+
+    pub fn config(&self) -> &PluginConfig {
+        &self.config
+    }
+
+    async fn run(&self, arguments: Option<Value>) -> Result<Value> {
+        let input = serde_json::to_vec(&arguments.unwrap_or(Value::Null))?;
+        let output = match &self.runner {
+            Runner::Command(command) => self.run_command(command, input).await?,
+            #[cfg(feature = "wasm")]
+            Runner::Wasm(module) => {
+                let output = module
+                    .run(
+                        &self.config.name,
+                        &self.config.args,
+                        &self.config.env,
+                        input,
+                    )
+                    .await
+                    .with_context(|| format!("Plugin {} failed", self.config.name))?;
+                RunOutput {
+                    failure: (output.exit_code != 0)
+                        .then(|| format!("exit code: {}", output.exit_code)),
+                    stdout: output.stdout,
+                    stderr: output.stderr,
+                }
+            }
+        };
+
+        if let Some(failure) = output.failure {
+            return Err(anyhow!(
+                "Plugin {} failed ({}): {}",
+                self.config.name,
+                failure,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        serde_json::from_slice(&output.stdout)
+            .with_context(|| format!("Plugin {} returned invalid JSON", self.config.name))
+    }
+
+    async fn run_command(&self, command: &Path, input: Vec<u8>) -> Result<RunOutput> {
+        let mut child = Command::new(command)
+            .args(&self.config.args)
+            .envs(&self.config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start plugin {}", self.config.name))?;
+
+        // Arguments are written while the output is read: a plugin that answers
+        // before taking all its input would otherwise fill the stdout pipe and
+        // wait on us as we wait on it. Dropping stdin once written closes it.
+        let stdin = child.stdin.take();
+        let write_input = async move {
+            match stdin {
+                Some(mut stdin) => stdin.write_all(&input).await,
+                None => Ok(()),
+            }
+        };
+        let (written, output) = tokio::join!(write_input, child.wait_with_output());
+        let output = output?;
+        // A plugin may exit without reading everything, which is its business
+        if let Err(e) = written
+            && e.kind() != ErrorKind::BrokenPipe
+        {
+            return Err(e)
+                .with_context(|| format!("Failed to write to plugin {}", self.config.name));
+        }
+
+        Ok(RunOutput {
+            failure: (!output.status.success()).then(|| output.status.to_string()),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+}
+
+#[cfg(feature = "wasm")]
+fn wasm_runner(_name: &str, path: &Path) -> Result<Runner> {
+    Ok(Runner::Wasm(WasmModule::load(path)?))
+}
+
+#[cfg(not(feature = "wasm"))]
+fn wasm_runner(name: &str, _path: &Path) -> Result<Runner> {
+    Err(anyhow!(
+        "Plugin {} is a WASM module; this build lacks the wasm feature",
+        name
+    ))
+}
+
+#[async_trait]
+impl ToolExecutor for PluginTool {
+    async fn execute(&self, arguments: Option<Value>) -> Result<Vec<ToolContent>> {
+        Ok(to_tool_content(self.run(arguments).await?))
+    }
+
+    fn to_tool(&self) -> Tool {
+        Tool {
+            name: self.config.name.clone(),
+            description: self.config.description.clone(),
+            input_schema: self.config.input_schema.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl StructuredToolExecutor for PluginTool {
+    fn output_schema(&self) -> Value {
+        self.config
+            .output_schema
+            .clone()
+            .unwrap_or_else(|| json!({ "type": "object" }))
+    }
+
+    /// Uses the `structuredContent` of an MCP-style result, or the whole result
+    /// when it is any other JSON object
+    async fn execute_structured(&self, arguments: Option<Value>) -> Result<ToolOutput> {
+        let result = self.run(arguments).await?;
+        let structured_content = match result.get("content") {
+            Some(_) => result.get("structuredContent").cloned(),
+            None => result.is_object().then(|| result.clone()),
+        };
+
+        Ok(ToolOutput {
+            content: to_tool_content(result),
+            structured_content,
+        })
+    }
+}
+
+/// Accepts an MCP-style `{"content": [{"type": "text", "text": ...}]}` object, a
+/// bare string, or any other JSON value, which is returned pretty-printed
+fn to_tool_content(result: Value) -> Vec<ToolContent> {
+    if let Some(content) = result.get("content").and_then(Value::as_array) {
+        return content
+            .iter()
+            .filter_map(|item| item.get("text").and_then(Value::as_str))
+            .map(|text| ToolContent::Text {
+                text: text.to_string(),
+            })
+            .collect();
+    }
+
+    let text = match result {
+        Value::String(text) => text,
+        other => serde_json::to_string_pretty(&other).unwrap_or_default(),
+    };
+
+    vec![ToolContent::Text { text }]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use super::*;
+
+    fn shell_plugin(script: &str) -> PluginTool {
+        PluginTool::new(PluginConfig {
+            name: "shell".to_string(),
+            description: None,
+            command: Some("sh".into()),
+            wasm: None,
+            args: vec!["-c".to_string(), script.to_string()],
+            env: HashMap::new(),
+            input_schema: json!({ "type": "object" }),
+            output_schema: None,
+            annotations: None,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_plugin_round_trip() {
+        let plugin = shell_plugin("cat");
+        let result = plugin.run(Some(json!({ "text": "hello" }))).await.unwrap();
+        assert_eq!(result, json!({ "text": "hello" }));
+
+        let plugin = shell_plugin("echo oops >&2; exit 3");
+        let error = plugin.run(None).await.err().unwrap().to_string();
+        assert!(error.contains("oops"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_plugin_answering_before_reading_input() {
+        // More output and input than a pipe buffers, with all output written first
+        let plugin = shell_plugin(
+            r#"printf '"'; head -c 200000 /dev/zero | tr '\0' a; printf '"'; cat >/dev/null"#,
+        );
+        let text = "b".repeat(200_000);
+        let result = tokio::time::timeout(
+            Duration::from_secs(10),
+            plugin.run(Some(json!({ "text": text }))),
+        )
+        .await
+        .expect("plugin deadlocked")
+        .unwrap();
+        assert_eq!(result.as_str().map(str::len), Some(200_000));
+    }
+
+    #[test]
+    fn test_plugin_needs_one_runner() {
+        let mut config = shell_plugin("cat").config().clone();
+        config.wasm = Some("plugin.wasm".into());
+        assert!(PluginTool::new(config.clone()).is_err());
+        config.command = None;
+        config.wasm = None;
+        assert!(PluginTool::new(config).is_err());
+    }
+
+    #[cfg(feature = "wasm")]
+    fn wasm_plugin(name: &str, module: &str) -> PluginTool {
+        let path =
+            std::env::temp_dir().join(format!("read-mcp-{}-{}.wat", name, std::process::id()));
+        std::fs::write(&path, module).unwrap();
+        let plugin = PluginTool::new(PluginConfig {
+            name: name.to_string(),
+            description: None,
+            command: None,
+            wasm: Some(path.clone()),
+            args: Vec::new(),
+            env: HashMap::new(),
+            input_schema: json!({ "type": "object" }),
+            output_schema: None,
+            annotations: None,
+        });
+        std::fs::remove_file(&path).unwrap();
+        plugin.unwrap()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[tokio::test]
+    async fn test_wasm_plugin() {
+        // Copies stdin to stdout
+        let echo = wasm_plugin(
+            "echo",
+            r#"(module
+                (import "wasi_snapshot_preview1" "fd_read"
+                    (func $fd_read (param i32 i32 i32 i32) (result i32)))
+                (import "wasi_snapshot_preview1" "fd_write"
+                    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (func (export "_start")
+                    (i32.store (i32.const 0) (i32.const 64))
+                    (i32.store (i32.const 4) (i32.const 65000))
+                    (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+                    (i32.store (i32.const 4) (i32.load (i32.const 8)))
+                    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))"#,
+        );
+        let result = echo.run(Some(json!({ "text": "hello" }))).await.unwrap();
+        assert_eq!(result, json!({ "text": "hello" }));
+
+        let failing = wasm_plugin(
+            "failing",
+            r#"(module
+                (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+                (memory (export "memory") 1)
+                (func (export "_start") (call $proc_exit (i32.const 3))))"#,
+        );
+        let error = failing.run(None).await.err().unwrap().to_string();
+        assert!(error.contains("exit code: 3"), "{}", error);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[tokio::test]
+    async fn test_wasm_plugin_stops_on_timeout() {
+        let spinning = wasm_plugin(
+            "spinning",
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "_start") (loop (br 0))))"#,
+        );
+        let result = tokio::time::timeout(Duration::from_millis(200), spinning.run(None)).await;
+        assert!(result.is_err());
+    }
+}
@@ -10,7 +10,6 @@ use serde_json::Value;
 pub struct PromptRegistry(RwLock<HashMap<String, Arc<dyn PromptExecutor>>>);
 
 impl PromptRegistry {
-    #[allow(unused)]
     pub fn register(&self, prompt: Arc<dyn PromptExecutor>) {
         self.0.write().insert(prompt.name().to_string(), prompt);
     }
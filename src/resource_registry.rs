@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
@@ -6,6 +6,14 @@ use context_server::{Resource, ResourceContent, ResourceContentType, ResourceDel
 
 use parking_lot::RwLock;
 
+/// A source of resources whose listing and content are computed on demand
+pub trait ResourceProvider: Send + Sync {
+    fn list(&self) -> Vec<Resource>;
+
+    /// Read the content of `uri`, or `None` if this provider doesn't own it
+    fn read(&self, uri: &str) -> Option<String>;
+}
+
 #[derive(Default)]
 pub struct ResourceRegistry {
     inner: RwLock<Inner>,
@@ -15,6 +23,7 @@ pub struct ResourceRegistry {
 struct Inner {
     resources: HashMap<String, Resource>,
     contents: HashMap<String, String>,
+    providers: Vec<Arc<dyn ResourceProvider>>,
 }
 
 impl ResourceRegistry {
@@ -25,19 +34,36 @@ impl ResourceRegistry {
         guard.resources.insert(resource.uri.clone(), resource);
     }
 
+    pub fn register_provider(&self, provider: Arc<dyn ResourceProvider>) {
+        self.inner.write().providers.push(provider);
+    }
+
     pub fn list_resources(&self) -> Vec<Resource> {
         let guard = self.inner.read();
-        guard.resources.values().cloned().collect()
+        let mut resources: Vec<Resource> = guard.resources.values().cloned().collect();
+        for provider in &guard.providers {
+            resources.extend(provider.list());
+        }
+        resources
     }
 
     pub fn get_resource(&self, uri: &str) -> Option<Resource> {
         let guard = self.inner.read();
-        guard.resources.get(uri).cloned()
+        guard.resources.get(uri).cloned().or_else(|| {
+            guard
+                .providers
+                .iter()
+                .find_map(|p| p.list().into_iter().find(|r| r.uri == uri))
+        })
     }
 
     pub fn read_content(&self, uri: &str) -> Option<String> {
         let guard = self.inner.read();
-        guard.contents.get(uri).cloned()
+        guard
+            .contents
+            .get(uri)
+            .cloned()
+            .or_else(|| guard.providers.iter().find_map(|p| p.read(uri)))
     }
 }
 
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow, bail};
+use read_mcp_tools::FetchHistory;
+use serde_json::{Map, Value, json};
+
+use crate::{ContextServerState, Services};
+
+/// A single tool call given on the command line as
+/// `run <tool> [--<argument> <value>]... [--json]`
+pub struct RunCommand {
+    tool: Option<String>,
+    arguments: Map<String, Value>,
+    /// Print the whole tool result as JSON instead of its text
+    json: bool,
+}
+
+impl RunCommand {
+    /// The `run` subcommand in `args`, if that is what was asked for
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>> {
+        let mut args = args.into_iter().peekable();
+
+        // `--config` may come before the subcommand
+        loop {
+            match args.next().as_deref() {
+                Some("--config") => {
+                    args.next();
+                }
+                Some(arg) if arg.starts_with("--config=") => {}
+                Some("run") => break,
+                _ => return Ok(None),
+            }
+        }
+
+        let mut command = RunCommand {
+            tool: args.next_if(|arg| !arg.starts_with("--")),
+            arguments: Map::new(),
+            json: false,
+        };
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                bail!(
+                    "Unexpected argument {}; tool arguments are given as --<name> <value>",
+                    arg
+                );
+            };
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (
+                    option.to_string(),
+                    args.next_if(|value| !value.starts_with("--")),
+                ),
+            };
+
+            match (name.as_str(), value) {
+                ("json", None) => command.json = true,
+                ("config", _) => {}
+                (_, value) => {
+                    command
+                        .arguments
+                        .insert(name.replace('-', "_"), parse_value(value));
+                }
+            }
+        }
+
+        Ok(Some(command))
+    }
+}
+
+/// Values are read as JSON when they parse as such, so numbers, booleans and
+/// arrays work; anything else is a string. A bare flag is `true`.
+fn parse_value(value: Option<String>) -> Value {
+    match value {
+        Some(value) => serde_json::from_str(&value).unwrap_or(Value::String(value)),
+        None => Value::Bool(true),
+    }
+}
+
+/// Call the tool and print its result, without speaking MCP; the available
+/// tools are listed when none is named
+pub async fn run(services: Arc<Services>, command: RunCommand) -> Result<()> {
+    let session = ContextServerState::new(&services, "cli", Arc::new(FetchHistory::default()))?;
+
+    let Some(tool) = command.tool else {
+        let mut tools = session.tools.list();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        println!("Usage: read-mcp run <tool> [--<argument> <value>]... [--json]\n");
+        for tool in tools {
+            let summary = tool
+                .description
+                .as_deref()
+                .and_then(|description| description.lines().next())
+                .unwrap_or_default();
+            println!("  {:<24} {}", tool.name, summary);
+        }
+        return Ok(());
+    };
+
+    let result = session
+        .call_tool(Some(&json!({
+            "name": tool,
+            "arguments": command.arguments,
+        })))
+        .await;
+
+    if command.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else {
+        for block in result["content"].as_array().into_iter().flatten() {
+            match block["type"].as_str() {
+                Some("text") => println!("{}", block["text"].as_str().unwrap_or_default()),
+                Some("resource") => println!("{}", block["resource"]["uri"]),
+                _ => println!("{}", block),
+            }
+        }
+    }
+
+    if result["isError"] == true {
+        return Err(anyhow!("{} failed", tool));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Option<RunCommand>> {
+        RunCommand::parse(args.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn test_parse_run_command() {
+        let command = parse(
+            "--config read.toml run read_url --url https://example.com --max-length 500 --raw --json",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(command.tool.as_deref(), Some("read_url"));
+        assert!(command.json);
+        assert_eq!(
+            Value::Object(command.arguments),
+            json!({ "url": "https://example.com", "max_length": 500, "raw": true })
+        );
+
+        let command =
+            parse("run search --query=rust --domains [\"a.com\",\"b.com\"] --config=x.toml")
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            Value::Object(command.arguments),
+            json!({ "query": "rust", "domains": ["a.com", "b.com"] })
+        );
+
+        // Listing the tools
+        let command = parse("run --json").unwrap().unwrap();
+        assert!(command.tool.is_none());
+        assert!(command.json);
+    }
+
+    #[test]
+    fn test_parse_other_commands() {
+        assert!(parse("").unwrap().is_none());
+        assert!(parse("--config read.toml").unwrap().is_none());
+        assert!(parse("serve run").unwrap().is_none());
+        assert!(parse("run read_url https://example.com").is_err());
+    }
+}
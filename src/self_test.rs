@@ -0,0 +1,224 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::{Result, anyhow, bail};
+use axum::{Router, response::Html, routing::get};
+use read_mcp_tools::FetchHistory;
+use serde_json::{Value, json};
+
+use crate::{ContextServerState, Services};
+
+const FIXTURE_TITLE: &str = "Keeping a Sourdough Starter Alive";
+
+/// Served from a local port so the fetch goes over real HTTP; the checks rely on
+/// its title, the second heading, the emphasis and the link
+const FIXTURE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>Keeping a Sourdough Starter Alive</title>
+</head>
+<body>
+  <nav><a href="/">Home</a> <a href="/recipes">Recipes</a></nav>
+  <article>
+    <h1>Keeping a Sourdough Starter Alive</h1>
+    <p>A sourdough starter is a colony of wild yeast and bacteria living in flour and
+    water. It needs regular feeding, a steady temperature and a little patience, and in
+    return it leavens bread with a flavour no packet of dried yeast can match.</p>
+    <p>Most starters that die are not killed by neglect but by <strong>irregular
+    feeding</strong>: a starter fed at random times never settles into a rhythm, and its
+    rise becomes impossible to predict from one day to the next.</p>
+    <h2>Feeding schedule</h2>
+    <p>Feed the starter at the same time every day, discarding all but a spoonful before
+    adding equal weights of flour and water. At room temperature it will double within
+    six to eight hours; in the fridge it can wait a week between feeds.</p>
+    <p>The <a href="https://example.com/hydration">hydration guide</a> explains how to
+    adjust the ratio of flour to water for stiffer or looser starters.</p>
+  </article>
+  <footer>Copyright Example Bakery</footer>
+</body>
+</html>
+"#;
+
+/// Exercise fetching, extraction, markdown conversion and JSON-RPC framing against
+/// the bundled fixture, and `live_url` when given, printing a pass/fail report
+pub async fn run(services: Arc<Services>, live_url: Option<&str>) -> Result<()> {
+    let fixture_url = format!("http://{}/article", serve_fixture().await?);
+    let session =
+        ContextServerState::new(&services, "self-test", Arc::new(FetchHistory::default()))?;
+
+    let mut checks = vec![
+        ("fetch", check_fetch(&services, &fixture_url).await),
+        ("framing", check_framing(&session).await),
+    ];
+    match read_url(&session, &fixture_url).await {
+        Ok((markdown, structured)) => {
+            checks.push(("extraction", check_extraction(&markdown, &structured)));
+            checks.push(("markdown", check_markdown(&markdown)));
+        }
+        Err(e) => {
+            checks.push(("extraction", Err(e)));
+            checks.push(("markdown", Err(anyhow!("skipped, nothing was extracted"))));
+        }
+    }
+    if let Some(live_url) = live_url {
+        checks.push(("live", check_live(&session, live_url).await));
+    }
+
+    let mut failures = 0;
+    for (name, result) in &checks {
+        match result {
+            Ok(detail) => println!("PASS {:<10} {}", name, detail),
+            Err(e) => {
+                failures += 1;
+                println!("FAIL {:<10} {:#}", name, e);
+            }
+        }
+    }
+
+    if failures > 0 {
+        bail!("{} of {} self-test checks failed", failures, checks.len());
+    }
+    println!("All {} checks passed", checks.len());
+    Ok(())
+}
+
+async fn serve_fixture() -> Result<SocketAddr> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+    let app = Router::new().route("/article", get(|| async { Html(FIXTURE) }));
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    Ok(address)
+}
+
+async fn check_fetch(services: &Services, url: &str) -> Result<String> {
+    let page = services.fetcher.fetch(url).await?;
+    if page.status != 200 {
+        bail!("{} answered with HTTP {}", url, page.status);
+    }
+    if page.body != FIXTURE {
+        bail!(
+            "received {} bytes, expected the {} of the fixture",
+            page.body.len(),
+            FIXTURE.len()
+        );
+    }
+    Ok(format!("{} bytes over HTTP", page.body.len()))
+}
+
+/// Messages survive a round trip through the one-line-per-message stdio encoding
+async fn check_framing(session: &ContextServerState) -> Result<String> {
+    let initialize = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": { "name": "self-test", "version": env!("CARGO_PKG_VERSION") },
+        },
+    });
+    let response = round_trip(session, initialize).await?;
+    if response["id"] != 1 || response["result"]["serverInfo"].is_null() {
+        bail!("unexpected initialize response {}", response);
+    }
+
+    let batch = json!([
+        { "jsonrpc": "2.0", "id": 2, "method": "ping" },
+        { "jsonrpc": "2.0", "method": "notifications/initialized" },
+        { "jsonrpc": "2.0", "id": 3, "method": "ping" },
+    ]);
+    let responses = round_trip(session, batch).await?;
+    let ids = responses.as_array().map(|responses| {
+        responses
+            .iter()
+            .map(|r| r["id"].clone())
+            .collect::<Vec<_>>()
+    });
+    if ids != Some(vec![json!(2), json!(3)]) {
+        bail!("unexpected batch response {}", responses);
+    }
+
+    Ok("initialize and a batch round-trip as single JSON lines".to_string())
+}
+
+async fn round_trip(session: &ContextServerState, message: Value) -> Result<Value> {
+    let line = serde_json::to_string(&message)?;
+    let response = session
+        .process_payload(serde_json::from_str(&line)?)
+        .await
+        .ok_or_else(|| anyhow!("no response to {}", line))?;
+
+    let line = serde_json::to_string(&response)?;
+    if line.contains('\n') {
+        bail!("response spans several lines");
+    }
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// The markdown and structured content read_url returns for `url`
+async fn read_url(session: &ContextServerState, url: &str) -> Result<(String, Value)> {
+    let result = session
+        .call_tool(Some(
+            &json!({ "name": "read_url", "arguments": { "url": url } }),
+        ))
+        .await;
+    let text = result["content"][0]["text"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    if result["isError"] == true {
+        bail!("read_url failed: {}", text);
+    }
+    Ok((text, result["structuredContent"].clone()))
+}
+
+fn check_extraction(markdown: &str, structured: &Value) -> Result<String> {
+    if structured["title"] != FIXTURE_TITLE {
+        bail!("extracted the title {}", structured["title"]);
+    }
+    if !markdown.contains("leavens bread") || !markdown.contains("six to eight hours") {
+        bail!("the article's paragraphs are missing");
+    }
+    if markdown.contains("Copyright Example Bakery") {
+        bail!("the page footer was kept");
+    }
+    Ok(format!(
+        "\"{}\", {} characters",
+        FIXTURE_TITLE,
+        markdown.len()
+    ))
+}
+
+fn check_markdown(markdown: &str) -> Result<String> {
+    let expected = [
+        ("emphasis", "**irregular"),
+        ("link", "](https://example.com/hydration)"),
+    ];
+    let missing = expected
+        .iter()
+        .filter(|(_, marker)| !markdown.contains(marker))
+        .map(|(element, _)| *element)
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        bail!("no {} in the markdown", missing.join(", "));
+    }
+    if !markdown
+        .lines()
+        .any(|line| line.starts_with('#') && line.contains("Feeding schedule"))
+    {
+        bail!("\"Feeding schedule\" is not a heading");
+    }
+    Ok("headings, emphasis and links converted".to_string())
+}
+
+async fn check_live(session: &ContextServerState, url: &str) -> Result<String> {
+    let (markdown, structured) = read_url(session, url).await?;
+    if markdown.trim().is_empty() {
+        bail!("{} yielded no content", url);
+    }
+    Ok(format!(
+        "{} ({} characters)",
+        structured["title"].as_str().unwrap_or(url),
+        markdown.len()
+    ))
+}
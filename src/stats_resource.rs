@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use context_server::Resource;
+use read_mcp_tools::ServerStats;
+
+use crate::resource_registry::ResourceProvider;
+
+const STATS_URI: &str = "read://stats";
+
+/// Exposes the server's activity counters as `read://stats`
+pub struct StatsResource {
+    stats: Arc<ServerStats>,
+    domains: bool,
+}
+
+impl StatsResource {
+    pub fn new(stats: Arc<ServerStats>) -> Self {
+        StatsResource {
+            stats,
+            domains: true,
+        }
+    }
+
+    /// Leave out the calls made per domain, for servers whose clients shouldn't
+    /// learn which sites the others read
+    pub fn without_domains(mut self) -> Self {
+        self.domains = false;
+        self
+    }
+}
+
+impl ResourceProvider for StatsResource {
+    fn list(&self) -> Vec<Resource> {
+        vec![Resource {
+            uri: STATS_URI.to_string(),
+            name: "Server statistics".to_string(),
+            description: Some(if self.domains {
+                "Tool calls with their error rates and latencies, failures by domain, fetch latency, \
+                 bytes downloaded and response statuses"
+                    .to_string()
+            } else {
+                "Tool calls with their error rates and latencies, fetch latency, bytes downloaded \
+                 and response statuses"
+                    .to_string()
+            }),
+            mime_type: Some("application/json".to_string()),
+        }]
+    }
+
+    fn read(&self, uri: &str) -> Option<String> {
+        if uri != STATS_URI {
+            return None;
+        }
+
+        let json = if self.domains {
+            self.stats.to_json()
+        } else {
+            self.stats.to_shared_json()
+        };
+        serde_json::to_string_pretty(&json).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn test_shared_stats_hide_domains() {
+        let stats = Arc::new(ServerStats::default());
+        stats.record_tool_call(
+            "read_url",
+            Some("https://private.example/inbox"),
+            true,
+            Duration::from_millis(5),
+        );
+
+        let read = |resource: StatsResource| -> Value {
+            serde_json::from_str(&resource.read(STATS_URI).unwrap()).unwrap()
+        };
+        let own = read(StatsResource::new(stats.clone()));
+        assert_eq!(own["domains"]["private.example"]["calls"], 1);
+
+        let shared = read(StatsResource::new(stats).without_domains());
+        assert!(shared.get("domains").is_none());
+        assert_eq!(shared["tools"]["read_url"]["calls"], 1);
+    }
+}
@@ -0,0 +1,181 @@
+use std::{io::ErrorKind, process::ExitCode, sync::Arc, time::Duration};
+
+use serde_json::Value;
+use tokio::{
+    io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader, Stdout},
+    task::{JoinError, JoinSet},
+};
+
+use crate::{ContextServerState, PARSE_ERROR, Services, rpc_error};
+
+/// How long requests still running when stdin closes get to finish
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// `EX_IOERR` from sysexits.h
+const EXIT_IO_ERROR: u8 = 74;
+
+/// Why serving over stdio stopped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shutdown {
+    /// The client closed stdin
+    EndOfInput,
+    /// SIGINT, or SIGTERM on Unix
+    Signal,
+    /// stdout or stdin failed, usually because the client exited without
+    /// closing stdin first
+    ClientGone,
+}
+
+impl Shutdown {
+    /// Success unless the client vanished, which exits with `EX_IOERR`
+    pub fn exit_code(self) -> ExitCode {
+        match self {
+            Shutdown::EndOfInput | Shutdown::Signal => ExitCode::SUCCESS,
+            Shutdown::ClientGone => ExitCode::from(EXIT_IO_ERROR),
+        }
+    }
+}
+
+/// Serve MCP over stdin and stdout, one JSON message per line, until the client
+/// closes stdin, goes away or the process is told to stop.
+///
+/// Messages are handled concurrently, so the client's answer to a request made
+/// during a tool call can be read while the call waits for it. When stdin is
+/// closed, requests still running are given a while to finish and be answered;
+/// in every other case they are cancelled.
+pub async fn serve(services: &Services, state: Arc<ContextServerState>) -> Shutdown {
+    let mut stdin = BufReader::new(io::stdin()).lines();
+    let mut stdout = io::stdout();
+    let mut config_changes = services.config.subscribe();
+    let mut outgoing = state.client.subscribe();
+    let mut requests = JoinSet::new();
+    let stop_signal = stop_signal();
+    tokio::pin!(stop_signal);
+
+    let mut shutdown = 'serve: loop {
+        let line = tokio::select! {
+            line = stdin.next_line() => line,
+            Some(handled) = requests.join_next() => {
+                if write_response(&mut stdout, handled).await.is_err() {
+                    break Shutdown::ClientGone;
+                }
+                continue;
+            }
+            Ok(message) = outgoing.recv() => {
+                if write_message(&mut stdout, &message).await.is_err() {
+                    break Shutdown::ClientGone;
+                }
+                continue;
+            }
+            Ok(()) = config_changes.changed() => {
+                let config = config_changes.borrow_and_update().clone();
+                for notification in state.apply_config(config) {
+                    if write_message(&mut stdout, &notification).await.is_err() {
+                        break 'serve Shutdown::ClientGone;
+                    }
+                }
+                continue;
+            }
+            () = &mut stop_signal => break Shutdown::Signal,
+        };
+
+        let line = match line {
+            Ok(Some(line)) => line,
+            Ok(None) => break Shutdown::EndOfInput,
+            // The offending line has been consumed, so reading can go on
+            Err(e) if e.kind() == ErrorKind::InvalidData => {
+                let error = rpc_error(Value::Null, PARSE_ERROR, format!("Parse error: {}", e));
+                if write_message(&mut stdout, &error).await.is_err() {
+                    break Shutdown::ClientGone;
+                }
+                continue;
+            }
+            Err(e) => {
+                eprintln!("Error reading stdin: {}", e);
+                break Shutdown::ClientGone;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let message: Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = rpc_error(Value::Null, PARSE_ERROR, format!("Parse error: {}", e));
+                if write_message(&mut stdout, &error).await.is_err() {
+                    break Shutdown::ClientGone;
+                }
+                continue;
+            }
+        };
+
+        let state = state.clone();
+        requests.spawn(async move { state.process_payload(message).await });
+    };
+
+    if shutdown == Shutdown::EndOfInput {
+        let drained = tokio::time::timeout(DRAIN_TIMEOUT, async {
+            while let Some(handled) = requests.join_next().await {
+                write_response(&mut stdout, handled).await?;
+            }
+            Ok::<_, io::Error>(())
+        })
+        .await;
+        if let Ok(Err(_)) = drained {
+            shutdown = Shutdown::ClientGone;
+        }
+    }
+
+    if !requests.is_empty() {
+        eprintln!("Cancelling {} requests still in progress", requests.len());
+    }
+    requests.shutdown().await;
+    if shutdown == Shutdown::ClientGone {
+        eprintln!("The client went away; shutting down");
+    }
+    shutdown
+}
+
+async fn write_response(
+    stdout: &mut Stdout,
+    handled: Result<Option<Value>, JoinError>,
+) -> io::Result<()> {
+    match handled {
+        Ok(Some(response)) => write_message(stdout, &response).await,
+        Ok(None) => Ok(()),
+        Err(e) => {
+            eprintln!("Error handling request: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Write `message` as a single line; failing means the client can't be reached
+async fn write_message(stdout: &mut Stdout, message: &Value) -> io::Result<()> {
+    let mut line = message.to_string();
+    line.push('\n');
+    stdout.write_all(line.as_bytes()).await?;
+    stdout.flush().await
+}
+
+/// Resolves when the process is asked to stop
+async fn stop_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                Ok(()) = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+            return;
+        }
+    }
+
+    if tokio::signal::ctrl_c().await.is_err() {
+        // Without a handler there is nothing to wait for
+        std::future::pending::<()>().await;
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+
+use crate::config::TelemetryConfig;
+
+/// Exports the spans recorded while it is alive, flushing what is still
+/// buffered when dropped
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+/// Start exporting spans as configured; nothing is exported without a config
+#[cfg(feature = "otel")]
+pub fn init(config: Option<&TelemetryConfig>) -> Result<Telemetry> {
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let Some(config) = config else {
+        return Ok(Telemetry { provider: None });
+    };
+
+    let mut exporter = SpanExporter::builder().with_http();
+    if let Some(endpoint) = &config.endpoint {
+        exporter = exporter.with_endpoint(endpoint);
+    }
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter.build()?)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build();
+
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    tracing::subscriber::set_global_default(
+        tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer)),
+    )?;
+
+    Ok(Telemetry {
+        provider: Some(provider),
+    })
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn init(config: Option<&TelemetryConfig>) -> Result<Telemetry> {
+    if config.is_some() {
+        eprintln!("Warning: [telemetry] is ignored; this build lacks the otel feature");
+    }
+    Ok(Telemetry {})
+}
+
+#[cfg(feature = "otel")]
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(provider) = &self.provider
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Error flushing traces: {}", e);
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use context_server::{
+    ComputedPrompt, PromptArgument, PromptContent, PromptExecutor, PromptMessage,
+    ResourceContentType, Role, ToolContent,
+};
+use serde_json::{Map, Value};
+
+use crate::{config::PromptConfig, tool_registry::ToolRegistry};
+
+/// A prompt defined in the config file, whose template is filled in with the
+/// prompt's arguments and the output of the tool calls it makes
+pub struct TemplatePrompt {
+    config: PromptConfig,
+    segments: Vec<Segment>,
+    tools: Arc<ToolRegistry>,
+}
+
+/// A piece of a parsed template
+#[derive(Debug)]
+enum Segment {
+    Text(String),
+    /// `{{argument}}`
+    Argument(String),
+    /// `{{tool parameter parameter=argument parameter="literal"}}`
+    ToolCall {
+        tool: String,
+        parameters: Vec<(String, ParameterValue)>,
+    },
+}
+
+#[derive(Debug)]
+enum ParameterValue {
+    Argument(String),
+    Literal(Value),
+}
+
+impl TemplatePrompt {
+    pub fn new(config: PromptConfig, tools: Arc<ToolRegistry>) -> Result<Self> {
+        let segments = parse_template(&config)
+            .with_context(|| format!("Invalid template for prompt {}", config.name))?;
+        Ok(TemplatePrompt {
+            config,
+            segments,
+            tools,
+        })
+    }
+
+    async fn render(&self, arguments: &Map<String, Value>) -> Result<String> {
+        let argument = |name: &str| -> Option<String> {
+            match arguments.get(name)? {
+                Value::String(value) => Some(value.clone()),
+                Value::Null => None,
+                value => Some(value.to_string()),
+            }
+        };
+
+        let mut rendered = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Argument(name) => {
+                    rendered.push_str(&argument(name).unwrap_or_default());
+                }
+                Segment::ToolCall { tool, parameters } => {
+                    let mut tool_arguments = Map::new();
+                    for (parameter, value) in parameters {
+                        let value = match value {
+                            // Optional arguments left out are not passed on
+                            ParameterValue::Argument(name) => match arguments.get(name) {
+                                Some(value) if !value.is_null() => value.clone(),
+                                _ => continue,
+                            },
+                            ParameterValue::Literal(value) => value.clone(),
+                        };
+                        tool_arguments.insert(parameter.clone(), value);
+                    }
+
+                    let output = self
+                        .tools
+                        .execute(tool, Some(Value::Object(tool_arguments)))
+                        .await
+                        .with_context(|| format!("{} failed", tool))?;
+                    rendered.push_str(&content_text(&output.content));
+                }
+            }
+        }
+        Ok(rendered)
+    }
+}
+
+#[async_trait]
+impl PromptExecutor for TemplatePrompt {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn description(&self) -> &str {
+        self.config
+            .description
+            .as_deref()
+            .unwrap_or(&self.config.name)
+    }
+
+    fn arguments(&self) -> Vec<PromptArgument> {
+        self.config
+            .arguments
+            .iter()
+            .map(|argument| PromptArgument {
+                name: argument.name.clone(),
+                description: argument.description.clone(),
+                required: Some(argument.required),
+            })
+            .collect()
+    }
+
+    async fn compute(&self, arguments: Option<Value>) -> Result<ComputedPrompt> {
+        let arguments = match arguments {
+            Some(Value::Object(arguments)) => arguments,
+            None | Some(Value::Null) => Map::new(),
+            Some(_) => bail!("Prompt arguments must be an object"),
+        };
+        if let Some(missing) = self
+            .config
+            .arguments
+            .iter()
+            .find(|argument| argument.required && !arguments.contains_key(&argument.name))
+        {
+            bail!("Missing required argument: {}", missing.name);
+        }
+
+        Ok(ComputedPrompt {
+            description: self.description().to_string(),
+            messages: vec![PromptMessage {
+                role: Role::User,
+                content: PromptContent::Text {
+                    text: self.render(&arguments).await?,
+                },
+            }],
+        })
+    }
+}
+
+/// Split the template into text and `{{...}}` placeholders, checking that the
+/// placeholders only refer to declared arguments
+fn parse_template(config: &PromptConfig) -> Result<Vec<Segment>> {
+    let is_argument = |name: &str| config.arguments.iter().any(|a| a.name == name);
+
+    let mut segments = Vec::new();
+    let mut rest = config.template.as_str();
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            segments.push(Segment::Text(rest[..start].to_string()));
+        }
+        let end = rest[start..]
+            .find("}}")
+            .map(|end| start + end)
+            .ok_or_else(|| anyhow!("unclosed {{{{ in {}", &rest[start..]))?;
+        let placeholder = &rest[start + 2..end];
+        rest = &rest[end + 2..];
+
+        let mut tokens = tokenize(placeholder)?.into_iter();
+        let Some(name) = tokens.next() else {
+            bail!("empty {{{{}}}} placeholder");
+        };
+        let parameters = tokens.collect::<Vec<_>>();
+
+        if parameters.is_empty() && is_argument(&name) {
+            segments.push(Segment::Argument(name));
+            continue;
+        }
+
+        let parameters = parameters
+            .into_iter()
+            .map(|parameter| {
+                let (parameter, value) = match parameter.split_once('=') {
+                    Some((parameter, value)) => (parameter.to_string(), value.to_string()),
+                    None => (parameter.clone(), parameter),
+                };
+                let value = if is_argument(&value) {
+                    ParameterValue::Argument(value)
+                } else if let Ok(literal) = serde_json::from_str(&value) {
+                    ParameterValue::Literal(literal)
+                } else {
+                    bail!(
+                        "{} in {{{{{}}}}} is not an argument of the prompt",
+                        value,
+                        name
+                    );
+                };
+                Ok((parameter, value))
+            })
+            .collect::<Result<_>>()?;
+        segments.push(Segment::ToolCall {
+            tool: name,
+            parameters,
+        });
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest.to_string()));
+    }
+
+    Ok(segments)
+}
+
+/// Split on whitespace, except inside double-quoted strings
+fn tokenize(placeholder: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for c in placeholder.chars() {
+        match c {
+            _ if escaped => {
+                token.push(c);
+                escaped = false;
+            }
+            '\\' if quoted => {
+                token.push(c);
+                escaped = true;
+            }
+            '"' => {
+                token.push(c);
+                quoted = !quoted;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if quoted {
+        bail!("unterminated string in {{{{{}}}}}", placeholder.trim());
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// The text of a tool result, as it is spliced into the prompt
+fn content_text(content: &[ToolContent]) -> String {
+    content
+        .iter()
+        .filter_map(|block| match block {
+            ToolContent::Text { text } => Some(text.as_str()),
+            ToolContent::Resource { resource } => match &resource.content {
+                ResourceContentType::Text { text } => Some(text.as_str()),
+                ResourceContentType::Blob { .. } => None,
+            },
+            ToolContent::Image { .. } => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
@@ -0,0 +1,53 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use read_mcp_tools::{ServerStats, ToolOutput};
+use serde_json::Value;
+
+/// Hooks run around every tool call made through the `ToolRegistry`.
+///
+/// `before` hooks run in registration order and `after` hooks in reverse, so
+/// the first middleware registered is the outermost layer.
+#[async_trait]
+pub trait ToolMiddleware: Send + Sync {
+    /// Inspect or rewrite the arguments; returning an error rejects the call
+    async fn before(&self, _tool: &str, arguments: Option<Value>) -> Result<Option<Value>> {
+        Ok(arguments)
+    }
+
+    /// Inspect or rewrite the outcome of the call, which took `elapsed`
+    async fn after(
+        &self,
+        _tool: &str,
+        _arguments: Option<&Value>,
+        _elapsed: Duration,
+        result: Result<ToolOutput>,
+    ) -> Result<ToolOutput> {
+        result
+    }
+}
+
+/// Counts every tool call, its outcome and latency in the server statistics
+pub struct StatsMiddleware(Arc<ServerStats>);
+
+impl StatsMiddleware {
+    pub fn new(stats: Arc<ServerStats>) -> Self {
+        StatsMiddleware(stats)
+    }
+}
+
+#[async_trait]
+impl ToolMiddleware for StatsMiddleware {
+    async fn after(
+        &self,
+        tool: &str,
+        arguments: Option<&Value>,
+        elapsed: Duration,
+        result: Result<ToolOutput>,
+    ) -> Result<ToolOutput> {
+        let url = arguments.and_then(|arguments| arguments.get("url")?.as_str());
+        self.0.record_tool_call(tool, url, result.is_ok(), elapsed);
+        result
+    }
+}
@@ -1,32 +1,343 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use context_server::{Tool, ToolContent, ToolDelegate, ToolExecutor};
 use parking_lot::RwLock;
+use read_mcp_tools::{StructuredToolExecutor, ToolAnnotations, ToolOutput};
 use serde_json::Value;
+use tokio::sync::Semaphore;
+use tracing::Span;
+
+use crate::{argument_validation::validate_arguments, tool_middleware::ToolMiddleware};
+
+/// Bounds on how long and how many times at once a tool may run
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExecutionLimits {
+    pub timeout: Option<Duration>,
+    pub max_concurrent: Option<usize>,
+}
+
+/// Returned when a tool call is rejected or aborted by its execution limits
+#[derive(Debug)]
+pub enum LimitExceeded {
+    Timeout { tool: String, after: Duration },
+    Concurrency { tool: String, limit: usize },
+    GlobalConcurrency { limit: usize },
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::Timeout { tool, after } => {
+                write!(f, "Tool {} timed out after {}s", tool, after.as_secs_f32())
+            }
+            LimitExceeded::Concurrency { tool, limit } => write!(
+                f,
+                "Tool {} is already running {} times, the maximum allowed; retry later",
+                tool, limit
+            ),
+            LimitExceeded::GlobalConcurrency { limit } => write!(
+                f,
+                "The server is already running {} tool calls, the maximum allowed; retry later",
+                limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// A tool as held by the registry, remembering whether it returns structured content
+#[derive(Clone)]
+pub struct RegisteredTool {
+    executor: Arc<dyn ToolExecutor>,
+    structured: Option<Arc<dyn StructuredToolExecutor>>,
+    annotations: Option<ToolAnnotations>,
+    /// Name exposed to clients in place of the tool's own
+    exposed_name: Option<String>,
+}
+
+impl RegisteredTool {
+    pub fn new(executor: Arc<dyn ToolExecutor>) -> Self {
+        RegisteredTool {
+            executor,
+            structured: None,
+            annotations: None,
+            exposed_name: None,
+        }
+    }
+
+    pub fn structured<T: StructuredToolExecutor + 'static>(tool: Arc<T>) -> Self {
+        RegisteredTool {
+            executor: tool.clone(),
+            structured: Some(tool),
+            annotations: None,
+            exposed_name: None,
+        }
+    }
+
+    /// Advertise behaviour hints for the tool in `tools/list`
+    pub fn with_annotations(mut self, annotations: ToolAnnotations) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    /// Expose the tool to clients as `name`
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.exposed_name = Some(name.into());
+        self
+    }
+
+    /// The name clients call the tool by
+    pub fn name(&self) -> String {
+        self.exposed_name.clone().unwrap_or_else(|| self.own_name())
+    }
+
+    /// The tool's own name, which limits, statistics and the configuration use
+    pub fn own_name(&self) -> String {
+        self.executor.to_tool().name
+    }
+
+    fn to_tool(&self) -> Tool {
+        let mut tool = self.executor.to_tool();
+        if let Some(name) = &self.exposed_name {
+            tool.name = name.clone();
+        }
+        tool
+    }
+
+    async fn execute(&self, arguments: Option<Value>) -> Result<ToolOutput> {
+        match &self.structured {
+            Some(tool) => tool.execute_structured(arguments).await,
+            None => Ok(self.executor.execute(arguments).await?.into()),
+        }
+    }
+}
+
+/// Execution limits and the permits enforcing them, shared by the registries of
+/// every session so `max_concurrent` bounds the whole server
+#[derive(Default)]
+pub struct ToolLimits(RwLock<Limits>);
 
 #[derive(Default)]
-pub struct ToolRegistry(RwLock<HashMap<String, Arc<dyn ToolExecutor>>>);
+struct Limits {
+    default_limits: ExecutionLimits,
+    tool_limits: HashMap<String, ExecutionLimits>,
+    global_permits: Option<(Arc<Semaphore>, usize)>,
+    tool_permits: HashMap<String, (Arc<Semaphore>, usize)>,
+}
+
+impl ToolLimits {
+    /// Apply `global` limits across all calls, with `max_concurrent` shared by every
+    /// tool and `timeout` used for tools without an entry in `per_tool`
+    pub fn new(global: ExecutionLimits, per_tool: HashMap<String, ExecutionLimits>) -> Self {
+        let limits = ToolLimits::default();
+        limits.set(global, per_tool);
+        limits
+    }
+
+    /// Replace the limits for calls made from now on; calls already running keep
+    /// the permits they hold
+    pub fn set(&self, global: ExecutionLimits, per_tool: HashMap<String, ExecutionLimits>) {
+        let global_permits = global
+            .max_concurrent
+            .map(|limit| (Arc::new(Semaphore::new(limit)), limit));
+        let tool_permits = per_tool
+            .iter()
+            .filter_map(|(tool, limits)| {
+                limits
+                    .max_concurrent
+                    .map(|limit| (tool.clone(), (Arc::new(Semaphore::new(limit)), limit)))
+            })
+            .collect();
+        *self.0.write() = Limits {
+            default_limits: global,
+            tool_limits: per_tool,
+            global_permits,
+            tool_permits,
+        };
+    }
+}
+
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: RwLock<HashMap<String, RegisteredTool>>,
+    middleware: RwLock<Vec<Arc<dyn ToolMiddleware>>>,
+    limits: Arc<ToolLimits>,
+}
 
 impl ToolRegistry {
-    pub fn register(&self, tool: Arc<dyn ToolExecutor>) {
-        self.0.write().insert(tool.to_tool().name.clone(), tool);
+    /// Run calls within `limits`, which other registries may share
+    pub fn with_limits(mut self, limits: Arc<ToolLimits>) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    #[allow(unused)]
+    pub fn register(&self, tool: RegisteredTool) {
+        self.tools.write().insert(tool.name(), tool);
+    }
+
+    /// Replace the registered tools with `tools`, returning whether their names changed
+    pub fn set_tools(&self, tools: Vec<RegisteredTool>) -> bool {
+        let tools = tools
+            .into_iter()
+            .map(|tool| (tool.name(), tool))
+            .collect::<HashMap<_, _>>();
+        let mut registered = self.tools.write();
+        let changed = registered.len() != tools.len()
+            || registered.keys().any(|name| !tools.contains_key(name));
+        *registered = tools;
+        changed
+    }
+
+    /// Wrap every subsequent tool call in `middleware`
+    pub fn add_middleware(&self, middleware: Arc<dyn ToolMiddleware>) {
+        self.middleware.write().push(middleware);
     }
 
     pub fn list(&self) -> Vec<Tool> {
-        self.0.read().values().map(|t| t.to_tool()).collect()
+        self.tools
+            .read()
+            .values()
+            .map(RegisteredTool::to_tool)
+            .collect()
     }
 
-    pub async fn execute(&self, tool: &str, arguments: Option<Value>) -> Result<Vec<ToolContent>> {
-        let tool = self
-            .0
+    pub fn input_schema(&self, tool: &str) -> Option<Value> {
+        Some(self.tools.read().get(tool)?.executor.to_tool().input_schema)
+    }
+
+    /// The schema of `tool`'s structured content, if it returns any
+    pub fn output_schema(&self, tool: &str) -> Option<Value> {
+        let tools = self.tools.read();
+        tools
+            .get(tool)?
+            .structured
+            .as_ref()
+            .map(|t| t.output_schema())
+    }
+
+    pub fn annotations(&self, tool: &str) -> Option<ToolAnnotations> {
+        self.tools.read().get(tool)?.annotations
+    }
+
+    #[tracing::instrument(
+        name = "tool.call",
+        skip(self, arguments),
+        fields(otel.status_code = tracing::field::Empty)
+    )]
+    pub async fn execute(&self, tool: &str, mut arguments: Option<Value>) -> Result<ToolOutput> {
+        let executor = self
+            .tools
             .read()
             .get(tool)
             .ok_or_else(|| anyhow!("Tool not found: {}", tool))?
             .clone();
+        // Whatever name it was called by, the tool is limited and observed as itself
+        let own_name = executor.own_name();
+        let tool = own_name.as_str();
+
+        // Malformed calls are turned away before middleware, statistics or limits
+        // see them, so they can't use up permits or skew the success rate; the
+        // arguments checked are the client's, before any layer rewrites them
+        let input_schema = executor.executor.to_tool().input_schema;
+        if let Err(e) = validate_arguments(tool, &input_schema, arguments.as_ref()) {
+            Span::current().record("otel.status_code", "ERROR");
+            return Err(e.into());
+        }
+
+        let middleware = self.middleware.read().clone();
+        let started_at = Instant::now();
+
+        for (i, layer) in middleware.iter().enumerate() {
+            match layer.before(tool, arguments).await {
+                Ok(rewritten) => arguments = rewritten,
+                // Layers that already saw the call still see its outcome
+                Err(e) => {
+                    return Self::unwind(&middleware[..i], tool, None, started_at, Err(e)).await;
+                }
+            }
+        }
+
+        let result = self
+            .execute_within_limits(tool, executor, arguments.clone())
+            .await;
+
+        Self::unwind(&middleware, tool, arguments.as_ref(), started_at, result).await
+    }
 
-        tool.execute(arguments).await
+    async fn unwind(
+        middleware: &[Arc<dyn ToolMiddleware>],
+        tool: &str,
+        arguments: Option<&Value>,
+        started_at: Instant,
+        mut result: Result<ToolOutput>,
+    ) -> Result<ToolOutput> {
+        let elapsed = started_at.elapsed();
+        for layer in middleware.iter().rev() {
+            result = layer.after(tool, arguments, elapsed, result).await;
+        }
+        if result.is_err() {
+            Span::current().record("otel.status_code", "ERROR");
+        }
+        result
+    }
+
+    async fn execute_within_limits(
+        &self,
+        tool: &str,
+        executor: RegisteredTool,
+        arguments: Option<Value>,
+    ) -> Result<ToolOutput> {
+        // Limits are read up front so a reload can't block on a running call
+        let (_global_permit, _tool_permit, timeout) = {
+            let limits = self.limits.0.read();
+            let global_permit = match &limits.global_permits {
+                Some((semaphore, limit)) => Some(
+                    semaphore
+                        .clone()
+                        .try_acquire_owned()
+                        .map_err(|_| LimitExceeded::GlobalConcurrency { limit: *limit })?,
+                ),
+                None => None,
+            };
+
+            let tool_permit = match limits.tool_permits.get(tool) {
+                Some((semaphore, limit)) => {
+                    Some(semaphore.clone().try_acquire_owned().map_err(|_| {
+                        LimitExceeded::Concurrency {
+                            tool: tool.to_string(),
+                            limit: *limit,
+                        }
+                    })?)
+                }
+                None => None,
+            };
+
+            let timeout = limits
+                .tool_limits
+                .get(tool)
+                .and_then(|limits| limits.timeout)
+                .or(limits.default_limits.timeout);
+            (global_permit, tool_permit, timeout)
+        };
+
+        match timeout {
+            Some(after) => tokio::time::timeout(after, executor.execute(arguments))
+                .await
+                .map_err(|_| LimitExceeded::Timeout {
+                    tool: tool.to_string(),
+                    after,
+                })?,
+            None => executor.execute(arguments).await,
+        }
     }
 }
 
@@ -37,6 +348,155 @@ impl ToolDelegate for ToolRegistry {
     }
 
     async fn execute(&self, tool: &str, arguments: Option<Value>) -> Result<Vec<ToolContent>> {
-        self.execute(tool, arguments).await
+        Ok(self.execute(tool, arguments).await?.content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::argument_validation::InvalidArguments;
+
+    struct Echo;
+
+    #[async_trait]
+    impl ToolExecutor for Echo {
+        async fn execute(&self, arguments: Option<Value>) -> Result<Vec<ToolContent>> {
+            Ok(vec![ToolContent::Text {
+                text: arguments.unwrap_or_default().to_string(),
+            }])
+        }
+
+        fn to_tool(&self) -> Tool {
+            Tool {
+                name: "echo".into(),
+                description: None,
+                input_schema: json!({
+                    "type": "object",
+                    "properties": { "text": { "type": "string" } },
+                    "required": ["text"]
+                }),
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct Counter {
+        before: AtomicUsize,
+        after: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ToolMiddleware for Counter {
+        async fn before(&self, _tool: &str, arguments: Option<Value>) -> Result<Option<Value>> {
+            self.before.fetch_add(1, Ordering::SeqCst);
+            Ok(arguments)
+        }
+
+        async fn after(
+            &self,
+            _tool: &str,
+            _arguments: Option<&Value>,
+            _elapsed: Duration,
+            result: Result<ToolOutput>,
+        ) -> Result<ToolOutput> {
+            self.after.fetch_add(1, Ordering::SeqCst);
+            result
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments_skip_middleware_and_limits() {
+        let registry = ToolRegistry::default().with_limits(Arc::new(ToolLimits::new(
+            ExecutionLimits {
+                timeout: None,
+                max_concurrent: Some(1),
+            },
+            HashMap::new(),
+        )));
+        registry.register(RegisteredTool::new(Arc::new(Echo)));
+        let counter = Arc::new(Counter::default());
+        registry.add_middleware(counter.clone());
+
+        let Err(error) = registry.execute("echo", Some(json!({ "text": 1 }))).await else {
+            panic!("a number passed for a string argument");
+        };
+        assert!(error.downcast_ref::<InvalidArguments>().is_some());
+        assert_eq!(counter.before.load(Ordering::SeqCst), 0);
+        assert_eq!(counter.after.load(Ordering::SeqCst), 0);
+
+        registry
+            .execute("echo", Some(json!({ "text": "hi" })))
+            .await
+            .unwrap();
+        assert_eq!(counter.before.load(Ordering::SeqCst), 1);
+        assert_eq!(counter.after.load(Ordering::SeqCst), 1);
+    }
+
+    /// Waits to be released before answering
+    struct Gate(tokio::sync::Semaphore);
+
+    #[async_trait]
+    impl ToolExecutor for Gate {
+        async fn execute(&self, _arguments: Option<Value>) -> Result<Vec<ToolContent>> {
+            let _open = self.0.acquire().await?;
+            Ok(Vec::new())
+        }
+
+        fn to_tool(&self) -> Tool {
+            Tool {
+                name: "gate".into(),
+                description: None,
+                input_schema: json!({ "type": "object" }),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registries_share_concurrency_limits() {
+        let limits = Arc::new(ToolLimits::new(
+            ExecutionLimits {
+                timeout: None,
+                max_concurrent: Some(1),
+            },
+            HashMap::new(),
+        ));
+        let gate = Arc::new(Gate(tokio::sync::Semaphore::new(0)));
+        let first = Arc::new(ToolRegistry::default().with_limits(limits.clone()));
+        let second = ToolRegistry::default().with_limits(limits.clone());
+        first.register(RegisteredTool::new(gate.clone()));
+        second.register(RegisteredTool::new(gate.clone()));
+
+        let running = tokio::spawn({
+            let first = first.clone();
+            async move { first.execute("gate", None).await.is_ok() }
+        });
+        while limits
+            .0
+            .read()
+            .global_permits
+            .as_ref()
+            .unwrap()
+            .0
+            .available_permits()
+            > 0
+        {
+            tokio::task::yield_now().await;
+        }
+
+        let Err(error) = second.execute("gate", None).await else {
+            panic!("a second call ran past the shared limit");
+        };
+        assert!(matches!(
+            error.downcast_ref::<LimitExceeded>(),
+            Some(LimitExceeded::GlobalConcurrency { limit: 1 })
+        ));
+
+        gate.0.add_permits(1);
+        assert!(running.await.unwrap());
     }
 }
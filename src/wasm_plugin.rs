@@ -0,0 +1,110 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Result};
+use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+use wasmtime_wasi::{
+    I32Exit, WasiCtxBuilder,
+    pipe::{MemoryInputPipe, MemoryOutputPipe},
+    preview1::{self, WasiP1Ctx},
+};
+
+/// Most a module may write to stdout or stderr during one call
+const MAX_OUTPUT_BYTES: usize = 16 * 1024 * 1024;
+
+/// Most linear memory a module may grow to during one call
+const MAX_MEMORY_BYTES: usize = 256 * 1024 * 1024;
+
+/// Fuel a module burns between yields to the runtime, so a call that times out
+/// stops soon after instead of running on in the background
+const FUEL_PER_YIELD: u64 = 100_000;
+
+/// What a run of the module left behind
+pub struct WasmOutput {
+    pub exit_code: i32,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+struct CallState {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+}
+
+/// A WASI command module, compiled once and instantiated afresh for every call.
+/// It sees its arguments, environment, stdin and stdout, and no files or network.
+pub struct WasmModule {
+    engine: Engine,
+    module: Module,
+    linker: Linker<CallState>,
+}
+
+impl WasmModule {
+    /// Compile the module at `path`, either binary or text format
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut config = Config::new();
+        config.async_support(true).consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module = Module::from_file(&engine, path)
+            .with_context(|| format!("Failed to load WASM module {}", path.display()))?;
+        let mut linker = Linker::new(&engine);
+        preview1::add_to_linker_async(&mut linker, |state: &mut CallState| &mut state.wasi)?;
+
+        Ok(WasmModule {
+            engine,
+            module,
+            linker,
+        })
+    }
+
+    /// Run the module's `_start` with `input` on stdin; `args` follow `name` as argv
+    pub async fn run(
+        &self,
+        name: &str,
+        args: &[String],
+        env: &HashMap<String, String>,
+        input: Vec<u8>,
+    ) -> Result<WasmOutput> {
+        let stdout = MemoryOutputPipe::new(MAX_OUTPUT_BYTES);
+        let stderr = MemoryOutputPipe::new(MAX_OUTPUT_BYTES);
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.stdin(MemoryInputPipe::new(input))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .args(&[name])
+            .args(args);
+        for (key, value) in env {
+            wasi.env(key, value);
+        }
+
+        let state = CallState {
+            wasi: wasi.build_p1(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(MAX_MEMORY_BYTES)
+                .build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(u64::MAX)?;
+        store.fuel_async_yield_interval(Some(FUEL_PER_YIELD))?;
+
+        let instance = self
+            .linker
+            .instantiate_async(&mut store, &self.module)
+            .await?;
+        let start = instance.get_typed_func::<(), ()>(&mut store, "_start")?;
+        let exit_code = match start.call_async(&mut store, ()).await {
+            Ok(()) => 0,
+            Err(e) => match e.downcast_ref::<I32Exit>() {
+                Some(exit) => exit.0,
+                None => return Err(e),
+            },
+        };
+        drop(store);
+
+        Ok(WasmOutput {
+            exit_code,
+            stdout: stdout.contents().to_vec(),
+            stderr: stderr.contents().to_vec(),
+        })
+    }
+}